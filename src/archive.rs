@@ -0,0 +1,253 @@
+use crate::error::{HermesError, Result};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use tar::{Archive, Builder, EntryType};
+
+/// What went into or came out of an archive
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveStats {
+    pub files: usize,
+    pub dirs: usize,
+    pub bytes: u64,
+    /// Symlinks and special files, which are left out of archives
+    pub skipped: usize,
+}
+
+/// Total size of the regular files under `dir`
+pub fn content_size(dir: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            total += content_size(&entry.path())?;
+        } else if file_type.is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+/// Write `dir` as a tar stream with paths relative to it, keeping modes and mtimes.
+/// Symlinks are skipped so the archive never points outside what was sent.
+pub fn write_archive<W: Write>(dir: &Path, writer: W) -> Result<(W, ArchiveStats)> {
+    let mut builder = Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    let mut stats = ArchiveStats::default();
+    append_dir(&mut builder, dir, Path::new(""), &mut stats)?;
+
+    let writer = builder.into_inner()?;
+    Ok((writer, stats))
+}
+
+fn append_dir<W: Write>(
+    builder: &mut Builder<W>,
+    dir: &Path,
+    relative: &Path,
+    stats: &mut ArchiveStats,
+) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = relative.join(entry.file_name());
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            builder.append_dir(&name, &path)?;
+            stats.dirs += 1;
+            append_dir(builder, &path, &name, stats)?;
+        } else if file_type.is_file() {
+            builder.append_path_with_name(&path, &name)?;
+            stats.files += 1;
+            stats.bytes += entry.metadata()?.len();
+        } else {
+            stats.skipped += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Extract a tar stream into `dest`. Only regular files and directories are
+/// accepted; absolute paths, `..` components, links and existing files are refused.
+pub fn extract_archive<R: Read>(reader: R, dest: &Path) -> Result<ArchiveStats> {
+    fs::create_dir_all(dest)?;
+
+    let mut archive = Archive::new(reader);
+    archive.set_preserve_permissions(false);
+    archive.set_preserve_mtime(true);
+    archive.set_overwrite(false);
+
+    let mut stats = ArchiveStats::default();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let relative = safe_relative_path(&path)?;
+
+        match entry.header().entry_type() {
+            EntryType::Directory => stats.dirs += 1,
+            EntryType::Regular | EntryType::Continuous => {
+                stats.files += 1;
+                stats.bytes += entry.size();
+            }
+            other => {
+                return Err(HermesError::ConfigError(format!(
+                    "Refusing to extract {} ({other:?} entries are not allowed)",
+                    relative.display()
+                )));
+            }
+        }
+
+        // unpack_in also checks the resolved parent stays inside `dest`
+        if !entry.unpack_in(dest).map_err(|e| {
+            HermesError::ConfigError(format!("Cannot extract {}: {e}", relative.display()))
+        })? {
+            return Err(HermesError::ConfigError(format!(
+                "Refusing to extract {} outside the output directory",
+                relative.display()
+            )));
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Reject paths that could land outside the extraction directory
+fn safe_relative_path(path: &Path) -> Result<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(HermesError::ConfigError(format!(
+                    "Refusing to extract unsafe path: {}",
+                    path.display()
+                )));
+            }
+        }
+    }
+
+    if relative.as_os_str().is_empty() {
+        return Err(HermesError::ConfigError(
+            "Refusing to extract an entry with an empty path".to_string(),
+        ));
+    }
+
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use tar::Header;
+
+    /// A tar with one entry whose name is written raw, bypassing the builder's checks
+    fn raw_tar(name: &str, entry_type: EntryType, data: &[u8]) -> Vec<u8> {
+        let mut header = Header::new_gnu();
+        let field = &mut header.as_old_mut().name;
+        field[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(entry_type);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        if entry_type == EntryType::Symlink {
+            header.set_link_name("/etc").unwrap();
+        }
+        header.set_cksum();
+
+        let mut builder = Builder::new(Vec::new());
+        builder.append(&header, data).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip_keeps_structure_and_mtimes() {
+        let src = tempfile::tempdir().unwrap();
+        fs::create_dir_all(src.path().join("docs/nested")).unwrap();
+        fs::write(src.path().join("top.txt"), b"top").unwrap();
+        fs::write(src.path().join("docs/nested/deep.txt"), b"deep").unwrap();
+
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let file = fs::File::options()
+            .write(true)
+            .open(src.path().join("top.txt"))
+            .unwrap();
+        file.set_modified(mtime).unwrap();
+        drop(file);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = src.path().join("docs/run.sh");
+            fs::write(&script, b"#!/bin/sh\n").unwrap();
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).unwrap();
+            std::os::unix::fs::symlink("/etc/passwd", src.path().join("link")).unwrap();
+        }
+
+        let (tar, sent) = write_archive(src.path(), Vec::new()).unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let out = dest.path().join("out");
+        let received = extract_archive(&tar[..], &out).unwrap();
+
+        assert_eq!(sent.files, received.files);
+        assert_eq!(sent.dirs, 2);
+        assert_eq!(fs::read(out.join("docs/nested/deep.txt")).unwrap(), b"deep");
+        assert_eq!(
+            fs::metadata(out.join("top.txt"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            mtime
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(out.join("docs/run.sh"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o750);
+            assert_eq!(sent.skipped, 1);
+            assert!(!out.join("link").exists());
+        }
+    }
+
+    #[test]
+    fn test_rejects_traversal_and_links() {
+        let dest = tempfile::tempdir().unwrap();
+        let out = dest.path().join("out");
+
+        for (name, entry_type) in [
+            ("../escape.txt", EntryType::Regular),
+            ("/tmp/absolute.txt", EntryType::Regular),
+            ("a/../../escape.txt", EntryType::Regular),
+            ("link", EntryType::Symlink),
+        ] {
+            let tar = raw_tar(name, entry_type, b"x");
+            assert!(
+                extract_archive(&tar[..], &out).is_err(),
+                "{name} was extracted"
+            );
+        }
+
+        assert!(!dest.path().join("escape.txt").exists());
+        assert!(!out.join("link").exists());
+    }
+
+    #[test]
+    fn test_does_not_overwrite_existing_files() {
+        let dest = tempfile::tempdir().unwrap();
+        fs::write(dest.path().join("file.txt"), b"mine").unwrap();
+
+        let tar = raw_tar("file.txt", EntryType::Regular, b"theirs");
+        assert!(extract_archive(&tar[..], dest.path()).is_err());
+        assert_eq!(fs::read(dest.path().join("file.txt")).unwrap(), b"mine");
+    }
+}
@@ -1,17 +1,35 @@
+use crate::crypto;
 use crate::error::{HermesError, Result};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::Mutex;
 
-const CHUNK_SIZE: usize = 50 * 1024 * 1024;
+/// Content-defined chunk bounds: boundaries follow the data, so an edit only
+/// changes the chunks around it and the rest can be reused
+pub const CHUNKER: Chunker = Chunker {
+    min_size: 8 * 1024 * 1024,
+    avg_bits: 24,
+    max_size: 64 * 1024 * 1024,
+};
+
+/// Domain separator of the chunk binding in each chunk's associated data
+const CHUNK_CONTEXT: &[u8] = b"hermes-chunk-v1";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChunkManifest {
+    /// Random ID binding the chunks to this manifest; empty for legacy manifests
+    #[serde(default)]
+    pub manifest_id: String,
     pub original_filename: String,
     pub total_size: u64,
+    /// Fixed chunk size of legacy manifests; chunks are content-defined now
+    #[serde(default, skip_serializing)]
     pub chunk_size: usize,
+    /// Set by [`plan_chunks`] once the chunk boundaries are known
     pub total_chunks: usize,
     pub file_hash: String,
     pub chunks: Vec<ChunkInfo>,
@@ -23,18 +41,31 @@ pub struct ChunkInfo {
     pub size: u64,
     pub hash: String,
     pub encrypted_path: String,
+    /// Set when the chunk was reused from an earlier upload and is bound to that one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ChunkOrigin>,
+}
+
+/// Manifest and position a chunk was originally encrypted for
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChunkOrigin {
+    pub manifest_id: String,
+    pub index: usize,
+    pub total_chunks: usize,
 }
 
 impl ChunkManifest {
     #[must_use]
     pub fn new(filename: String, total_size: u64) -> Self {
-        let total_chunks = ((total_size as f64) / (CHUNK_SIZE as f64)).ceil() as usize;
+        let mut id = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut id);
 
         Self {
+            manifest_id: hex::encode(id),
             original_filename: filename,
             total_size,
-            chunk_size: CHUNK_SIZE,
-            total_chunks,
+            chunk_size: 0,
+            total_chunks: 0,
             file_hash: String::new(),
             chunks: Vec::new(),
         }
@@ -56,20 +87,104 @@ impl ChunkManifest {
         serde_json::from_str(json).map_err(HermesError::SerializationError)
     }
 
-    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+    /// Encrypt the manifest for a password or recipients. The header carries no
+    /// filename, so only the holders of the key learn what the chunks contain.
+    pub fn encrypt(
+        &self,
+        password: Option<&str>,
+        recipients: Option<Vec<String>>,
+        ttl_hours: Option<u64>,
+    ) -> Result<Vec<u8>> {
         let json = self.to_json()?;
-        let mut file = File::create(path)?;
-        file.write_all(json.as_bytes())?;
-        Ok(())
+        crypto::encrypt::encrypt_data_multi(
+            json.as_bytes(),
+            password,
+            None,
+            ttl_hours,
+            recipients,
+            false,
+        )
     }
 
-    pub fn load_from_file(path: &Path) -> Result<Self> {
-        let mut file = File::open(path)?;
-        let mut json = String::new();
-        file.read_to_string(&mut json)?;
+    pub fn decrypt(
+        encrypted: &[u8],
+        password: Option<&str>,
+        recipient_name: Option<&str>,
+    ) -> Result<Self> {
+        let json = match (recipient_name, password) {
+            (Some(name), _) => crypto::decrypt::decrypt_data_multi(encrypted, name)?,
+            (None, Some(pwd)) => crypto::decrypt_data(encrypted, pwd)?,
+            (None, None) => return Err(missing_key()),
+        };
+
+        let json = String::from_utf8(json).map_err(|_| HermesError::DecryptionFailed)?;
         Self::from_json(&json)
     }
 
+    /// Where `chunk` was encrypted: its origin if reused, otherwise this manifest
+    #[must_use]
+    pub fn origin_of(&self, chunk: &ChunkInfo) -> ChunkOrigin {
+        chunk.origin.clone().unwrap_or_else(|| ChunkOrigin {
+            manifest_id: self.manifest_id.clone(),
+            index: chunk.index,
+            total_chunks: self.total_chunks,
+        })
+    }
+
+    /// Associated data tying a chunk to the manifest and position it was encrypted
+    /// for, so chunks can't be swapped, reordered or moved between manifests
+    #[must_use]
+    pub fn chunk_context(&self, chunk: &ChunkInfo) -> Vec<u8> {
+        let origin = self.origin_of(chunk);
+        if origin.manifest_id.is_empty() {
+            return Vec::new();
+        }
+
+        let mut context = CHUNK_CONTEXT.to_vec();
+        context.extend_from_slice(origin.manifest_id.as_bytes());
+        context.extend_from_slice(&(origin.index as u64).to_le_bytes());
+        context.extend_from_slice(&(origin.total_chunks as u64).to_le_bytes());
+        context
+    }
+
+    pub fn encrypt_chunk(
+        &self,
+        chunk: &ChunkInfo,
+        data: &[u8],
+        password: Option<&str>,
+        recipients: Option<Vec<String>>,
+        ttl_hours: Option<u64>,
+    ) -> Result<Vec<u8>> {
+        if password.is_none() && recipients.is_none() {
+            return Err(missing_key());
+        }
+
+        crypto::encrypt::encrypt_data_bound(
+            data,
+            password,
+            None,
+            ttl_hours,
+            recipients,
+            false,
+            &self.chunk_context(chunk),
+        )
+    }
+
+    pub fn decrypt_chunk(
+        &self,
+        chunk: &ChunkInfo,
+        encrypted: &[u8],
+        password: Option<&str>,
+        recipient_name: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let context = self.chunk_context(chunk);
+        match (recipient_name, password) {
+            (Some(name), _) => crypto::decrypt::decrypt_data_multi_bound(encrypted, name, &context),
+            (None, Some(pwd)) => crypto::decrypt::decrypt_data_bound(encrypted, pwd, &context),
+            (None, None) => Err(missing_key()),
+        }
+    }
+
     pub fn verify_integrity(&self) -> Result<()> {
         if self.chunks.len() != self.total_chunks {
             return Err(HermesError::ConfigError(format!(
@@ -92,106 +207,182 @@ impl ChunkManifest {
     }
 }
 
-pub fn split_file_into_chunks<P: AsRef<Path>>(
-    file_path: P,
-    output_dir: P,
-) -> Result<ChunkManifest> {
-    let file_path = file_path.as_ref();
-    let output_dir = output_dir.as_ref();
-
-    let filename = file_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| HermesError::FileNotFound("Invalid filename".to_string()))?
-        .to_string();
-
-    let mut file = File::open(file_path)?;
-    let file_size = file.metadata()?.len();
+fn missing_key() -> HermesError {
+    HermesError::ConfigError("Password or recipient name required".to_string())
+}
 
-    let mut manifest = ChunkManifest::new(filename.clone(), file_size);
-    let mut file_hasher = Sha256::new();
+/// Gear table for the rolling hash, fixed so boundaries are stable across runs
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut i = 0;
+    while i < 256 {
+        // splitmix64
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Content-defined chunking with a gear rolling hash
+#[derive(Debug, Clone, Copy)]
+pub struct Chunker {
+    pub min_size: usize,
+    /// A boundary is cut when this many top bits of the hash are zero (average 2^bits)
+    pub avg_bits: u32,
+    pub max_size: usize,
+}
 
-    let mut chunk_index = 0;
-    let mut buffer = vec![0u8; CHUNK_SIZE];
+impl Chunker {
+    /// Split `reader` into chunks, returning each chunk's size and SHA-256 and
+    /// the SHA-256 of the whole input
+    pub fn split<R: Read>(&self, mut reader: R) -> Result<(Vec<(u64, String)>, String)> {
+        let mask = !(u64::MAX >> self.avg_bits);
+        let mut chunks = Vec::new();
+        let mut file_hasher = Sha256::new();
+        let mut chunk_hasher = Sha256::new();
+        let mut chunk_len = 0usize;
+        let mut hash = 0u64;
+
+        let mut buffer = vec![0u8; 1024 * 1024];
+        loop {
+            let n = reader.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            let data = &buffer[..n];
+            file_hasher.update(data);
+
+            let mut start = 0;
+            for (i, &byte) in data.iter().enumerate() {
+                chunk_len += 1;
+                hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+
+                let cut =
+                    chunk_len >= self.max_size || (chunk_len >= self.min_size && hash & mask == 0);
+                if cut {
+                    chunk_hasher.update(&data[start..=i]);
+                    let digest = std::mem::take(&mut chunk_hasher).finalize();
+                    chunks.push((chunk_len as u64, format!("{digest:x}")));
+                    start = i + 1;
+                    chunk_len = 0;
+                    hash = 0;
+                }
+            }
+            chunk_hasher.update(&data[start..]);
+        }
 
-    loop {
-        let bytes_read = file.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+        if chunk_len > 0 {
+            chunks.push((chunk_len as u64, format!("{:x}", chunk_hasher.finalize())));
         }
 
-        file_hasher.update(&buffer[..bytes_read]);
+        Ok((chunks, format!("{:x}", file_hasher.finalize())))
+    }
+}
 
-        let mut chunk_hasher = Sha256::new();
-        chunk_hasher.update(&buffer[..bytes_read]);
-        let chunk_hash = format!("{:x}", chunk_hasher.finalize());
+/// Hash a file chunk by chunk without writing anything to disk. Chunks are read
+/// again from the source with [`read_chunk`] when they are encrypted.
+pub fn plan_chunks<P: AsRef<Path>>(file_path: P) -> Result<ChunkManifest> {
+    plan_chunks_with(file_path, &CHUNKER)
+}
 
-        let chunk_filename = format!("{}.chunk.{:03}", filename, chunk_index + 1);
-        let chunk_path = output_dir.join(&chunk_filename);
+pub fn plan_chunks_with<P: AsRef<Path>>(file_path: P, chunker: &Chunker) -> Result<ChunkManifest> {
+    let file_path = file_path.as_ref();
 
-        let mut chunk_file = File::create(&chunk_path)?;
-        chunk_file.write_all(&buffer[..bytes_read])?;
+    let filename = file_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| HermesError::FileNotFound("Invalid filename".to_string()))?
+        .to_string();
 
-        let chunk_info = ChunkInfo {
-            index: chunk_index,
-            size: bytes_read as u64,
-            hash: chunk_hash,
-            encrypted_path: chunk_filename,
-        };
+    let file = File::open(file_path)?;
+    let file_size = file.metadata()?.len();
 
-        manifest.add_chunk(chunk_info);
-        chunk_index += 1;
+    let mut manifest = ChunkManifest::new(filename, file_size);
+    let (chunks, file_hash) = chunker.split(file)?;
+
+    for (index, (size, hash)) in chunks.into_iter().enumerate() {
+        manifest.add_chunk(ChunkInfo {
+            index,
+            size,
+            hash,
+            encrypted_path: String::new(),
+            origin: None,
+        });
     }
-
-    let file_hash = format!("{:x}", file_hasher.finalize());
+    manifest.total_chunks = manifest.chunks.len();
     manifest.set_file_hash(file_hash);
 
     Ok(manifest)
 }
 
-pub fn reassemble_chunks_from_manifest<P: AsRef<Path>>(
+/// Byte offset of a chunk in the original file
+fn chunk_offset(manifest: &ChunkManifest, chunk: &ChunkInfo) -> u64 {
+    manifest.chunks[..chunk.index].iter().map(|c| c.size).sum()
+}
+
+/// Read one chunk from the source file, failing if the file changed since planning
+pub fn read_chunk(
+    file_path: &Path,
     manifest: &ChunkManifest,
-    chunks_dir: P,
-    output_path: P,
-) -> Result<()> {
-    let chunks_dir = chunks_dir.as_ref();
-    let output_path = output_path.as_ref();
+    chunk: &ChunkInfo,
+) -> Result<Vec<u8>> {
+    let mut file = File::open(file_path)?;
+    file.seek(SeekFrom::Start(chunk_offset(manifest, chunk)))?;
 
-    manifest.verify_integrity()?;
+    let mut data = Vec::with_capacity(chunk.size as usize);
+    file.take(chunk.size).read_to_end(&mut data)?;
 
-    let mut output_file = File::create(output_path)?;
-    let mut file_hasher = Sha256::new();
+    if format!("{:x}", Sha256::digest(&data)) != chunk.hash {
+        return Err(HermesError::ConfigError(format!(
+            "{} changed while it was being sent (chunk {})",
+            file_path.display(),
+            chunk.index
+        )));
+    }
 
-    for chunk in &manifest.chunks {
-        let chunk_path = chunks_dir.join(&chunk.encrypted_path);
+    Ok(data)
+}
 
-        if !chunk_path.exists() {
-            return Err(HermesError::FileNotFound(format!(
-                "Chunk file not found: {}",
-                chunk.encrypted_path
-            )));
-        }
+/// Check a decrypted chunk against the manifest and write it at its position in `output`
+pub fn write_chunk(
+    output: &Mutex<File>,
+    manifest: &ChunkManifest,
+    chunk: &ChunkInfo,
+    data: &[u8],
+) -> Result<()> {
+    let calculated_hash = format!("{:x}", Sha256::digest(data));
+    if calculated_hash != chunk.hash {
+        return Err(HermesError::ConfigError(format!(
+            "Chunk {} hash mismatch: expected {}, got {}",
+            chunk.index, chunk.hash, calculated_hash
+        )));
+    }
 
-        let mut chunk_file = File::open(&chunk_path)?;
-        let mut chunk_data = Vec::new();
-        chunk_file.read_to_end(&mut chunk_data)?;
+    let mut file = output.lock().unwrap_or_else(|e| e.into_inner());
+    file.seek(SeekFrom::Start(chunk_offset(manifest, chunk)))?;
+    file.write_all(data)?;
 
-        let mut chunk_hasher = Sha256::new();
-        chunk_hasher.update(&chunk_data);
-        let calculated_hash = format!("{:x}", chunk_hasher.finalize());
+    Ok(())
+}
 
-        if calculated_hash != chunk.hash {
-            return Err(HermesError::ConfigError(format!(
-                "Chunk {} hash mismatch: expected {}, got {}",
-                chunk.index, chunk.hash, calculated_hash
-            )));
-        }
+/// Check a reassembled file against the manifest's size and hash
+pub fn verify_reassembled<P: AsRef<Path>>(manifest: &ChunkManifest, path: P) -> Result<()> {
+    let path = path.as_ref();
 
-        file_hasher.update(&chunk_data);
-        output_file.write_all(&chunk_data)?;
+    let size = path.metadata()?.len();
+    if size != manifest.total_size {
+        return Err(HermesError::ConfigError(format!(
+            "File size mismatch: expected {}, got {}",
+            manifest.total_size, size
+        )));
     }
 
-    let calculated_file_hash = format!("{:x}", file_hasher.finalize());
+    let calculated_file_hash = calculate_file_hash(path)?;
     if calculated_file_hash != manifest.file_hash {
         return Err(HermesError::ConfigError(format!(
             "File hash mismatch: expected {}, got {}",
@@ -217,3 +408,171 @@ pub fn calculate_file_hash<P: AsRef<Path>>(path: P) -> Result<String> {
 
     Ok(format!("{:x}", hasher.finalize()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL: Chunker = Chunker {
+        min_size: 1024,
+        avg_bits: 12,
+        max_size: 16 * 1024,
+    };
+
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    fn chunk(index: usize) -> ChunkInfo {
+        ChunkInfo {
+            index,
+            size: 0,
+            hash: String::new(),
+            encrypted_path: String::new(),
+            origin: None,
+        }
+    }
+
+    fn manifest(chunks: usize) -> ChunkManifest {
+        let mut manifest = ChunkManifest::new("report.pdf".to_string(), 10);
+        manifest.total_chunks = chunks;
+        manifest
+    }
+
+    #[test]
+    fn test_manifest_roundtrip_hides_metadata() {
+        let manifest = manifest(2);
+        let encrypted = manifest.encrypt(Some("pw"), None, None).unwrap();
+
+        let package = crypto::EncryptedPackage::from_bytes(&encrypted).unwrap();
+        assert!(package.filename.is_none());
+        assert!(!encrypted.windows(10).any(|w| w == b"report.pdf"));
+
+        let opened = ChunkManifest::decrypt(&encrypted, Some("pw"), None).unwrap();
+        assert_eq!(opened.manifest_id, manifest.manifest_id);
+        assert_eq!(opened.original_filename, "report.pdf");
+    }
+
+    #[test]
+    fn test_chunks_are_bound_to_manifest_and_index() {
+        let manifest = manifest(2);
+        let first = manifest
+            .encrypt_chunk(&chunk(0), b"first", Some("pw"), None, None)
+            .unwrap();
+
+        assert_eq!(
+            manifest
+                .decrypt_chunk(&chunk(0), &first, Some("pw"), None)
+                .unwrap(),
+            b"first"
+        );
+
+        // Swapped into another position
+        assert!(manifest
+            .decrypt_chunk(&chunk(1), &first, Some("pw"), None)
+            .is_err());
+
+        // Moved into another upload of the same file
+        let mut other = ChunkManifest::new("report.pdf".to_string(), 10);
+        other.total_chunks = 2;
+        assert!(other
+            .decrypt_chunk(&chunk(0), &first, Some("pw"), None)
+            .is_err());
+
+        // Unless that upload's manifest records where the chunk came from
+        let mut reused = chunk(1);
+        reused.origin = Some(manifest.origin_of(&chunk(0)));
+        other.chunks = vec![chunk(0), reused.clone()];
+        assert_eq!(
+            other
+                .decrypt_chunk(&reused, &first, Some("pw"), None)
+                .unwrap(),
+            b"first"
+        );
+    }
+
+    #[test]
+    fn test_boundaries_follow_content() {
+        let original = noise(200 * 1024, 7);
+        let mut edited = original.clone();
+        edited.splice(100_000..100_000, b"inserted bytes".iter().copied());
+
+        let (before, _) = SMALL.split(&original[..]).unwrap();
+        let (after, _) = SMALL.split(&edited[..]).unwrap();
+
+        assert!(before.iter().all(|(size, _)| *size <= 16 * 1024));
+        assert_eq!(
+            before.iter().map(|(size, _)| size).sum::<u64>(),
+            original.len() as u64
+        );
+
+        // Only the chunks around the edit change
+        let known: std::collections::HashSet<_> = before.iter().map(|(_, hash)| hash).collect();
+        let changed = after
+            .iter()
+            .filter(|(_, hash)| !known.contains(hash))
+            .count();
+        assert!(changed <= 2, "{changed} of {} chunks changed", after.len());
+    }
+
+    #[test]
+    fn test_manifest_drops_legacy_chunk_size() {
+        let manifest = ChunkManifest::new("a.bin".to_string(), 1 << 30);
+        assert_eq!(manifest.total_chunks, 0);
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert!(json.get("chunk_size").is_none());
+
+        let legacy = r#"{"original_filename":"a.bin","total_size":10,"chunk_size":4,
+            "total_chunks":3,"file_hash":"","chunks":[]}"#;
+        let manifest: ChunkManifest = serde_json::from_str(legacy).unwrap();
+        assert_eq!(manifest.chunk_size, 4);
+        assert_eq!(manifest.total_chunks, 3);
+    }
+
+    #[test]
+    fn test_chunks_roundtrip_without_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.bin");
+        let data = noise(100 * 1024, 3);
+        std::fs::write(&source, &data).unwrap();
+
+        let manifest = plan_chunks_with(&source, &SMALL).unwrap();
+        assert!(manifest.chunks.len() > 1);
+        assert_eq!(manifest.total_chunks, manifest.chunks.len());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // Written out of order, as parallel workers would
+        let output_path = dir.path().join("output.bin");
+        let output = Mutex::new(File::create(&output_path).unwrap());
+        for chunk in manifest.chunks.iter().rev() {
+            let chunk_data = read_chunk(&source, &manifest, chunk).unwrap();
+            write_chunk(&output, &manifest, chunk, &chunk_data).unwrap();
+        }
+        drop(output);
+
+        verify_reassembled(&manifest, &output_path).unwrap();
+        assert_eq!(std::fs::read(&output_path).unwrap(), data);
+
+        // A chunk that doesn't match the manifest is refused
+        let output = Mutex::new(File::create(&output_path).unwrap());
+        assert!(write_chunk(&output, &manifest, &manifest.chunks[0], b"forged").is_err());
+    }
+
+    #[test]
+    fn test_tampered_manifest_is_rejected() {
+        let mut encrypted = manifest(1).encrypt(Some("pw"), None, None).unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+
+        assert!(ChunkManifest::decrypt(&encrypted, Some("pw"), None).is_err());
+    }
+}
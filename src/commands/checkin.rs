@@ -20,6 +20,14 @@ pub fn execute(file_path: &str) -> Result<()> {
         return Ok(());
     }
 
+    if let Some(fired_at) = switch.fired_at {
+        return Err(HermesError::ConfigError(format!(
+            "DMS already fired at {} for file: {}",
+            fired_at.format("%Y-%m-%d %H:%M UTC"),
+            file_path
+        )));
+    }
+
     let timeout = switch.timeout_hours;
 
     switch.checkin();
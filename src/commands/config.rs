@@ -1,4 +1,4 @@
-use crate::config::Settings;
+use crate::config::{ConfigFile, Settings};
 use crate::error::Result;
 use crate::ui;
 
@@ -8,6 +8,9 @@ pub fn execute() -> Result<()> {
     println!();
     ui::print_box_start("CONFIGURATION");
 
+    ui::print_box_line(&format!("Profile: {}", config.profile));
+    ui::print_box_line("");
+
     ui::print_box_line("SFTP Settings:");
     ui::print_box_line(&format!("  Host: {}", config.sftp.host));
     ui::print_box_line(&format!("  Port: {}", config.sftp.port));
@@ -16,6 +19,9 @@ pub fn execute() -> Result<()> {
         "  Key File: {}",
         config.sftp.key_file.unwrap_or_else(|| "None".to_string())
     ));
+    ui::print_box_line(&format!("  Host Keys: {:?}", config.sftp.host_key_policy));
+    let auth_methods: Vec<&str> = config.sftp.auth_methods.iter().map(|m| m.name()).collect();
+    ui::print_box_line(&format!("  Auth: {}", auth_methods.join(", ")));
     ui::print_box_line("");
 
     ui::print_box_line("Vault Paths:");
@@ -29,3 +35,43 @@ pub fn execute() -> Result<()> {
 
     Ok(())
 }
+
+/// Make `name` the profile used when `--profile` is not given
+pub fn use_profile(name: &str) -> Result<()> {
+    let mut file = ConfigFile::load()?;
+
+    // Fails with the list of available profiles if the name is unknown
+    file.profile(name)?;
+    file.default_profile = Some(name.to_string());
+    file.save()?;
+
+    println!();
+    ui::print_success("DEFAULT PROFILE CHANGED");
+    ui::print_info("Profile", name);
+    println!();
+
+    Ok(())
+}
+
+pub fn list_profiles() -> Result<()> {
+    let file = ConfigFile::load()?;
+    let default = file.default_profile_name();
+
+    println!();
+    ui::print_box_start("PROFILES");
+
+    for name in file.profile_names() {
+        let profile = file.profile(&name)?;
+        let marker = if name == default { "*" } else { " " };
+        ui::print_box_line(&format!(
+            "{marker} {name}  ({:?}, {}@{})",
+            profile.transport, profile.sftp.username, profile.sftp.host
+        ));
+    }
+
+    ui::print_box_line("");
+    ui::print_box_end();
+    println!();
+
+    Ok(())
+}
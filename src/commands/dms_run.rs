@@ -0,0 +1,304 @@
+use crate::config::Settings;
+use crate::dms::{format_duration, get_registry_path, DeadManSwitch, DmsAction, DmsRegistry};
+use crate::error::{HermesError, Result};
+use crate::notify::{self, Notification, NotificationKind};
+use crate::transfer::{self, Transport};
+use crate::ui;
+use chrono::Local;
+use std::fs;
+use std::time::Duration;
+
+/// What the watcher did for one switch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DmsEvent {
+    Warned {
+        file_path: String,
+    },
+    Deleted {
+        file_path: String,
+    },
+    Released {
+        file_path: String,
+        envelope: String,
+    },
+    Notified {
+        file_path: String,
+    },
+    Failed {
+        file_path: String,
+        error: String,
+    },
+    /// A warning or fired notification could not be delivered
+    NotifyFailed {
+        file_path: String,
+        error: String,
+    },
+}
+
+pub fn execute(daemon: bool, interval_minutes: u64) -> Result<()> {
+    if !daemon {
+        return run_pass();
+    }
+
+    if interval_minutes == 0 {
+        return Err(HermesError::ConfigError(
+            "DMS interval must be at least 1 minute".to_string(),
+        ));
+    }
+
+    println!("DMS watcher running every {interval_minutes} minute(s). Press Ctrl+C to stop.");
+
+    loop {
+        // Keep watching even if the server is unreachable for a pass
+        if let Err(e) = run_pass() {
+            ui::print_error(&format!("DMS run failed: {e}"));
+        }
+        std::thread::sleep(Duration::from_secs(interval_minutes * 60));
+    }
+}
+
+fn run_pass() -> Result<()> {
+    ui::print_box_start("DMS_RUN");
+
+    // Reload every pass so check-ins from other processes are seen
+    let registry_path = get_registry_path()?;
+    let mut registry = DmsRegistry::load_from_file(&registry_path)?;
+
+    let pending: Vec<(String, String)> = registry
+        .get_expired()
+        .into_iter()
+        .chain(registry.get_warning_needed())
+        .map(|s| (s.profile.clone(), s.file_path.clone()))
+        .collect();
+    if pending.is_empty() {
+        ui::print_box_line("   Nothing to do");
+        ui::print_box_end();
+        return Ok(());
+    }
+
+    let mut profiles: Vec<&str> = pending
+        .iter()
+        .map(|(profile, _)| profile.as_str())
+        .collect();
+    profiles.sort_unstable();
+    profiles.dedup();
+
+    // Each switch acts on the vault of the profile it was armed on
+    let mut events = Vec::new();
+    for profile in profiles {
+        let pass = Settings::load_profile(profile).and_then(|config| {
+            let client = transfer::connect(&config)?;
+            Ok(run_switches(&mut registry, client.as_ref(), &config))
+        });
+        match pass {
+            Ok(profile_events) => events.extend(profile_events),
+            Err(e) => events.extend(pending.iter().filter(|(p, _)| p == profile).map(
+                |(_, file_path)| DmsEvent::Failed {
+                    file_path: file_path.clone(),
+                    error: format!("profile '{profile}': {e}"),
+                },
+            )),
+        }
+    }
+    registry.save_to_file(&registry_path)?;
+
+    let mut fired = 0;
+    let mut warned = 0;
+    let mut failed = 0;
+
+    for event in &events {
+        match event {
+            DmsEvent::Warned { file_path } => {
+                warned += 1;
+                ui::print_box_line(&format!("   ⚠ Check-in overdue soon: {file_path}"));
+            }
+            DmsEvent::Deleted { file_path } => {
+                fired += 1;
+                ui::print_box_line(&format!("   ✗ Deleted: {file_path}"));
+            }
+            DmsEvent::Released {
+                file_path,
+                envelope,
+            } => {
+                fired += 1;
+                ui::print_box_line(&format!("   ➜ Released: {file_path}"));
+                ui::print_box_line(&format!("     Envelope: {envelope}"));
+            }
+            DmsEvent::Notified { file_path } => {
+                fired += 1;
+                ui::print_box_line(&format!("   ✉ Expired (notify only): {file_path}"));
+            }
+            DmsEvent::Failed { file_path, error } => {
+                failed += 1;
+                ui::print_box_line(&format!("   ! Failed: {file_path} ({error})"));
+            }
+            DmsEvent::NotifyFailed { file_path, error } => {
+                ui::print_box_line(&format!("   ! Notification failed: {file_path} ({error})"));
+            }
+        }
+    }
+
+    ui::print_box_line("");
+    ui::print_box_end();
+
+    println!();
+    ui::print_success("DMS RUN COMPLETE");
+    ui::print_info("Fired", &fired.to_string());
+    ui::print_info("Warnings", &warned.to_string());
+    if failed > 0 {
+        ui::print_info("Failed (will retry)", &failed.to_string());
+    }
+    ui::print_info(
+        "Time",
+        &Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    );
+    println!();
+
+    Ok(())
+}
+
+/// Warn for switches of `config`'s profile in their grace period and fire expired ones;
+/// switches armed on other profiles are left alone.
+/// Release envelopes are published to the configured inbox.
+/// Outcomes are recorded on each switch; the caller saves the registry.
+pub fn run_switches(
+    registry: &mut DmsRegistry,
+    client: &dyn Transport,
+    config: &Settings,
+) -> Vec<DmsEvent> {
+    let mut events = Vec::new();
+
+    let warning: Vec<String> = registry
+        .get_warning_needed()
+        .iter()
+        .filter(|s| s.profile == config.profile)
+        .map(|s| s.file_path.clone())
+        .collect();
+
+    for file_path in warning {
+        if let Some(switch) = registry.get_mut(&file_path) {
+            // Leave the switch unwarned so delivery is retried next pass
+            if let Err(e) = send_notification(switch, config, NotificationKind::Warning) {
+                switch.last_error = Some(e.to_string());
+                events.push(DmsEvent::NotifyFailed {
+                    file_path,
+                    error: e.to_string(),
+                });
+                continue;
+            }
+
+            switch.mark_warned();
+            events.push(DmsEvent::Warned { file_path });
+        }
+    }
+
+    let expired: Vec<String> = registry
+        .get_expired()
+        .iter()
+        .filter(|s| s.profile == config.profile)
+        .map(|s| s.file_path.clone())
+        .collect();
+
+    for file_path in expired {
+        let Some(switch) = registry.get_mut(&file_path) else {
+            continue;
+        };
+
+        match fire(&switch.action, &file_path, client, &config.paths.inbox) {
+            Ok(event) => {
+                switch.mark_fired();
+                events.push(event);
+
+                // The action already happened; a lost notification must not undo it
+                if let Err(e) = send_notification(switch, config, NotificationKind::Fired) {
+                    switch.last_error = Some(e.to_string());
+                    events.push(DmsEvent::NotifyFailed {
+                        file_path,
+                        error: e.to_string(),
+                    });
+                }
+            }
+            Err(e) => {
+                switch.last_error = Some(e.to_string());
+                events.push(DmsEvent::Failed {
+                    file_path,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    events
+}
+
+fn send_notification(
+    switch: &DeadManSwitch,
+    config: &Settings,
+    kind: NotificationKind,
+) -> Result<()> {
+    let channels = switch.notifiers(&config.notifiers);
+    if channels.is_empty() {
+        return Ok(());
+    }
+
+    let message = match kind {
+        NotificationKind::Warning => format!(
+            "Check in for {} within {} or the switch will fire ({}).",
+            switch.file_path,
+            format_duration(switch.time_until_deletion()),
+            switch.action.describe()
+        ),
+        NotificationKind::Fired => format!(
+            "The Dead Man's Switch for {} fired ({}).",
+            switch.file_path,
+            switch.action.describe()
+        ),
+    };
+
+    let notification = Notification {
+        kind,
+        file_path: switch.file_path.clone(),
+        action: switch.action.describe(),
+        deadline: switch.last_checkin + chrono::Duration::hours(switch.timeout_hours as i64),
+        message,
+    };
+
+    notify::notify_all(
+        &channels,
+        switch.notification_email.as_deref(),
+        &notification,
+    )
+}
+
+fn fire(
+    action: &DmsAction,
+    file_path: &str,
+    client: &dyn Transport,
+    inbox: &str,
+) -> Result<DmsEvent> {
+    match action {
+        DmsAction::Delete => {
+            transfer::shred(client, file_path)?;
+            Ok(DmsEvent::Deleted {
+                file_path: file_path.to_string(),
+            })
+        }
+        DmsAction::Release { envelope_path, .. } => {
+            let sealed = fs::read(envelope_path)?;
+            let stem = transfer::entry_name(file_path);
+            let stem = stem.strip_suffix(".enc").unwrap_or(&stem);
+            let envelope = format!("{inbox}/{stem}_release.enc");
+
+            client.upload(&sealed, &envelope)?;
+            fs::remove_file(envelope_path)?;
+
+            Ok(DmsEvent::Released {
+                file_path: file_path.to_string(),
+                envelope,
+            })
+        }
+        DmsAction::Notify => Ok(DmsEvent::Notified {
+            file_path: file_path.to_string(),
+        }),
+    }
+}
@@ -30,7 +30,10 @@ pub fn execute() -> Result<()> {
 
         active += 1;
 
-        let status = if switch.is_expired() {
+        let status = if switch.has_fired() {
+            expired += 1;
+            "FIRED".bright_black().to_string()
+        } else if switch.is_expired() {
             expired += 1;
             "EXPIRED".red().to_string()
         } else if switch.is_in_grace_period() {
@@ -42,11 +45,15 @@ pub fn execute() -> Result<()> {
 
         ui::print_box_line(&format!(">> File: {}", path));
         ui::print_box_line(&format!("   Status: {}", status));
+        ui::print_box_line(&format!("   Profile: {}", switch.profile));
         ui::print_box_line(&format!(
             "   Time Left: {}",
             crate::dms::format_duration(switch.time_until_deletion())
         ));
         ui::print_box_line(&format!("   Timeout: {} hours", switch.timeout_hours));
+        if let Some(ref error) = switch.last_error {
+            ui::print_box_line(&format!("   Last Error: {}", error));
+        }
         ui::print_box_line("");
     }
 
@@ -7,10 +7,7 @@ use std::path::PathBuf;
 pub fn execute(name: &str, output_path: Option<&str>) -> Result<()> {
     ui::print_box_start("EXPORT_KYBER_KEY");
 
-    let keys_dir = dirs::home_dir()
-        .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-        .join(".hermes")
-        .join("keys");
+    let keys_dir = crate::config::keys_dir()?;
 
     let kyber_pubkey_path = keys_dir.join(format!("{}_kyber.pub", name));
 
@@ -44,7 +41,7 @@ pub fn execute(name: &str, output_path: Option<&str>) -> Result<()> {
     ui::print_info("Exported to", dest_path.to_str().unwrap());
     println!();
 
-    println!("Share this file along with your RSA public key for hybrid encryption");
+    println!("Share this file along with your identity public key for hybrid encryption");
     println!();
 
     Ok(())
@@ -1,18 +1,13 @@
-use crate::crypto;
+use crate::crypto::{self, IdentityPublicKey};
 use crate::error::Result;
 use crate::ui;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub fn execute(name: &str, output_path: Option<&str>) -> Result<()> {
     ui::print_box_start("EXPORT_PUBKEY");
 
-    let key_dir = dirs::home_dir()
-        .ok_or_else(|| {
-            crate::error::HermesError::ConfigError("Could not find home directory".to_string())
-        })?
-        .join(".hermes")
-        .join("keys");
+    let key_dir = crate::config::keys_dir()?;
 
     let public_key_path = key_dir.join(format!("{name}.pub"));
 
@@ -24,8 +19,8 @@ pub fn execute(name: &str, output_path: Option<&str>) -> Result<()> {
 
     ui::print_box_line(&format!(">> Exporting public key: {name}"));
 
-    let public_key = crypto::load_public_key(public_key_path.to_str().unwrap())?;
-    let fingerprint = crypto::get_key_fingerprint(&public_key)?;
+    let public_key = IdentityPublicKey::load(public_key_path.to_str().unwrap())?;
+    let fingerprint = public_key.fingerprint()?;
 
     let dest = if let Some(path) = output_path {
         PathBuf::from(path)
@@ -35,6 +30,17 @@ pub fn execute(name: &str, output_path: Option<&str>) -> Result<()> {
 
     fs::copy(&public_key_path, &dest)?;
 
+    // X25519 identities sign with a separate Ed25519 key; export it alongside
+    let ed25519_path = key_dir.join(format!("{name}_ed25519.pub"));
+    let ed25519_export = if ed25519_path.exists() {
+        let ed25519_key = crypto::load_ed25519_public_key(ed25519_path.to_str().unwrap())?;
+        let ed25519_dest = ed25519_destination(&dest);
+        fs::copy(&ed25519_path, &ed25519_dest)?;
+        Some((ed25519_dest, crypto::get_ed25519_fingerprint(&ed25519_key)))
+    } else {
+        None
+    };
+
     ui::print_box_line(">> Public key exported");
     ui::print_box_line("");
     ui::print_box_end();
@@ -42,11 +48,32 @@ pub fn execute(name: &str, output_path: Option<&str>) -> Result<()> {
     println!();
     ui::print_success("PUBLIC KEY EXPORTED");
     ui::print_info("Output", dest.to_str().unwrap());
+    ui::print_info("Key Type", public_key.key_type().name());
     ui::print_info("Fingerprint", &fingerprint);
+    if let Some((ref ed25519_dest, ref ed25519_fingerprint)) = ed25519_export {
+        ui::print_info("Ed25519 Output", ed25519_dest.to_str().unwrap());
+        ui::print_info("Ed25519 Fingerprint", ed25519_fingerprint);
+    }
     println!();
 
     println!("📤 Share this file with others so they can encrypt files for you");
+    if ed25519_export.is_some() {
+        println!("📤 Share the Ed25519 key too so they can verify your signatures");
+    }
     println!();
 
     Ok(())
 }
+
+/// `alice.pem` -> `alice_ed25519.pem`, next to the exported identity key
+fn ed25519_destination(dest: &Path) -> PathBuf {
+    let stem = dest
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match dest.extension() {
+        Some(extension) => format!("{stem}_ed25519.{}", extension.to_string_lossy()),
+        None => format!("{stem}_ed25519"),
+    };
+    dest.with_file_name(file_name)
+}
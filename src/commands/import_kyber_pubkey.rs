@@ -19,10 +19,7 @@ pub fn execute(name: &str, pubkey_path: &str) -> Result<()> {
     let kyber_key = crypto::load_kyber_public_key(pubkey_path)?;
     let fingerprint = crypto::get_kyber_fingerprint(&kyber_key);
 
-    let recipients_dir = dirs::home_dir()
-        .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-        .join(".hermes")
-        .join("recipients");
+    let recipients_dir = crate::config::recipients_dir()?;
 
     fs::create_dir_all(&recipients_dir)?;
 
@@ -36,6 +33,7 @@ pub fn execute(name: &str, pubkey_path: &str) -> Result<()> {
     println!();
     ui::print_success("KYBER PUBLIC KEY IMPORTED");
     ui::print_info("Recipient", name);
+    ui::print_info("Algorithm", kyber_key.algorithm().name());
     ui::print_info("Fingerprint", &fingerprint);
     ui::print_info("Saved to", dest_path.to_str().unwrap());
     println!();
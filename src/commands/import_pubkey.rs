@@ -1,4 +1,4 @@
-use crate::crypto;
+use crate::crypto::{self, IdentityPublicKey};
 use crate::error::Result;
 use crate::ui;
 use std::fs;
@@ -6,21 +6,30 @@ use std::fs;
 pub fn execute(name: &str, pubkey_path: &str) -> Result<()> {
     ui::print_box_start("IMPORT_PUBKEY");
 
-    let recipients_dir = dirs::home_dir()
-        .ok_or_else(|| {
-            crate::error::HermesError::ConfigError("Could not find home directory".to_string())
-        })?
-        .join(".hermes")
-        .join("recipients");
+    let recipients_dir = crate::config::recipients_dir()?;
 
     std::fs::create_dir_all(&recipients_dir)?;
 
     ui::print_box_line(&format!(">> Importing public key for: {name}"));
 
-    let public_key = crypto::load_public_key(pubkey_path)?;
-    let fingerprint = crypto::get_key_fingerprint(&public_key)?;
-
-    let dest_path = recipients_dir.join(format!("{name}.pub"));
+    // Ed25519 signing keys are kept next to the identity key as `{name}_ed25519.pub`
+    let (file_name, key_type, fingerprint) = match crypto::load_ed25519_public_key(pubkey_path) {
+        Ok(public_key) => (
+            format!("{name}_ed25519.pub"),
+            "Ed25519",
+            crypto::get_ed25519_fingerprint(&public_key),
+        ),
+        Err(_) => {
+            let public_key = IdentityPublicKey::load(pubkey_path)?;
+            (
+                format!("{name}.pub"),
+                public_key.key_type().name(),
+                public_key.fingerprint()?,
+            )
+        }
+    };
+
+    let dest_path = recipients_dir.join(file_name);
     fs::copy(pubkey_path, &dest_path)?;
 
     ui::print_box_line(">> Public key imported successfully");
@@ -30,11 +39,16 @@ pub fn execute(name: &str, pubkey_path: &str) -> Result<()> {
     println!();
     ui::print_success("PUBLIC KEY IMPORTED");
     ui::print_info("Recipient", name);
+    ui::print_info("Key Type", key_type);
     ui::print_info("Stored At", dest_path.to_str().unwrap());
     ui::print_info("Fingerprint", &fingerprint);
     println!();
 
-    println!("✅ You can now encrypt files for {name} using --recipients flag");
+    if key_type == "Ed25519" {
+        println!("✅ You can now verify files signed by {name}");
+    } else {
+        println!("✅ You can now encrypt files for {name} using --recipients flag");
+    }
     println!();
 
     Ok(())
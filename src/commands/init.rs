@@ -1,7 +1,6 @@
-use crate::config::Settings;
+use crate::config::{ConfigFile, Settings, DEFAULT_PROFILE};
 use crate::error::Result;
 use crate::ui;
-use std::fs;
 
 pub fn execute() -> Result<()> {
     ui::print_box_start("INITIALIZE");
@@ -9,19 +8,19 @@ pub fn execute() -> Result<()> {
     ui::print_box_line(">> Creating configuration...");
 
     let config = Settings::default_config();
-    let config_dir = dirs::config_dir()
-        .ok_or_else(|| {
-            crate::error::HermesError::ConfigError("Could not find config directory".to_string())
-        })?
-        .join("hermes");
+    config.save()?;
 
-    fs::create_dir_all(&config_dir)?;
+    // A named profile that is the only one in the file becomes the default
+    let mut file = ConfigFile::load()?;
+    if config.profile != DEFAULT_PROFILE
+        && file.default_profile.is_none()
+        && file.profile_names() == [config.profile.clone()]
+    {
+        file.default_profile = Some(config.profile.clone());
+        file.save()?;
+    }
 
-    let config_path = config_dir.join("config.toml");
-    let toml_string = toml::to_string_pretty(&config)
-        .map_err(|e| crate::error::HermesError::ConfigError(e.to_string()))?;
-
-    fs::write(&config_path, toml_string)?;
+    let config_path = ConfigFile::path()?;
 
     ui::print_box_line(">> Configuration saved");
     ui::print_box_line("");
@@ -30,6 +29,7 @@ pub fn execute() -> Result<()> {
     println!();
     ui::print_success("HERMES INITIALIZED");
     ui::print_info("Config", &config_path.display().to_string());
+    ui::print_info("Profile", &config.profile);
     ui::print_info("Status", "Ready for secure transfers");
     println!();
 
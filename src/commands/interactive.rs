@@ -41,7 +41,7 @@ pub fn execute() -> Result<()> {
             4 => batch_operations_wizard()?,
             5 => key_management_wizard()?,
             6 => configuration_wizard()?,
-            7 => commands::list::execute()?,
+            7 => commands::list::execute(Default::default(), &Default::default())?,
             8 => {
                 println!("{}", "👋 Goodbye!".bright_green());
                 break;
@@ -65,7 +65,7 @@ fn send_message_wizard() -> Result<()> {
 
     let encryption_type = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select encryption type")
-        .items(&["Password", "Recipients (RSA)"])
+        .items(&["Password", "Recipients (public key)"])
         .default(0)
         .interact()?;
 
@@ -98,7 +98,16 @@ fn send_message_wizard() -> Result<()> {
     };
 
     println!();
-    commands::send_msg::execute(&message, password.as_deref(), None, ttl, recipients)?;
+    commands::send_msg::execute(
+        &message,
+        password.as_deref(),
+        None,
+        ttl,
+        recipients,
+        None,
+        false,
+        false,
+    )?;
 
     Ok(())
 }
@@ -145,7 +154,7 @@ fn send_file_wizard() -> Result<()> {
 
     let encryption_type = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select encryption type")
-        .items(&["Password", "Recipients (RSA)"])
+        .items(&["Password", "Recipients (public key)"])
         .default(0)
         .interact()?;
 
@@ -178,7 +187,18 @@ fn send_file_wizard() -> Result<()> {
     };
 
     println!();
-    commands::send_file::execute(&file_path, password.as_deref(), None, ttl, recipients, None, false)?;
+    commands::send_file::execute(
+        &file_path,
+        password.as_deref(),
+        None,
+        ttl,
+        recipients,
+        None,
+        false,
+        None,
+        false,
+        false,
+    )?;
 
     Ok(())
 }
@@ -229,6 +249,7 @@ fn receive_file_wizard() -> Result<()> {
         password.as_deref(),
         output.as_deref(),
         recipient.as_deref(),
+        None,
     )?;
 
     Ok(())
@@ -273,7 +294,7 @@ fn send_batch_wizard() -> Result<()> {
 
     let encryption_type = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select encryption type")
-        .items(&["Password", "Recipients (RSA)"])
+        .items(&["Password", "Recipients (public key)"])
         .default(0)
         .interact()?;
 
@@ -326,7 +347,7 @@ fn send_dir_wizard() -> Result<()> {
 
     let encryption_type = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select encryption type")
-        .items(&["Password", "Recipients (RSA)"])
+        .items(&["Password", "Recipients (public key)"])
         .default(0)
         .interact()?;
 
@@ -359,7 +380,14 @@ fn send_dir_wizard() -> Result<()> {
     };
 
     println!();
-    commands::send_dir::execute(&dir_path, password.as_deref(), ttl, recipients, recursive)?;
+    commands::send_dir::execute(
+        &dir_path,
+        password.as_deref(),
+        ttl,
+        recipients,
+        recursive,
+        false,
+    )?;
 
     Ok(())
 }
@@ -446,7 +474,16 @@ fn key_management_wizard() -> Result<()> {
                 .interact_text()?;
 
             println!();
-            commands::keygen::execute(&name, None, false, false)?;
+            commands::keygen::execute(
+                &name,
+                None,
+                Default::default(),
+                false,
+                false,
+                false,
+                Default::default(),
+                Default::default(),
+            )?;
         }
         1 => {
             let name: String = Input::with_theme(&ColorfulTheme::default())
@@ -1,4 +1,4 @@
-use crate::error::{HermesError, Result};
+use crate::error::Result;
 use crate::ui;
 use std::collections::HashMap;
 use std::fs;
@@ -6,10 +6,7 @@ use std::fs;
 pub fn execute() -> Result<()> {
     ui::print_box_start("ARCHIVED_KEYS");
 
-    let archive_dir = dirs::home_dir()
-        .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-        .join(".hermes")
-        .join("keys")
+    let archive_dir = crate::config::keys_dir()?
         .join("archive");
 
     if !archive_dir.exists() {
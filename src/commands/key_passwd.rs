@@ -0,0 +1,101 @@
+use crate::crypto::{self, keystore, IdentityPrivateKey};
+use crate::error::{HermesError, Result};
+use crate::ui;
+
+pub fn execute(name: &str, remove: bool) -> Result<()> {
+    ui::print_box_start("KEY_PASSWD");
+
+    let keys_dir = crate::config::keys_dir()?;
+
+    let identity_path = keys_dir.join(format!("{name}.pem"));
+    let kyber_path = keys_dir.join(format!("{name}_kyber.pem"));
+    let dilithium_path = keys_dir.join(format!("{name}_dilithium.pem"));
+    let ed25519_path = keys_dir.join(format!("{name}_ed25519.pem"));
+
+    if !identity_path.exists() {
+        return Err(HermesError::ConfigError(format!(
+            "Private key not found for: {name}"
+        )));
+    }
+
+    ui::print_box_line(&format!(">> Key: {name}"));
+
+    let current = keystore::unlock_passphrase(
+        &[&identity_path, &kyber_path, &dilithium_path, &ed25519_path],
+        name,
+    )?;
+
+    // Load every secret key first so a wrong passphrase changes nothing
+    ui::print_box_line(">> Unlocking private keys...");
+    let identity_key =
+        IdentityPrivateKey::load(identity_path.to_str().unwrap(), current.as_deref())?;
+    let kyber_key = if kyber_path.exists() {
+        Some(crypto::load_kyber_secret_key(
+            kyber_path.to_str().unwrap(),
+            current.as_deref(),
+        )?)
+    } else {
+        None
+    };
+    let dilithium_key = if dilithium_path.exists() {
+        Some(crypto::load_dilithium_secret_key(
+            dilithium_path.to_str().unwrap(),
+            current.as_deref(),
+        )?)
+    } else {
+        None
+    };
+    let ed25519_key = if ed25519_path.exists() {
+        Some(crypto::load_ed25519_secret_key(
+            ed25519_path.to_str().unwrap(),
+            current.as_deref(),
+        )?)
+    } else {
+        None
+    };
+
+    let new_passphrase = if remove {
+        None
+    } else {
+        Some(keystore::prompt_new_passphrase(true)?).filter(|p| !p.is_empty())
+    };
+
+    ui::print_box_line(">> Re-encrypting private keys...");
+    identity_key.save(&identity_path, new_passphrase.as_deref())?;
+    if let Some(ref key) = kyber_key {
+        crypto::save_kyber_secret_key(key, &kyber_path, new_passphrase.as_deref())?;
+    }
+    if let Some(ref key) = dilithium_key {
+        crypto::save_dilithium_secret_key(key, &dilithium_path, new_passphrase.as_deref())?;
+    }
+    if let Some(ref key) = ed25519_key {
+        crypto::save_ed25519_secret_key(key, &ed25519_path, new_passphrase.as_deref())?;
+    }
+
+    ui::print_box_line("");
+    ui::print_box_end();
+
+    println!();
+    if new_passphrase.is_some() {
+        ui::print_success("PASSPHRASE CHANGED");
+    } else {
+        ui::print_success("PASSPHRASE REMOVED");
+    }
+    ui::print_info("Key Name", name);
+    ui::print_info(
+        &format!("{} Private Key", identity_key.key_type().name()),
+        identity_path.to_str().unwrap(),
+    );
+    if kyber_key.is_some() {
+        ui::print_info("Kyber Private Key", kyber_path.to_str().unwrap());
+    }
+    if dilithium_key.is_some() {
+        ui::print_info("Dilithium Private Key", dilithium_path.to_str().unwrap());
+    }
+    if ed25519_key.is_some() {
+        ui::print_info("Ed25519 Private Key", ed25519_path.to_str().unwrap());
+    }
+    println!();
+
+    Ok(())
+}
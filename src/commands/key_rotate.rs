@@ -1,20 +1,23 @@
-use crate::crypto;
+use crate::crypto::{self, keystore, IdentityPublicKey, KemAlgorithm, KeyType, SignatureAlgorithm};
 use crate::error::{HermesError, Result};
 use crate::ui;
 use chrono::Utc;
 use colored::Colorize;
-use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey};
-use rsa::{RsaPrivateKey, RsaPublicKey};
 use std::fs;
 use std::path::Path;
 
-pub fn execute(name: &str, archive: bool, generate_pqc: bool, generate_sign: bool) -> Result<()> {
+pub fn execute(
+    name: &str,
+    archive: bool,
+    key_type: Option<KeyType>,
+    generate_pqc: bool,
+    generate_sign: bool,
+    kem: KemAlgorithm,
+    sig_alg: SignatureAlgorithm,
+) -> Result<()> {
     ui::print_box_start("KEY_ROTATE");
 
-    let keys_dir = dirs::home_dir()
-        .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-        .join(".hermes")
-        .join("keys");
+    let keys_dir = crate::config::keys_dir()?;
 
     if !keys_dir.exists() {
         return Err(HermesError::ConfigError(
@@ -35,6 +38,21 @@ pub fn execute(name: &str, archive: bool, generate_pqc: bool, generate_sign: boo
 
     ui::print_box_line(&format!(">> Rotating key: {}", name));
 
+    let key_type = match key_type {
+        Some(key_type) => key_type,
+        None => IdentityPublicKey::load(current_public.to_str().unwrap())?.key_type(),
+    };
+    let ed25519_private = keys_dir.join(format!("{}_ed25519.pem", name));
+    let ed25519_public = keys_dir.join(format!("{}_ed25519.pub", name));
+
+    // Keep passphrase protection on the rotated keys
+    let passphrase = if keystore::is_protected(&current_private) {
+        ui::print_box_line(">> Current key is passphrase-protected");
+        Some(keystore::prompt_new_passphrase(false)?)
+    } else {
+        None
+    };
+
     // Archive old keys if requested
     if archive {
         let archive_dir = keys_dir.join("archive");
@@ -45,9 +63,13 @@ pub fn execute(name: &str, archive: bool, generate_pqc: bool, generate_sign: boo
         let timestamp = Utc::now().format("%Y%m%d_%H%M%S").to_string();
         let archive_name = format!("{}_{}", name, timestamp);
 
-        ui::print_box_line(">> Archiving old RSA keys...");
+        ui::print_box_line(">> Archiving old identity keys...");
         archive_key_file(&current_private, &archive_dir, &archive_name, "pem")?;
         archive_key_file(&current_public, &archive_dir, &archive_name, "pub")?;
+        if ed25519_private.exists() {
+            archive_key_file(&ed25519_private, &archive_dir, &archive_name, "ed25519.pem")?;
+            archive_key_file(&ed25519_public, &archive_dir, &archive_name, "ed25519.pub")?;
+        }
 
         // Archive PQC keys if they exist
         let kyber_private = keys_dir.join(format!("{}_kyber.pem", name));
@@ -70,63 +92,92 @@ pub fn execute(name: &str, archive: bool, generate_pqc: bool, generate_sign: boo
         ui::print_box_line(&format!(">> Archived to: {}/", archive_dir.display()));
     }
 
-    // Generate new RSA keypair
-    ui::print_box_line(">> Generating new RSA-4096 keypair...");
-    let mut rng = rand::thread_rng();
-    let private_key = RsaPrivateKey::new(&mut rng, 4096)
-        .map_err(|e| HermesError::KeyGenerationFailed(e.to_string()))?;
-    let public_key = RsaPublicKey::from(&private_key);
-
-    // Save new RSA keys
-    let private_pem = private_key
-        .to_pkcs8_pem(rsa::pkcs8::LineEnding::LF)
-        .map_err(|e| HermesError::KeyGenerationFailed(e.to_string()))?;
-    let public_pem = public_key
-        .to_public_key_pem(rsa::pkcs8::LineEnding::LF)
-        .map_err(|e| HermesError::KeyGenerationFailed(e.to_string()))?;
-
-    fs::write(&current_private, private_pem.as_bytes())?;
-    fs::write(&current_public, public_pem.as_bytes())?;
-
-    let rsa_fingerprint = crypto::get_key_fingerprint(&public_key)?;
-    ui::print_box_line(&format!(">> New RSA fingerprint: {}", rsa_fingerprint));
+    // Generate new identity keypair
+    ui::print_box_line(&format!(">> Generating new {} keypair...", key_type.name()));
+    let public_key = crypto::generate_identity(
+        key_type,
+        &current_private,
+        &current_public,
+        passphrase.as_deref(),
+    )?;
+
+    let fingerprint = public_key.fingerprint()?;
+    ui::print_box_line(&format!(
+        ">> New {} fingerprint: {}",
+        key_type.name(),
+        fingerprint
+    ));
+
+    // X25519 identities sign with Ed25519; an RSA identity signs with RSA-PSS instead
+    if key_type == KeyType::X25519 {
+        let (ed25519_public_key, ed25519_secret_key) = crypto::generate_ed25519_keypair()?;
+        crypto::save_ed25519_secret_key(
+            &ed25519_secret_key,
+            &ed25519_private,
+            passphrase.as_deref(),
+        )?;
+        crypto::save_ed25519_public_key(&ed25519_public_key, &ed25519_public)?;
+        ui::print_box_line(&format!(
+            ">> New Ed25519 fingerprint: {}",
+            crypto::get_ed25519_fingerprint(&ed25519_public_key)
+        ));
+    } else {
+        for path in [&ed25519_private, &ed25519_public] {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+    }
 
     // Generate new PQC keys if requested
     if generate_pqc {
-        ui::print_box_line(">> Generating new Kyber-1024 keypair...");
-        let (kyber_public, kyber_secret) = crypto::generate_kyber_keypair()?;
+        ui::print_box_line(&format!(">> Generating new {} keypair...", kem.name()));
+        let (kyber_public, kyber_secret) = crypto::generate_kyber_keypair(kem)?;
 
         let kyber_private_path = keys_dir.join(format!("{}_kyber.pem", name));
         let kyber_public_path = keys_dir.join(format!("{}_kyber.pub", name));
 
-        crypto::save_kyber_secret_key(&kyber_secret, &kyber_private_path)?;
+        crypto::save_kyber_secret_key(
+            &kyber_secret,
+            &kyber_private_path,
+            passphrase.as_deref(),
+        )?;
         crypto::save_kyber_public_key(&kyber_public, &kyber_public_path)?;
 
         let kyber_fingerprint = crypto::get_kyber_fingerprint(&kyber_public);
-        ui::print_box_line(&format!(">> New Kyber fingerprint: {}", kyber_fingerprint));
+        ui::print_box_line(&format!(">> New {} fingerprint: {}", kem.name(), kyber_fingerprint));
     }
 
     // Generate new signing keys if requested
     if generate_sign {
-        ui::print_box_line(">> Generating new Dilithium-5 keypair...");
-        let (dilithium_public, dilithium_secret) = crypto::generate_dilithium_keypair()?;
+        ui::print_box_line(&format!(">> Generating new {} keypair...", sig_alg.name()));
+        let (dilithium_public, dilithium_secret) = crypto::generate_dilithium_keypair(sig_alg)?;
 
         let dilithium_private_path = keys_dir.join(format!("{}_dilithium.pem", name));
         let dilithium_public_path = keys_dir.join(format!("{}_dilithium.pub", name));
 
-        crypto::save_dilithium_secret_key(&dilithium_secret, &dilithium_private_path)?;
+        crypto::save_dilithium_secret_key(
+            &dilithium_secret,
+            &dilithium_private_path,
+            passphrase.as_deref(),
+        )?;
         crypto::save_dilithium_public_key(&dilithium_public, &dilithium_public_path)?;
 
         let dilithium_fingerprint = crypto::get_dilithium_fingerprint(&dilithium_public);
-        ui::print_box_line(&format!(">> New Dilithium fingerprint: {}", dilithium_fingerprint));
+        ui::print_box_line(&format!(
+            ">> New {} fingerprint: {}",
+            sig_alg.name(),
+            dilithium_fingerprint
+        ));
     }
 
     // Create rotation metadata
     let metadata_path = keys_dir.join(format!("{}.rotation", name));
     let rotation_info = format!(
-        "Last rotated: {}\nRSA fingerprint: {}\n",
+        "Last rotated: {}\n{} fingerprint: {}\n",
         Utc::now().to_rfc3339(),
-        rsa_fingerprint
+        key_type.name(),
+        fingerprint
     );
     fs::write(&metadata_path, rotation_info)?;
 
@@ -136,15 +187,15 @@ pub fn execute(name: &str, archive: bool, generate_pqc: bool, generate_sign: boo
     println!();
     ui::print_success("KEY ROTATION COMPLETE");
     ui::print_info("Key name", name);
-    ui::print_info("RSA fingerprint", &rsa_fingerprint);
+    ui::print_info(&format!("{} fingerprint", key_type.name()), &fingerprint);
     if archive {
         ui::print_info("Old keys archived", "Yes");
     }
     if generate_pqc {
-        ui::print_info("New Kyber keys", "Generated");
+        ui::print_info("New PQC keys", &format!("Generated ({})", kem.name()));
     }
     if generate_sign {
-        ui::print_info("New Dilithium keys", "Generated");
+        ui::print_info("New signing keys", &format!("Generated ({})", sig_alg.name()));
     }
     println!();
 
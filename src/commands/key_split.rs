@@ -1,3 +1,4 @@
+use crate::crypto::keystore::unlock_passphrase;
 use crate::crypto::rsa::load_private_key;
 use crate::error::Result;
 use crate::shamir::split_secret;
@@ -12,7 +13,8 @@ pub fn execute(name: &str, threshold: u8, total_shares: u8, output_dir: Option<&
     ui::print_box_line(&format!(">> Threshold: {}/{}", threshold, total_shares));
     ui::print_box_line("");
 
-    let private_key = load_private_key(name)?;
+    let passphrase = unlock_passphrase(&[Path::new(name)], name)?;
+    let private_key = load_private_key(name, passphrase.as_deref())?;
     let key_bytes = private_key.to_pkcs8_der()
         .map_err(|e| crate::error::HermesError::EncryptionFailed(format!("Failed to encode key: {}", e)))?
         .as_bytes().to_vec();
@@ -1,16 +1,28 @@
-use crate::crypto;
+use crate::crypto::{self, KemAlgorithm, KeyType, SignatureAlgorithm};
 use crate::error::Result;
 use crate::progress;
 use crate::ui;
 use std::path::PathBuf;
 
-pub fn execute(name: &str, output_dir: Option<&str>, use_pqc: bool, use_sign: bool) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+pub fn execute(
+    name: &str,
+    output_dir: Option<&str>,
+    key_type: KeyType,
+    use_pqc: bool,
+    use_sign: bool,
+    protect: bool,
+    kem: KemAlgorithm,
+    sig_alg: SignatureAlgorithm,
+) -> Result<()> {
     let title = if use_pqc && use_sign {
         "FULL_PQC_KEYGEN"
     } else if use_pqc {
         "HYBRID_KEYGEN"
     } else if use_sign {
         "SIGNING_KEYGEN"
+    } else if key_type == KeyType::X25519 {
+        "X25519_KEYGEN"
     } else {
         "RSA_KEYGEN"
     };
@@ -19,12 +31,7 @@ pub fn execute(name: &str, output_dir: Option<&str>, use_pqc: bool, use_sign: bo
     let key_dir = if let Some(dir) = output_dir {
         PathBuf::from(dir)
     } else {
-        dirs::home_dir()
-            .ok_or_else(|| {
-                crate::error::HermesError::ConfigError("Could not find home directory".to_string())
-            })?
-            .join(".hermes")
-            .join("keys")
+        crate::config::keys_dir()?
     };
 
     std::fs::create_dir_all(&key_dir)?;
@@ -32,21 +39,53 @@ pub fn execute(name: &str, output_dir: Option<&str>, use_pqc: bool, use_sign: bo
     let private_key_path = key_dir.join(format!("{name}.pem"));
     let public_key_path = key_dir.join(format!("{name}.pub"));
 
-    ui::print_box_line(&format!(">> Generating RSA-4096 keypair for: {name}"));
-    ui::print_box_line(">> This may take a moment...");
+    let passphrase = if protect {
+        ui::print_box_line(">> Private keys will be passphrase-protected");
+        Some(crypto::keystore::prompt_new_passphrase(false)?)
+    } else {
+        None
+    };
 
+    ui::print_box_line(&format!(
+        ">> Generating {} keypair for: {name}",
+        key_type.name()
+    ));
     let spinner = progress::create_keygen_spinner();
-    spinner.set_message("Generating prime numbers...".to_string());
+    if key_type == KeyType::Rsa {
+        ui::print_box_line(">> This may take a moment...");
+        spinner.set_message("Generating prime numbers...".to_string());
+    }
 
-    crypto::generate_keypair(
-        private_key_path.to_str().unwrap(),
-        public_key_path.to_str().unwrap(),
+    let public_key = crypto::generate_identity(
+        key_type,
+        &private_key_path,
+        &public_key_path,
+        passphrase.as_deref(),
     )?;
+    let fingerprint = public_key.fingerprint()?;
+
+    spinner.finish_with_message(format!("{} keypair generated", key_type.name()));
 
-    spinner.finish_with_message("RSA keypair generated".to_string());
+    // X25519 can't sign, so those identities get an Ed25519 key for classical signatures
+    let ed25519 = if key_type == KeyType::X25519 {
+        let ed25519_private_path = key_dir.join(format!("{name}_ed25519.pem"));
+        let ed25519_public_path = key_dir.join(format!("{name}_ed25519.pub"));
 
-    let public_key = crypto::load_public_key(public_key_path.to_str().unwrap())?;
-    let fingerprint = crypto::get_key_fingerprint(&public_key)?;
+        let (ed25519_public, ed25519_secret) = crypto::generate_ed25519_keypair()?;
+        crypto::save_ed25519_secret_key(
+            &ed25519_secret,
+            &ed25519_private_path,
+            passphrase.as_deref(),
+        )?;
+        crypto::save_ed25519_public_key(&ed25519_public, &ed25519_public_path)?;
+
+        Some((
+            ed25519_public_path,
+            crypto::get_ed25519_fingerprint(&ed25519_public),
+        ))
+    } else {
+        None
+    };
 
     // Generate Kyber (Post-Quantum) keypair if PQC is enabled
     let (kyber_private_path, kyber_public_path, kyber_fingerprint) = if use_pqc {
@@ -54,19 +93,26 @@ pub fn execute(name: &str, output_dir: Option<&str>, use_pqc: bool, use_sign: bo
         let kyber_public_path = key_dir.join(format!("{name}_kyber.pub"));
 
         ui::print_box_line("");
-        ui::print_box_line(">> Generating Kyber-1024 keypair (Post-Quantum)...");
+        ui::print_box_line(&format!(
+            ">> Generating {} keypair (Post-Quantum)...",
+            kem.name()
+        ));
 
         let pq_spinner = progress::create_keygen_spinner();
-        pq_spinner.set_message("Generating Kyber lattice...".to_string());
+        pq_spinner.set_message("Generating ML-KEM lattice...".to_string());
 
-        let (kyber_public, kyber_secret) = crypto::generate_kyber_keypair()?;
+        let (kyber_public, kyber_secret) = crypto::generate_kyber_keypair(kem)?;
 
-        crypto::save_kyber_secret_key(&kyber_secret, &kyber_private_path)?;
+        crypto::save_kyber_secret_key(
+            &kyber_secret,
+            &kyber_private_path,
+            passphrase.as_deref(),
+        )?;
         crypto::save_kyber_public_key(&kyber_public, &kyber_public_path)?;
 
         let kyber_fp = crypto::get_kyber_fingerprint(&kyber_public);
 
-        pq_spinner.finish_with_message("Kyber keypair generated".to_string());
+        pq_spinner.finish_with_message(format!("{} keypair generated", kem.name()));
 
         (
             Some(kyber_private_path),
@@ -83,19 +129,26 @@ pub fn execute(name: &str, output_dir: Option<&str>, use_pqc: bool, use_sign: bo
         let dilithium_public_path = key_dir.join(format!("{name}_dilithium.pub"));
 
         ui::print_box_line("");
-        ui::print_box_line(">> Generating Dilithium-5 keypair (Signatures)...");
+        ui::print_box_line(&format!(
+            ">> Generating {} keypair (Signatures)...",
+            sig_alg.name()
+        ));
 
         let sign_spinner = progress::create_keygen_spinner();
-        sign_spinner.set_message("Generating Dilithium lattice...".to_string());
+        sign_spinner.set_message("Generating ML-DSA lattice...".to_string());
 
-        let (dilithium_public, dilithium_secret) = crypto::generate_dilithium_keypair()?;
+        let (dilithium_public, dilithium_secret) = crypto::generate_dilithium_keypair(sig_alg)?;
 
-        crypto::save_dilithium_secret_key(&dilithium_secret, &dilithium_private_path)?;
+        crypto::save_dilithium_secret_key(
+            &dilithium_secret,
+            &dilithium_private_path,
+            passphrase.as_deref(),
+        )?;
         crypto::save_dilithium_public_key(&dilithium_public, &dilithium_public_path)?;
 
         let dilithium_fp = crypto::get_dilithium_fingerprint(&dilithium_public);
 
-        sign_spinner.finish_with_message("Dilithium keypair generated".to_string());
+        sign_spinner.finish_with_message(format!("{} keypair generated", sig_alg.name()));
 
         (
             Some(dilithium_private_path),
@@ -109,48 +162,70 @@ pub fn execute(name: &str, output_dir: Option<&str>, use_pqc: bool, use_sign: bo
     ui::print_box_line("");
     ui::print_box_end();
 
+    let classical = match key_type {
+        KeyType::Rsa => "RSA",
+        KeyType::X25519 => "X25519",
+    };
     println!();
     if use_pqc && use_sign {
-        ui::print_success("FULL PQC KEYPAIR GENERATED (RSA + Kyber + Dilithium)");
+        ui::print_success(&format!(
+            "FULL PQC KEYPAIR GENERATED ({classical} + ML-KEM + ML-DSA)"
+        ));
     } else if use_pqc {
-        ui::print_success("HYBRID KEYPAIR GENERATED (RSA + Kyber)");
+        ui::print_success(&format!("HYBRID KEYPAIR GENERATED ({classical} + ML-KEM)"));
     } else if use_sign {
-        ui::print_success("SIGNING KEYPAIR GENERATED (RSA + Dilithium)");
+        ui::print_success(&format!("SIGNING KEYPAIR GENERATED ({classical} + ML-DSA)"));
     } else {
-        ui::print_success("RSA KEYPAIR GENERATED");
+        ui::print_success(&format!("{classical} KEYPAIR GENERATED"));
     }
     ui::print_info("Name", name);
-    ui::print_info("RSA Private Key", private_key_path.to_str().unwrap());
-    ui::print_info("RSA Public Key", public_key_path.to_str().unwrap());
-    ui::print_info("RSA Fingerprint", &fingerprint);
-    ui::print_info("RSA Key Size", "4096 bits");
+    ui::print_info(
+        &format!("{classical} Private Key"),
+        private_key_path.to_str().unwrap(),
+    );
+    ui::print_info(
+        &format!("{classical} Public Key"),
+        public_key_path.to_str().unwrap(),
+    );
+    ui::print_info(&format!("{classical} Fingerprint"), &fingerprint);
+    match key_type {
+        KeyType::Rsa => ui::print_info("RSA Key Size", "4096 bits"),
+        KeyType::X25519 => ui::print_info("Key Agreement", "X25519 (Curve25519)"),
+    }
+    if let Some((ref ed25519_public_path, ref ed25519_fingerprint)) = ed25519 {
+        ui::print_info("Ed25519 Public Key", ed25519_public_path.to_str().unwrap());
+        ui::print_info("Ed25519 Fingerprint", ed25519_fingerprint);
+    }
+    if protect {
+        ui::print_info("Protection", "Passphrase");
+    }
 
     if use_pqc {
         println!();
         ui::print_info(
-            "Kyber Private Key",
+            "PQC Private Key",
             kyber_private_path.unwrap().to_str().unwrap(),
         );
         ui::print_info(
-            "Kyber Public Key",
+            "PQC Public Key",
             kyber_public_path.unwrap().to_str().unwrap(),
         );
-        ui::print_info("Kyber Fingerprint", &kyber_fingerprint.unwrap());
-        ui::print_info("Kyber Security", "ML-KEM 1024 (256-bit equiv)");
+        ui::print_info("PQC Fingerprint", &kyber_fingerprint.unwrap());
+        ui::print_info("PQC Algorithm", kem_security(kem));
     }
 
     if use_sign {
         println!();
         ui::print_info(
-            "Dilithium Private Key",
+            "Signing Private Key",
             dilithium_private_path.unwrap().to_str().unwrap(),
         );
         ui::print_info(
-            "Dilithium Public Key",
+            "Signing Public Key",
             dilithium_public_path.unwrap().to_str().unwrap(),
         );
-        ui::print_info("Dilithium Fingerprint", &dilithium_fingerprint.unwrap());
-        ui::print_info("Dilithium Security", "NIST Level 5 (256-bit equiv)");
+        ui::print_info("Signing Fingerprint", &dilithium_fingerprint.unwrap());
+        ui::print_info("Signing Algorithm", signature_security(sig_alg));
     }
     println!();
 
@@ -164,7 +239,28 @@ pub fn execute(name: &str, output_dir: Option<&str>, use_pqc: bool, use_sign: bo
     } else {
         println!("Share your public key with others to receive encrypted files");
     }
+    if ed25519.is_some() {
+        println!("Share _ed25519.pub as well so others can verify your signatures");
+    }
     println!();
 
     Ok(())
 }
+
+/// Algorithm and NIST security category, for the keygen summary
+pub(crate) fn kem_security(kem: KemAlgorithm) -> &'static str {
+    match kem {
+        KemAlgorithm::MlKem768 => "ML-KEM-768 (NIST Level 3)",
+        KemAlgorithm::MlKem1024 => "ML-KEM-1024 (NIST Level 5)",
+        KemAlgorithm::Kyber768 => "Kyber-768 (pre-standard)",
+    }
+}
+
+/// Algorithm and NIST security category, for the keygen summary
+pub(crate) fn signature_security(sig_alg: SignatureAlgorithm) -> &'static str {
+    match sig_alg {
+        SignatureAlgorithm::MlDsa65 => "ML-DSA-65 (NIST Level 3)",
+        SignatureAlgorithm::MlDsa87 => "ML-DSA-87 (NIST Level 5)",
+        SignatureAlgorithm::Dilithium5 => "Dilithium-5 (pre-standard)",
+    }
+}
@@ -1,81 +1,217 @@
 use crate::config::Settings;
+use crate::crypto::{keyring, EncryptedPackage};
 use crate::error::Result;
+use crate::transfer::{self, RemoteEntry, Transport};
 use colored::Colorize;
-use std::fs;
 
-pub fn execute() -> Result<()> {
+/// Ordering for vault listings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Expires,
+}
+
+/// Which packages to show
+#[derive(Debug, Clone, Default)]
+pub struct ListFilter {
+    pub expired_only: bool,
+    pub recipient: Option<String>,
+}
+
+impl ListFilter {
+    fn is_active(&self) -> bool {
+        self.expired_only || self.recipient.is_some()
+    }
+
+    fn matches(&self, header: Option<&EncryptedPackage>) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+
+        let Some(package) = header else {
+            return false;
+        };
+
+        if self.expired_only && !package.is_expired() {
+            return false;
+        }
+
+        if let Some(ref name) = self.recipient {
+            if !is_addressed_to(package, name) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Whether one of the package's slots is for `name`, by key ID or by legacy name
+fn is_addressed_to(package: &EncryptedPackage, name: &str) -> bool {
+    if !package.has_key_ids() {
+        return package.recipients.iter().any(|r| r.name == name);
+    }
+
+    let keys = keyring::recipient_ids(name)
+        .into_iter()
+        .chain(keyring::local_keys(Some(name)).unwrap_or_default())
+        .collect::<Vec<_>>();
+    package
+        .recipients
+        .iter()
+        .any(|r| keys.iter().any(|key| key.matches(&r.name)))
+}
+
+struct VaultItem {
+    entry: RemoteEntry,
+    header: Option<EncryptedPackage>,
+}
+
+pub fn execute(sort: SortKey, filter: &ListFilter) -> Result<()> {
     let config = Settings::load()?;
+    let client = transfer::connect(&config)?;
 
     println!("\n{}", "═".repeat(60).bright_cyan());
     println!("{}", "📁 HERMES VAULT INVENTORY".bright_white().bold());
     println!("{}", "═".repeat(60).bright_cyan());
 
-    print_directory(&config.paths.outbox, "OUTBOX")?;
-    print_directory(&config.paths.inbox, "INBOX")?;
-    print_directory(&config.paths.files, "FILES")?;
+    print_directory(
+        client.as_ref(),
+        &config.paths.outbox,
+        "OUTBOX",
+        sort,
+        filter,
+    )?;
+    print_directory(client.as_ref(), &config.paths.inbox, "INBOX", sort, filter)?;
+    print_directory(client.as_ref(), &config.paths.files, "FILES", sort, filter)?;
 
     println!("{}\n", "═".repeat(60).bright_cyan());
 
     Ok(())
 }
 
-fn print_directory(path: &str, label: &str) -> Result<()> {
+fn print_directory(
+    client: &dyn Transport,
+    path: &str,
+    label: &str,
+    sort: SortKey,
+    filter: &ListFilter,
+) -> Result<()> {
     println!("\n📁 {}", label.bright_yellow().bold());
     println!("   Path: {}", path.bright_black());
 
-    if let Ok(entries) = fs::read_dir(path) {
-        let mut files: Vec<_> = entries
-            .filter_map(std::result::Result::ok)
-            .filter(|e| e.path().is_file())
-            .collect();
-
-        if files.is_empty() {
-            println!("   {}", "(empty)".bright_black());
-        } else {
-            files.sort_by_key(std::fs::DirEntry::path);
-
-            for entry in files {
-                let file_path = entry.path();
-                let file_name = file_path.file_name().unwrap().to_str().unwrap();
-
-                if let Ok(metadata) = entry.metadata() {
-                    let size_kb = metadata.len() as f64 / 1024.0;
-
-                    let mut status_info = format!("{size_kb:.2} KB");
-
-                    if file_name.ends_with(".enc") {
-                        if let Ok(file_data) = fs::read(&file_path) {
-                            if let Ok(package) =
-                                crate::crypto::encrypt::EncryptedPackage::from_bytes(&file_data)
-                            {
-                                if package.is_expired() {
-                                    status_info.push_str(&format!(" {}", "[EXPIRED]".red().bold()));
-                                } else if package.expires_at > 0 {
-                                    let now = std::time::SystemTime::now()
-                                        .duration_since(std::time::UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_secs();
-                                    let remaining = (package.expires_at as i64 - now as i64) / 3600;
-                                    if remaining > 0 {
-                                        status_info.push_str(&format!(
-                                            " {}{}h",
-                                            "[⏰ ".yellow(),
-                                            remaining
-                                        ));
-                                        status_info.push_str(&"]".yellow().to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-
-                    println!("   • {} ({})", file_name.bright_green(), status_info);
-                }
-            }
-        }
-    } else {
+    let Ok(entries) = client.list(path) else {
         println!("   {}", "(directory not accessible)".bright_red());
+        return Ok(());
+    };
+
+    let mut items: Vec<VaultItem> = entries
+        .into_iter()
+        .filter(|e| !e.is_dir)
+        .map(|entry| {
+            // Only the header is fetched; ciphertext stays on the server
+            let header = if entry.name.ends_with(".enc") {
+                transfer::read_package_header(client, &entry.path).ok()
+            } else {
+                None
+            };
+            VaultItem { entry, header }
+        })
+        .filter(|item| filter.matches(item.header.as_ref()))
+        .collect();
+
+    if items.is_empty() {
+        println!("   {}", "(empty)".bright_black());
+        return Ok(());
+    }
+
+    sort_items(&mut items, sort);
+
+    for item in &items {
+        print_item(item);
     }
 
     Ok(())
 }
+
+fn sort_items(items: &mut [VaultItem], sort: SortKey) {
+    match sort {
+        SortKey::Name => items.sort_by(|a, b| a.entry.name.cmp(&b.entry.name)),
+        SortKey::Size => items.sort_by_key(|item| std::cmp::Reverse(item.entry.size)),
+        SortKey::Expires => items.sort_by_key(|item| {
+            // Soonest expiry first, packages without a TTL last
+            match item.header.as_ref().map(|h| h.expires_at) {
+                Some(0) | None => u64::MAX,
+                Some(ts) => ts,
+            }
+        }),
+    }
+}
+
+fn print_item(item: &VaultItem) {
+    let size_kb = item.entry.size as f64 / 1024.0;
+    let mut status_info = format!("{size_kb:.2} KB");
+
+    if let Some(ref package) = item.header {
+        if package.is_pqc_enabled() {
+            status_info.push_str(&format!(" {}", "[PQC]".bright_magenta()));
+        }
+
+        if package.is_expired() {
+            status_info.push_str(&format!(" {}", "[EXPIRED]".red().bold()));
+        } else if package.expires_at > 0 {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let remaining = (package.expires_at as i64 - now as i64) / 3600;
+            if remaining > 0 {
+                status_info.push_str(&format!(" {}{}h", "[⏰ ".yellow(), remaining));
+                status_info.push_str(&"]".yellow().to_string());
+            }
+        }
+    }
+
+    println!("   • {} ({})", item.entry.name.bright_green(), status_info);
+
+    if let Some(ref package) = item.header {
+        if let Some(ref filename) = package.filename {
+            println!(
+                "     {} {} ({:.2} KB)",
+                "File:".bright_black(),
+                filename,
+                package.original_size as f64 / 1024.0
+            );
+        }
+
+        if package.is_anonymous() {
+            println!(
+                "     {} hidden ({} recipients)",
+                "For:".bright_black(),
+                package.recipients.len()
+            );
+        } else if package.is_multi_recipient() {
+            let names = package
+                .recipients
+                .iter()
+                .map(|r| recipient_label(package, &r.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("     {} {}", "For:".bright_black(), names);
+        }
+    }
+}
+
+/// Contact name for a key-ID slot, or the ID itself if the key isn't known here
+fn recipient_label(package: &EncryptedPackage, slot: &str) -> String {
+    if !package.has_key_ids() {
+        return slot.to_string();
+    }
+
+    match keyring::known_name(slot) {
+        Ok(Some(name)) => name,
+        _ => slot.to_string(),
+    }
+}
@@ -1,25 +1,15 @@
-use crate::crypto;
+use crate::crypto::IdentityPublicKey;
 use crate::error::Result;
 use colored::Colorize;
 use std::fs;
 
 pub fn execute() -> Result<()> {
-    let key_dir = dirs::home_dir()
-        .ok_or_else(|| {
-            crate::error::HermesError::ConfigError("Could not find home directory".to_string())
-        })?
-        .join(".hermes")
-        .join("keys");
+    let key_dir = crate::config::keys_dir()?;
 
-    let recipients_dir = dirs::home_dir()
-        .ok_or_else(|| {
-            crate::error::HermesError::ConfigError("Could not find home directory".to_string())
-        })?
-        .join(".hermes")
-        .join("recipients");
+    let recipients_dir = crate::config::recipients_dir()?;
 
     println!("\n{}", "═".repeat(60).bright_cyan());
-    println!("{}", "🔑 RSA KEY MANAGEMENT".bright_white().bold());
+    println!("{}", "🔑 KEY MANAGEMENT".bright_white().bold());
     println!("{}", "═".repeat(60).bright_cyan());
 
     println!("\n🔐 {}", "Your Keys".bright_yellow().bold());
@@ -35,12 +25,13 @@ pub fn execute() -> Result<()> {
                     found_keys = true;
                     let name = path.file_stem().unwrap().to_str().unwrap();
 
-                    if let Ok(public_key) = crypto::load_public_key(path.to_str().unwrap()) {
-                        if let Ok(fingerprint) = crypto::get_key_fingerprint(&public_key) {
+                    if let Ok(public_key) = IdentityPublicKey::load(path.to_str().unwrap()) {
+                        if let Ok(fingerprint) = public_key.fingerprint() {
                             println!(
-                                "   • {} ({})",
+                                "   • {} ({}) {}",
                                 name.bright_green(),
-                                fingerprint.bright_black()
+                                fingerprint.bright_black(),
+                                public_key.key_type().name().bright_blue()
                             );
                         }
                     }
@@ -79,12 +70,13 @@ pub fn execute() -> Result<()> {
                     found_recipients = true;
                     let name = path.file_stem().unwrap().to_str().unwrap();
 
-                    if let Ok(public_key) = crypto::load_public_key(path.to_str().unwrap()) {
-                        if let Ok(fingerprint) = crypto::get_key_fingerprint(&public_key) {
+                    if let Ok(public_key) = IdentityPublicKey::load(path.to_str().unwrap()) {
+                        if let Ok(fingerprint) = public_key.fingerprint() {
                             println!(
-                                "   • {} ({})",
+                                "   • {} ({}) {}",
                                 name.bright_green(),
-                                fingerprint.bright_black()
+                                fingerprint.bright_black(),
+                                public_key.key_type().name().bright_blue()
                             );
                         }
                     }
@@ -2,6 +2,7 @@ pub mod checkin;
 pub mod completion;
 pub mod config;
 pub mod dms_disable;
+pub mod dms_run;
 pub mod dms_status;
 pub mod export_kyber_pubkey;
 pub mod export_pubkey;
@@ -11,12 +12,15 @@ pub mod init;
 pub mod interactive;
 pub mod keygen;
 pub mod key_list_archived;
+pub mod key_passwd;
 pub mod key_recover;
 pub mod key_rotate;
 pub mod key_split;
 pub mod list;
 pub mod list_keys;
+pub mod package;
 pub mod recv_batch;
+pub mod recv_dir;
 pub mod recv_file;
 pub mod recv_file_chunked;
 pub mod recv_msg;
@@ -30,5 +34,6 @@ pub mod sign_file;
 pub mod stego_capacity;
 pub mod stego_hide;
 pub mod stego_reveal;
+pub mod sweep;
 pub mod validate;
 pub mod verify_signature;
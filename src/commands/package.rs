@@ -0,0 +1,125 @@
+use crate::config::Settings;
+use crate::crypto::encrypt::replace_header;
+use crate::crypto::{Decryptor, EncryptedPackage};
+use crate::error::{HermesError, Result};
+use crate::transfer::{self, LocalDirTransport, Transport};
+use crate::ui;
+use std::io::BufReader;
+
+/// Give `names` access to an existing package by wrapping its data key for them.
+/// The data key is unwrapped with `recipient_name`, or whichever local key opens it.
+pub fn add_recipient(
+    file: &str,
+    names: &[String],
+    recipient_name: Option<&str>,
+    local: bool,
+) -> Result<()> {
+    ui::print_box_start("PACKAGE_ADD_RECIPIENT");
+
+    let (client, path) = open_target(file, local)?;
+
+    ui::print_box_line(">> Reading package header...");
+    let (reader, _) = client.open_file(&path)?;
+    let mut decryptor = Decryptor::new(BufReader::new(reader))?;
+    decryptor.header().check_editable()?;
+
+    if let Some(name) = recipient_name {
+        ui::print_box_line(&format!(">> Using recipient key: {name}"));
+        decryptor.unlock_with_recipient(name)?;
+    } else {
+        ui::print_box_line(">> Trying your keys...");
+        decryptor.unlock_with_own_keys()?;
+    }
+    let data_key = *decryptor.data_key().ok_or(HermesError::DecryptionFailed)?;
+    let mut header = decryptor.header().clone();
+    drop(decryptor);
+
+    ui::print_box_line(&format!(">> Wrapping data key for: {}", names.join(", ")));
+    header.add_recipients(&data_key, names)?;
+
+    ui::print_box_line(">> Rewriting header...");
+    rewrite(client.as_ref(), &path, &header)?;
+
+    ui::print_box_line("");
+    ui::print_box_end();
+
+    println!();
+    ui::print_success("RECIPIENTS ADDED");
+    ui::print_info("Package", &path);
+    ui::print_info("Added", &names.join(", "));
+    ui::print_info("Recipients", &header.recipients.len().to_string());
+    println!();
+
+    Ok(())
+}
+
+/// Drop the key slots of `names` from an existing package. No private key is needed,
+/// and a removed recipient who kept the data key or a copy can still decrypt.
+pub fn remove_recipient(file: &str, names: &[String], local: bool) -> Result<()> {
+    ui::print_box_start("PACKAGE_REMOVE_RECIPIENT");
+
+    let (client, path) = open_target(file, local)?;
+
+    ui::print_box_line(">> Reading package header...");
+    let mut header = transfer::read_package_header(client.as_ref(), &path)?;
+    header.remove_recipients(names)?;
+
+    ui::print_box_line(">> Rewriting header...");
+    rewrite(client.as_ref(), &path, &header)?;
+
+    ui::print_box_line("");
+    ui::print_box_end();
+
+    println!();
+    ui::print_success("RECIPIENTS REMOVED");
+    ui::print_info("Package", &path);
+    ui::print_info("Removed", &names.join(", "));
+    ui::print_info("Recipients", &header.recipients.len().to_string());
+    ui::print_info(
+        "Note",
+        "Re-encrypt to revoke access to copies already taken",
+    );
+    println!();
+
+    Ok(())
+}
+
+/// The transport holding `file`: this machine with `local`, otherwise the vault
+fn open_target(file: &str, local: bool) -> Result<(Box<dyn Transport>, String)> {
+    if local {
+        return Ok((Box::new(LocalDirTransport::new()), file.to_string()));
+    }
+
+    let config = Settings::load()?;
+
+    ui::print_box_line(">> Connecting to SFTP server...");
+    let client = transfer::connect(&config)?;
+
+    let path = if file.starts_with('/') || file.contains(':') {
+        file.to_string()
+    } else {
+        format!("{}/{}", config.paths.files, file)
+    };
+
+    Ok((client, path))
+}
+
+/// Copy the package under `header` next to the original, then swap it in
+fn rewrite(client: &dyn Transport, path: &str, header: &EncryptedPackage) -> Result<()> {
+    let temp_path = format!("{path}.rekey");
+
+    let (reader, _) = client.open_file(path)?;
+    let written = client
+        .create_file(&temp_path)
+        .and_then(|writer| replace_header(BufReader::new(reader), header, writer).map(drop));
+    if let Err(e) = written {
+        let _ = client.delete(&temp_path);
+        return Err(e);
+    }
+
+    let replaced = transfer::rename_over(client, &temp_path, path);
+    if replaced.is_err() {
+        let _ = client.delete(&temp_path);
+    }
+    replaced
+}
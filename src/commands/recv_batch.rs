@@ -2,7 +2,7 @@ use crate::config::Settings;
 use crate::crypto;
 use crate::error::{HermesError, Result};
 use crate::progress;
-use crate::transfer::SftpClient;
+use crate::transfer::{self, Transport};
 use crate::ui;
 use std::fs::File;
 use std::io::Write;
@@ -35,7 +35,7 @@ pub fn execute(
 
     // Load config and connect once
     let config = Settings::load()?;
-    let client = SftpClient::connect(&config)?;
+    let client = transfer::connect(&config)?;
 
     let mut successful = 0;
     let mut failed = 0;
@@ -55,7 +55,7 @@ pub fn execute(
             recipient_name,
             &output_path,
             &config,
-            &client,
+            client.as_ref(),
         ) {
             Ok(output_file) => {
                 ui::print_box_line(&format!("   ✓ Saved: {output_file}"));
@@ -106,7 +106,7 @@ fn process_single_file(
     recipient_name: Option<&str>,
     output_dir: &Option<PathBuf>,
     config: &Settings,
-    client: &SftpClient,
+    client: &dyn Transport,
 ) -> Result<String> {
     // Download
     let remote_path = if remote_file.starts_with('/') || remote_file.contains(':') {
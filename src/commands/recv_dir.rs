@@ -0,0 +1,108 @@
+use crate::archive;
+use crate::config::Settings;
+use crate::crypto;
+use crate::error::{HermesError, Result};
+use crate::transfer;
+use crate::ui;
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+pub fn execute(
+    remote_file: &str,
+    password: Option<&str>,
+    output_dir: Option<&str>,
+    recipient_name: Option<&str>,
+) -> Result<()> {
+    ui::print_box_start("ARCHIVE_DECRYPT");
+
+    let config = Settings::load()?;
+
+    ui::print_box_line(">> Connecting to SFTP server...");
+    let client = transfer::connect(&config)?;
+
+    let remote_path = if remote_file.starts_with('/') || remote_file.contains(':') {
+        remote_file.to_string()
+    } else {
+        format!("{}/{}", config.paths.files, remote_file)
+    };
+
+    ui::print_box_line(">> Opening encrypted archive...");
+    let (remote, _) = client.open_file(&remote_path)?;
+
+    let mut decryptor = crypto::Decryptor::new(BufReader::new(remote))?;
+    let package = decryptor.header().clone();
+
+    if package.is_expired() {
+        ui::print_box_end();
+        println!();
+        ui::print_error("FILE EXPIRED");
+        return Err(HermesError::DecryptionFailed);
+    }
+
+    if package.is_multi_recipient() {
+        if let Some(name) = recipient_name {
+            ui::print_box_line(&format!(">> Using recipient key: {name}"));
+            decryptor.unlock_with_recipient(name)?;
+        } else {
+            ui::print_box_line(">> Trying your keys...");
+            decryptor.unlock_with_own_keys()?;
+        }
+    } else if let Some(pwd) = password {
+        decryptor.unlock_with_password(pwd)?;
+    } else {
+        return Err(HermesError::ConfigError(
+            "Password required for password-encrypted file".to_string(),
+        ));
+    }
+
+    // Only the last component of the sender's name is trusted as a default
+    let default_dir = package
+        .filename
+        .as_deref()
+        .and_then(|name| Path::new(name).file_stem())
+        .and_then(|stem| Path::new(stem).file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or("decrypted_dir")
+        .to_string();
+    let output = output_dir.unwrap_or(&default_dir);
+    let dest = Path::new(output);
+    let created = !dest.exists();
+
+    ui::print_box_line(&format!(">> Extracting to: {output}"));
+
+    // Segments are authenticated as they are read; a failure part-way means
+    // the archive is tampered with or cut short, so drop what we created
+    let extracted = archive::extract_archive(&mut decryptor, dest).and_then(|stats| {
+        // tar stops at its end marker; read on so the final segment is checked
+        io::copy(&mut decryptor, &mut io::sink())?;
+        Ok(stats)
+    });
+    let stats = match extracted {
+        Ok(stats) => stats,
+        Err(e) => {
+            if created {
+                let _ = fs::remove_dir_all(dest);
+            }
+            return Err(e);
+        }
+    };
+
+    ui::print_box_line("");
+    ui::print_box_end();
+
+    println!();
+    ui::print_success("ARCHIVE DECRYPTED & EXTRACTED");
+    ui::print_info("Output", output);
+    ui::print_info("Files", &stats.files.to_string());
+    ui::print_info("Directories", &stats.dirs.to_string());
+    ui::print_info(
+        "Size",
+        &format!("{:.2} MB", stats.bytes as f64 / 1024.0 / 1024.0),
+    );
+    ui::print_info("Integrity", "VERIFIED ✓");
+    ui::print_status("UNLOCKED");
+    println!();
+
+    Ok(())
+}
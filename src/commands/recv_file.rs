@@ -1,24 +1,26 @@
 use crate::config::Settings;
 use crate::crypto;
+use crate::dms;
 use crate::error::{HermesError, Result};
 use crate::progress;
-use crate::transfer::SftpClient;
+use crate::transfer;
 use crate::ui;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Write};
 
 pub fn execute(
     remote_file: &str,
     password: Option<&str>,
     output_path: Option<&str>,
     recipient_name: Option<&str>,
+    envelope: Option<&str>,
 ) -> Result<()> {
     ui::print_box_start("FILE_DECRYPT");
 
     let config = Settings::load()?;
 
     ui::print_box_line(">> Connecting to SFTP server...");
-    let client = SftpClient::connect(&config)?;
+    let client = transfer::connect(&config)?;
 
     let remote_path = if remote_file.starts_with('/') || remote_file.contains(':') {
         remote_file.to_string()
@@ -26,16 +28,11 @@ pub fn execute(
         format!("{}/{}", config.paths.files, remote_file)
     };
 
-    // Download with progress
-    ui::print_box_line(">> Downloading encrypted file...");
-    let encrypted = client.download(&remote_path)?;
+    ui::print_box_line(">> Opening encrypted file...");
+    let (remote, remote_size) = client.open_file(&remote_path)?;
 
-    if encrypted.len() > 1024 * 1024 {
-        let progress = progress::create_download_progress(encrypted.len() as u64);
-        progress.finish_with_message("✓ Download complete".to_string());
-    }
-
-    let package = crate::crypto::encrypt::EncryptedPackage::from_bytes(&encrypted)?;
+    let mut decryptor = crypto::Decryptor::new(BufReader::new(remote))?;
+    let package = decryptor.header().clone();
 
     if package.is_expired() {
         ui::print_box_line("");
@@ -47,62 +44,39 @@ pub fn execute(
         return Err(HermesError::DecryptionFailed);
     }
 
-    // Decrypt with progress
+    // Unlock: for streamed packages this only recovers the data key
     ui::print_box_line(">> Decrypting and decompressing...");
 
-    let decrypted = if package.is_multi_recipient() {
+    if let Some(envelope_file) = envelope {
+        // Dead Man's Switch release: the data key comes from the envelope
+        let name = recipient_name.ok_or_else(|| {
+            HermesError::ConfigError("--envelope requires --recipient".to_string())
+        })?;
+        let envelope_path = if envelope_file.starts_with('/') || envelope_file.contains(':') {
+            envelope_file.to_string()
+        } else {
+            format!("{}/{}", config.paths.inbox, envelope_file)
+        };
+
+        ui::print_box_line(&format!(">> Opening release envelope as: {name}"));
+        let sealed = client.download(&envelope_path)?;
+        let release = dms::open_release_envelope(&sealed, name)?;
+        decryptor.unlock_with_key(&release.key()?)?;
+    } else if package.is_multi_recipient() {
         if let Some(name) = recipient_name {
             ui::print_box_line(&format!(">> Using recipient key: {name}"));
-
-            if encrypted.len() > 1024 * 1024 {
-                let spinner = progress::ProgressTracker::new_spinner("🔓 Decrypting");
-                spinner.set_message("Processing...".to_string());
-
-                let result = crypto::decrypt::decrypt_data_multi(&encrypted, name)?;
-
-                spinner.finish_with_message("✓ Decryption complete".to_string());
-                result
-            } else {
-                crypto::decrypt::decrypt_data_multi(&encrypted, name)?
-            }
+            decryptor.unlock_with_recipient(name)?;
         } else {
-            ui::print_box_line("");
-            ui::print_box_end();
-            println!();
-            ui::print_error("MULTI-RECIPIENT FILE");
-            ui::print_info(
-                "Recipients",
-                &package
-                    .recipients
-                    .iter()
-                    .map(|r| r.name.clone())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            );
-            println!();
-            println!("Use: hermes recv-file {remote_file} --recipient <n>");
-            println!();
-            return Err(HermesError::ConfigError(
-                "Recipient name required for multi-recipient file".to_string(),
-            ));
+            ui::print_box_line(">> Trying your keys...");
+            decryptor.unlock_with_own_keys()?;
         }
     } else if let Some(pwd) = password {
-        if encrypted.len() > 1024 * 1024 {
-            let spinner = progress::ProgressTracker::new_spinner("🔓 Decrypting");
-            spinner.set_message("Processing...".to_string());
-
-            let result = crypto::decrypt_data(&encrypted, pwd)?;
-
-            spinner.finish_with_message("✓ Decryption complete".to_string());
-            result
-        } else {
-            crypto::decrypt_data(&encrypted, pwd)?
-        }
+        decryptor.unlock_with_password(pwd)?;
     } else {
         return Err(HermesError::ConfigError(
             "Password required for password-encrypted file".to_string(),
         ));
-    };
+    }
 
     ui::print_box_line(">> Verifying file integrity...");
 
@@ -116,19 +90,21 @@ pub fn execute(
     ui::print_box_line(">> Writing to disk...");
     let mut file = File::create(output)?;
 
-    if decrypted.len() > 1024 * 1024 {
-        let progress = progress::ProgressTracker::new(decrypted.len() as u64, "💾 Writing");
-
-        let chunk_size = 8192;
-        for (i, chunk) in decrypted.chunks(chunk_size).enumerate() {
-            file.write_all(chunk)?;
-            progress.set_position((i * chunk_size) as u64);
+    let progress = progress::create_download_progress(remote_size);
+
+    // Every segment is authenticated before it is written, but a stream can
+    // still be cut short: don't leave a partial file behind on failure
+    let written = match write_decrypted(&mut decryptor, &mut file, &progress) {
+        Ok(written) => written,
+        Err(e) => {
+            progress.finish_and_clear();
+            drop(file);
+            let _ = fs::remove_file(output);
+            return Err(e);
         }
+    };
 
-        progress.finish_with_message("✓ File saved".to_string());
-    } else {
-        file.write_all(&decrypted)?;
-    }
+    progress.finish_with_message("✓ File saved".to_string());
 
     ui::print_box_line("");
     ui::print_box_end();
@@ -138,9 +114,13 @@ pub fn execute(
     ui::print_info("Output", output);
     ui::print_info(
         "Size",
-        &format!("{:.2} MB", decrypted.len() as f64 / 1024.0 / 1024.0),
+        &format!("{:.2} MB", written as f64 / 1024.0 / 1024.0),
     );
     ui::print_info("Integrity", "VERIFIED ✓");
+    match decryptor.sender() {
+        Some(sender) => ui::print_info("Sender", &format!("{sender} (signature verified ✓)")),
+        None => ui::print_info("Sender", "Not signed"),
+    }
     if package.compressed() {
         ui::print_info("Decompressed", "Yes");
     }
@@ -162,3 +142,24 @@ pub fn execute(
 
     Ok(())
 }
+
+fn write_decrypted<R: Read>(
+    decryptor: &mut crypto::Decryptor<R>,
+    file: &mut File,
+    progress: &progress::ProgressTracker,
+) -> Result<u64> {
+    let mut written = 0u64;
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let n = decryptor.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buffer[..n])?;
+        written += n as u64;
+        progress.set_position(written);
+    }
+
+    Ok(written)
+}
@@ -1,25 +1,28 @@
-use crate::chunking::{reassemble_chunks_from_manifest, ChunkManifest};
-use crate::config::Settings;
-use crate::crypto;
-use crate::error::{HermesError, Result};
-use crate::progress::ProgressTracker;
-use crate::transfer::SftpClient;
+use crate::chunking::{verify_reassembled, write_chunk, ChunkManifest};
+use crate::config::{hermes_home, Settings};
+use crate::error::Result;
+use crate::progress;
+use crate::transfer::journal::get_journal_path;
+use crate::transfer::{self, resume, TransportPool};
 use crate::ui;
-use std::fs;
+use indicatif::ProgressBar;
+use std::fs::{self, File};
 use std::path::PathBuf;
+use std::sync::Mutex;
 
 pub fn execute(
     remote_manifest: &str,
     password: Option<&str>,
     output: Option<&str>,
     recipient_name: Option<&str>,
+    jobs: usize,
 ) -> Result<()> {
     ui::print_box_start("CHUNKED_DECRYPT");
     ui::print_box_line(&format!(">> Manifest: {remote_manifest}"));
     ui::print_box_line("");
 
     let config = Settings::load()?;
-    let client = SftpClient::connect(&config)?;
+    let client = transfer::connect(&config)?;
 
     let remote_path = if remote_manifest.starts_with('/') || remote_manifest.contains(':') {
         remote_manifest.to_string()
@@ -27,23 +30,11 @@ pub fn execute(
         format!("{}/{}", config.paths.files, remote_manifest)
     };
 
-    ui::print_box_line(">> Step 1/4: Downloading manifest...");
+    ui::print_box_line(">> Step 1/3: Downloading manifest...");
     let encrypted_manifest = client.download(&remote_path)?;
 
-    let manifest_data = if let Some(name) = recipient_name {
-        crypto::decrypt::decrypt_data_multi(&encrypted_manifest, name)?
-    } else if let Some(pwd) = password {
-        crypto::decrypt_data(&encrypted_manifest, pwd)?
-    } else {
-        return Err(HermesError::ConfigError(
-            "Password or recipient name required".to_string(),
-        ));
-    };
-
-    let manifest_json =
-        String::from_utf8(manifest_data).map_err(|_| HermesError::DecryptionFailed)?;
-
-    let manifest = ChunkManifest::from_json(&manifest_json)?;
+    let manifest = ChunkManifest::decrypt(&encrypted_manifest, password, recipient_name)?;
+    manifest.verify_integrity()?;
     ui::print_box_line(&format!(
         "   ✓ Manifest loaded: {} chunks",
         manifest.total_chunks
@@ -54,61 +45,72 @@ pub fn execute(
     ));
     ui::print_box_line("");
 
-    let temp_dir = PathBuf::from("./hermes_chunks_temp");
-    if temp_dir.exists() {
-        fs::remove_dir_all(&temp_dir)?;
-    }
-    fs::create_dir_all(&temp_dir)?;
-
-    ui::print_box_line(">> Step 2/4: Downloading and decrypting chunks...");
-
-    for (index, chunk) in manifest.chunks.iter().enumerate() {
-        let progress_msg = format!(
-            "[{}/{}] Chunk {}",
-            index + 1,
-            manifest.total_chunks,
-            chunk.index + 1
-        );
-        ui::print_box_line(&format!("   {progress_msg}"));
-
-        let encrypted_chunk = client.download(&chunk.encrypted_path)?;
-
-        let decrypted_chunk = if let Some(name) = recipient_name {
-            crypto::decrypt::decrypt_data_multi(&encrypted_chunk, name)?
-        } else if let Some(pwd) = password {
-            crypto::decrypt_data(&encrypted_chunk, pwd)?
-        } else {
-            return Err(HermesError::ConfigError(
-                "Password or recipient name required".to_string(),
-            ));
-        };
-
-        let local_chunk_path = temp_dir.join(format!(
-            "{}.chunk.{:03}",
-            manifest.original_filename,
-            chunk.index + 1
-        ));
-        fs::write(&local_chunk_path, &decrypted_chunk)?;
-    }
-
-    ui::print_box_line("");
-    ui::print_box_line(">> Step 3/4: Reassembling file...");
-    let reassemble_progress = ProgressTracker::new_spinner("Reassembling");
-
     let output_path = if let Some(out) = output {
         PathBuf::from(out)
     } else {
         PathBuf::from(&manifest.original_filename)
     };
 
-    reassemble_chunks_from_manifest(&manifest, &temp_dir, &output_path)?;
-    reassemble_progress.finish_and_clear();
-    ui::print_box_line(&format!("   ✓ File reassembled: {}", output_path.display()));
+    // Encrypted chunks land here first so an interrupted run resumes where it stopped
+    let cache_name = if manifest.manifest_id.is_empty() {
+        &manifest.file_hash
+    } else {
+        &manifest.manifest_id
+    };
+    let download_dir = hermes_home()?.join("downloads").join(cache_name);
+    fs::create_dir_all(&download_dir)?;
+
+    ui::print_box_line(&format!(
+        ">> Step 2/3: Downloading and decrypting chunks ({jobs} jobs)..."
+    ));
+
+    // Reuse the manifest connection as the first worker
+    let mut transports = vec![client];
+    for _ in 1..jobs.min(manifest.chunks.len()) {
+        transports.push(transfer::connect(&config)?);
+    }
+    let mut pool = TransportPool::from_transports(transports);
+    let journal = get_journal_path()?;
+
+    // Decrypted chunks go straight to their place in the output; no plaintext elsewhere
+    let output_file = Mutex::new(File::create(&output_path)?);
+    let progress = progress::create_download_progress(manifest.total_size);
+
+    let decrypted = pool.map(&manifest.chunks, |client, chunk| {
+        let cached_chunk = download_dir.join(format!("chunk.{:03}.enc", chunk.index + 1));
+        if !cached_chunk.exists() {
+            resume::download_to_file_with_journal(
+                client,
+                &chunk.encrypted_path,
+                &cached_chunk,
+                &journal,
+                &ProgressBar::hidden(),
+            )?;
+        }
+        let encrypted_chunk = fs::read(&cached_chunk)?;
+
+        // Fails if the chunk was swapped, reordered or taken from another manifest
+        let decrypted_chunk =
+            manifest.decrypt_chunk(chunk, &encrypted_chunk, password, recipient_name)?;
+
+        write_chunk(&output_file, &manifest, chunk, &decrypted_chunk)?;
+        progress.inc(chunk.size);
+
+        Ok(())
+    });
+    progress.finish_and_clear();
+    drop(output_file);
 
     ui::print_box_line("");
-    ui::print_box_line(">> Step 4/4: Cleanup...");
-    fs::remove_dir_all(&temp_dir)?;
-    ui::print_box_line("   ✓ Temporary files removed");
+    ui::print_box_line(">> Step 3/3: Verifying file...");
+    let verified = decrypted.and_then(|_| verify_reassembled(&manifest, &output_path));
+    if let Err(e) = verified {
+        // Don't leave a partial or unverified file behind
+        let _ = fs::remove_file(&output_path);
+        return Err(e);
+    }
+    fs::remove_dir_all(&download_dir)?;
+    ui::print_box_line(&format!("   ✓ File verified: {}", output_path.display()));
 
     ui::print_box_end();
 
@@ -1,8 +1,9 @@
 use crate::config::Settings;
 use crate::crypto;
 use crate::error::{HermesError, Result};
-use crate::transfer::SftpClient;
+use crate::transfer;
 use crate::ui;
+use std::io::Read;
 
 pub fn execute(
     remote_file: &str,
@@ -14,7 +15,7 @@ pub fn execute(
     let config = Settings::load()?;
 
     ui::print_box_line(">> Connecting to SFTP server...");
-    let client = SftpClient::connect(&config)?;
+    let client = transfer::connect(&config)?;
 
     let remote_path = if remote_file.starts_with('/') || remote_file.contains(':') {
         remote_file.to_string()
@@ -25,7 +26,8 @@ pub fn execute(
     ui::print_box_line(">> Downloading encrypted message...");
     let encrypted = client.download(&remote_path)?;
 
-    let package = crate::crypto::encrypt::EncryptedPackage::from_bytes(&encrypted)?;
+    let mut decryptor = crypto::Decryptor::new(&encrypted[..])?;
+    let package = decryptor.header().clone();
 
     if package.is_expired() {
         ui::print_box_line("");
@@ -39,41 +41,27 @@ pub fn execute(
 
     ui::print_box_line(">> Decrypting message...");
 
-    let decrypted = if package.is_multi_recipient() {
+    if package.is_multi_recipient() {
         if let Some(name) = recipient_name {
             ui::print_box_line(&format!(">> Using recipient key: {name}"));
-            crypto::decrypt::decrypt_data_multi(&encrypted, name)?
+            decryptor.unlock_with_recipient(name)?;
         } else {
-            ui::print_box_line("");
-            ui::print_box_end();
-            println!();
-            ui::print_error("MULTI-RECIPIENT MESSAGE");
-            ui::print_info(
-                "Recipients",
-                &package
-                    .recipients
-                    .iter()
-                    .map(|r| r.name.clone())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            );
-            println!();
-            println!("Use: hermes recv-msg {remote_file} --recipient <name>");
-            println!();
-            return Err(HermesError::ConfigError(
-                "Recipient name required for multi-recipient message".to_string(),
-            ));
+            ui::print_box_line(">> Trying your keys...");
+            decryptor.unlock_with_own_keys()?;
         }
     } else if let Some(pwd) = password {
-        crypto::decrypt_data(&encrypted, pwd)?
+        decryptor.unlock_with_password(pwd)?;
     } else {
         return Err(HermesError::ConfigError(
             "Password required for password-encrypted message".to_string(),
         ));
-    };
+    }
 
     ui::print_box_line(">> Verifying integrity...");
 
+    let mut decrypted = Vec::new();
+    decryptor.read_to_end(&mut decrypted)?;
+
     let message = String::from_utf8(decrypted).map_err(|_| HermesError::DecryptionFailed)?;
 
     ui::print_box_line("");
@@ -83,6 +71,10 @@ pub fn execute(
     ui::print_success("MESSAGE DECRYPTED");
     ui::print_info("Content", &message);
     ui::print_info("Length", &format!("{} chars", message.len()));
+    match decryptor.sender() {
+        Some(sender) => ui::print_info("Sender", &format!("{sender} (signature verified ✓)")),
+        None => ui::print_info("Sender", "Not signed"),
+    }
     if package.is_multi_recipient() {
         ui::print_info("Type", "Multi-recipient");
     }
@@ -2,7 +2,7 @@ use crate::config::Settings;
 use crate::crypto;
 use crate::error::{HermesError, Result};
 use crate::progress;
-use crate::transfer::SftpClient;
+use crate::transfer::{self, Transport};
 use crate::ui;
 use chrono::Local;
 use std::fs::File;
@@ -27,7 +27,7 @@ pub fn execute(
 
     // Load config and connect once
     let config = Settings::load()?;
-    let client = SftpClient::connect(&config)?;
+    let client = transfer::connect(&config)?;
 
     let mut successful = 0;
     let mut failed = 0;
@@ -47,7 +47,7 @@ pub fn execute(
             ttl_hours,
             &recipients,
             &config,
-            &client,
+            client.as_ref(),
         ) {
             Ok(remote_path) => {
                 ui::print_box_line(&format!("   ✓ Success: {remote_path}"));
@@ -98,7 +98,7 @@ fn process_single_file(
     ttl_hours: Option<u64>,
     recipients: &Option<Vec<String>>,
     config: &Settings,
-    client: &SftpClient,
+    client: &dyn Transport,
 ) -> Result<String> {
     let path = Path::new(file_path);
     if !path.exists() {
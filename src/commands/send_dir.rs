@@ -1,8 +1,9 @@
+use crate::archive;
 use crate::config::Settings;
 use crate::crypto;
 use crate::error::{HermesError, Result};
 use crate::progress;
-use crate::transfer::SftpClient;
+use crate::transfer::{self, Transport};
 use crate::ui;
 use chrono::Local;
 use std::fs::{self, File};
@@ -15,6 +16,7 @@ pub fn execute(
     ttl_hours: Option<u64>,
     recipients: Option<Vec<String>>,
     recursive: bool,
+    archive: bool,
 ) -> Result<()> {
     let path = Path::new(dir_path);
     if !path.exists() {
@@ -27,6 +29,10 @@ pub fn execute(
         )));
     }
 
+    if archive {
+        return send_archive(path, password, ttl_hours, recipients);
+    }
+
     ui::print_box_start("DIRECTORY_ENCRYPT");
     ui::print_box_line(&format!(">> Directory: {dir_path}"));
     ui::print_box_line(&format!(">> Recursive: {recursive}"));
@@ -46,7 +52,7 @@ pub fn execute(
 
     // Load config and connect once
     let config = Settings::load()?;
-    let client = SftpClient::connect(&config)?;
+    let client = transfer::connect(&config)?;
 
     let mut successful = 0;
     let mut failed = 0;
@@ -71,7 +77,7 @@ pub fn execute(
             ttl_hours,
             &recipients,
             &config,
-            &client,
+            client.as_ref(),
         ) {
             Ok(remote_path) => {
                 ui::print_box_line(&format!("   ✓ {remote_path}"));
@@ -116,6 +122,83 @@ pub fn execute(
     }
 }
 
+/// Stream the whole tree as one tar inside a single package
+fn send_archive(
+    path: &Path,
+    password: Option<&str>,
+    ttl_hours: Option<u64>,
+    recipients: Option<Vec<String>>,
+) -> Result<()> {
+    let dirname = path
+        .canonicalize()?
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| HermesError::FileNotFound("Invalid directory name".to_string()))?;
+
+    ui::print_box_start("ARCHIVE_ENCRYPT");
+    ui::print_box_line(&format!(">> Directory: {}", path.display()));
+
+    let content_size = archive::content_size(path)?;
+    ui::print_box_line(&format!(
+        ">> Size: {} bytes ({:.2} MB)",
+        content_size,
+        content_size as f64 / 1024.0 / 1024.0
+    ));
+    ui::print_box_line("");
+
+    let config = Settings::load()?;
+    let client = transfer::connect(&config)?;
+
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    let final_path = format!("{}/{}_{}.tar.enc", config.paths.files, dirname, timestamp);
+    let archive_name = Some(format!("{dirname}.tar"));
+
+    ui::print_box_line(">> Archiving, encrypting and uploading...");
+    let remote_file = client.create_file(&final_path)?;
+
+    // The size is of the files inside; tar headers and padding come on top
+    let encryptor = if let Some(recips) = recipients {
+        crypto::Encryptor::with_recipients(
+            remote_file,
+            recips,
+            false,
+            archive_name,
+            content_size,
+            ttl_hours,
+        )?
+    } else if let Some(pwd) = password {
+        crypto::Encryptor::with_password(remote_file, pwd, archive_name, content_size, ttl_hours)?
+    } else {
+        return Err(HermesError::ConfigError(
+            "Either password or recipients required".to_string(),
+        ));
+    };
+
+    let (encryptor, stats) = archive::write_archive(path, encryptor)?;
+    encryptor.finish()?;
+    ui::print_box_line(&format!("   ✓ {final_path}"));
+
+    ui::print_box_line("");
+    ui::print_box_end();
+
+    println!();
+    ui::print_success("ARCHIVE ENCRYPTION COMPLETE");
+    ui::print_info("Files", &stats.files.to_string());
+    ui::print_info("Directories", &stats.dirs.to_string());
+    if stats.skipped > 0 {
+        ui::print_info(
+            "Skipped",
+            &format!("{} (symlinks and special files)", stats.skipped),
+        );
+    }
+    ui::print_info("Remote Path", &final_path);
+    ui::print_status("COMPLETE");
+    println!();
+
+    Ok(())
+}
+
 fn collect_files(path: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
@@ -154,7 +237,7 @@ fn process_single_file(
     ttl_hours: Option<u64>,
     recipients: &Option<Vec<String>>,
     config: &Settings,
-    client: &SftpClient,
+    client: &dyn Transport,
 ) -> Result<String> {
     let filename = file_path
         .file_name()
@@ -1,24 +1,34 @@
 use crate::config::Settings;
 use crate::crypto;
-use crate::dms::{get_registry_path, DeadManSwitch, DmsRegistry};
+use crate::dms::{
+    get_registry_path, store_release_envelope, DeadManSwitch, DmsAction, DmsOptions, DmsRegistry,
+};
 use crate::error::{HermesError, Result};
+use crate::notify::NotifierConfig;
 use crate::progress;
-use crate::transfer::SftpClient;
+use crate::transfer;
 use crate::ui;
 use chrono::Local;
+use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute(
     file_path: &str,
     password: Option<&str>,
     remote_path: Option<&str>,
     ttl_hours: Option<u64>,
     recipients: Option<Vec<String>>,
-    dms_hours: Option<u64>,
+    dms: Option<DmsOptions>,
     use_pqc: bool,
+    sign_as: Option<&str>,
+    anonymous: bool,
+    editable_recipients: bool,
 ) -> Result<()> {
+    let dms_hours = dms.as_ref().map(|d| d.timeout_hours);
+
     let path = Path::new(file_path);
     if !path.exists() {
         return Err(HermesError::FileNotFound(file_path.to_string()));
@@ -33,66 +43,78 @@ pub fn execute(
     ui::print_box_start(title);
     ui::print_box_line(&format!(">> File: {}", filename));
     if use_pqc {
-        ui::print_box_line(">> Mode: Hybrid RSA + Kyber (Post-Quantum)");
+        ui::print_box_line(">> Mode: Hybrid RSA/X25519 + ML-KEM (Post-Quantum)");
     }
-    if let Some(hours) = dms_hours {
-        ui::print_box_line(&format!(">> Dead Man's Switch: {} hours", hours));
+    if let Some(ref options) = dms {
+        ui::print_box_line(&format!(
+            ">> Dead Man's Switch: {} hours",
+            options.timeout_hours
+        ));
+    }
+    if let Some(name) = sign_as {
+        ui::print_box_line(&format!(">> Signed as: {name}"));
     }
     ui::print_box_line("");
 
-    let mut file = File::open(path)?;
-    let file_size = file.metadata()?.len();
-    let mut plaintext = Vec::new();
+    if password.is_none() && recipients.is_none() {
+        return Err(HermesError::ConfigError(
+            "Either password or recipients required".to_string(),
+        ));
+    }
+    if use_pqc && recipients.is_none() {
+        return Err(HermesError::ConfigError(
+            "PQC mode requires recipients, not password".to_string(),
+        ));
+    }
 
-    if file_size > 1024 * 1024 {
-        let progress = progress::create_encryption_progress(file_size);
-        let mut buffer = vec![0u8; 8192];
+    if let Some(release_to) = dms.as_ref().and_then(|d| d.release_to.as_ref()) {
+        let recipients_dir = crate::config::recipients_dir()?;
 
-        loop {
-            let bytes_read = file.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
+        for name in release_to {
+            if !recipients_dir.join(format!("{name}.pub")).exists() {
+                return Err(HermesError::ConfigError(format!(
+                    "Public key not found for DMS release recipient: {name}"
+                )));
             }
-            plaintext.extend_from_slice(&buffer[..bytes_read]);
-            progress.inc(bytes_read as u64);
         }
-        progress.finish_with_message("✓ Read complete".to_string());
-    } else {
-        file.read_to_end(&mut plaintext)?;
     }
 
-    let spinner = progress::ProgressTracker::new_spinner("Encrypting");
-    spinner.set_message("Processing...".to_string());
+    let signer = sign_as.map(crypto::SenderKey::load).transpose()?;
 
-    let encrypted = if let Some(recips) = recipients {
-        if use_pqc {
-            spinner.set_message("Encrypting with RSA + Kyber...".to_string());
+    let mut file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+
+    // The signature is written before the content, so hash the file up front
+    let content_hash = match signer {
+        Some(_) => {
+            let hashing = progress::ProgressTracker::new_spinner("Hashing");
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            file.seek(SeekFrom::Start(0))?;
+            hashing.finish_and_clear();
+            Some(hasher.finalize().into())
         }
-        crypto::encrypt::encrypt_data_multi(
-            &plaintext,
-            None,
-            Some(filename.to_string()),
-            ttl_hours,
-            Some(recips),
-            use_pqc,
-        )?
-    } else if let Some(pwd) = password {
-        if use_pqc {
+        None => None,
+    };
+
+    let config = Settings::load()?;
+
+    if let Some(ref options) = dms {
+        // The email goes through the config file's SMTP channel unless the switch has one
+        if options.notification_email.is_some()
+            && !config
+                .notifiers
+                .iter()
+                .chain(&options.notifiers)
+                .any(NotifierConfig::is_smtp)
+        {
             return Err(HermesError::ConfigError(
-                "PQC mode requires recipients, not password".to_string(),
+                "DMS email needs an SMTP notifier in the config file".to_string(),
             ));
         }
-        crypto::encrypt_data(&plaintext, pwd, Some(filename.to_string()), ttl_hours)?
-    } else {
-        return Err(HermesError::ConfigError(
-            "Either password or recipients required".to_string(),
-        ));
-    };
-
-    spinner.finish_and_clear();
+    }
 
-    let config = Settings::load()?;
-    let client = SftpClient::connect(&config)?;
+    let client = transfer::connect(&config)?;
 
     let final_path = if let Some(custom_path) = remote_path {
         custom_path.to_string()
@@ -106,22 +128,118 @@ pub fn execute(
         )
     };
 
-    let upload_progress = progress::create_upload_progress(encrypted.len() as u64);
-    client.upload(&encrypted, &final_path)?;
-    upload_progress.finish_with_message("✓ Upload complete".to_string());
+    // Encrypt into a temporary file, one segment at a time. It only takes the final
+    // name once complete, so a failed send never leaves a partial package behind.
+    let temp_path = format!("{final_path}{}", transfer::resume::PART_SUFFIX);
+    let encrypt = || -> Result<_> {
+        let remote_file = client.create_file(&temp_path)?;
 
-    if let Some(hours) = dms_hours {
+        let mut encryptor = if let Some(recips) = recipients {
+            crypto::Encryptor::with_recipients(
+                remote_file,
+                recips,
+                use_pqc,
+                Some(filename.to_string()),
+                file_size,
+                ttl_hours,
+            )?
+        } else if let Some(pwd) = password {
+            crypto::Encryptor::with_password(
+                remote_file,
+                pwd,
+                Some(filename.to_string()),
+                file_size,
+                ttl_hours,
+            )?
+        } else {
+            return Err(HermesError::ConfigError(
+                "Either password or recipients required".to_string(),
+            ));
+        };
+
+        if anonymous {
+            encryptor.hide_recipients()?;
+        }
+        if editable_recipients {
+            encryptor.allow_recipient_edits()?;
+        }
+        if let (Some(signer), Some(content_hash)) = (&signer, content_hash) {
+            encryptor.sign_as(signer, content_hash)?;
+        }
+
+        let progress = progress::create_encryption_progress(file_size);
+        let mut buffer = vec![0u8; 64 * 1024];
+
+        loop {
+            let bytes_read = file.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            encryptor.write_all(&buffer[..bytes_read])?;
+            progress.inc(bytes_read as u64);
+        }
+
+        // Close the remote file before moving it into place
+        let data_key = *encryptor.data_key();
+        drop(encryptor.finish()?);
+        transfer::rename_over(client.as_ref(), &temp_path, &final_path)?;
+        progress.finish_with_message("✓ Encrypted & uploaded".to_string());
+
+        Ok(data_key)
+    };
+
+    let data_key = match encrypt() {
+        Ok(data_key) => data_key,
+        Err(e) => {
+            let _ = client.delete(&temp_path);
+            return Err(e);
+        }
+    };
+
+    // Seal the release envelope only once the package is in place.
+    // The watcher never sees the data key.
+    let release_envelope = match dms.as_ref().and_then(|d| d.release_to.clone()) {
+        Some(release_to) => Some((
+            release_to.clone(),
+            store_release_envelope(&final_path, &data_key, release_to, use_pqc)?,
+        )),
+        None => None,
+    };
+
+    if let Some(ref options) = dms {
         let registry_path = get_registry_path()?;
         let mut registry = DmsRegistry::load_from_file(&registry_path)?;
 
-        let dms = DeadManSwitch::new(final_path.clone(), hours);
-        registry.add(dms);
+        let action = if let Some((recipients, envelope_path)) = release_envelope {
+            DmsAction::Release {
+                recipients,
+                envelope_path: envelope_path.to_string_lossy().into_owned(),
+            }
+        } else if options.notify_only {
+            DmsAction::Notify
+        } else {
+            DmsAction::Delete
+        };
+
+        let switch = DeadManSwitch::new(final_path.clone(), options.timeout_hours)
+            .with_profile(config.profile.clone())
+            .with_action(action)
+            .with_notifications(
+                options.notification_email.clone(),
+                options.notifiers.clone(),
+            );
+        ui::print_box_line("");
+        ui::print_box_line(&format!(">> DMS action: {}", switch.action.describe()));
+        if let Some(ref email) = switch.notification_email {
+            ui::print_box_line(&format!(">> DMS notifications: {email}"));
+        }
+        registry.add(switch);
         registry.save_to_file(&registry_path)?;
 
         ui::print_box_line("");
         ui::print_box_line(&format!(
             ">> DMS enabled: Check-in required every {} hours",
-            hours
+            options.timeout_hours
         ));
     }
 
@@ -130,6 +248,13 @@ pub fn execute(
     println!();
     ui::print_success("ENCRYPTION COMPLETE");
     ui::print_info("Remote Path", &final_path);
+    if let Some(ref signer) = signer {
+        let algorithm = match signer.classical_algorithm() {
+            Some(classical) => format!("{} + {classical}", signer.algorithm().name()),
+            None => signer.algorithm().name().to_string(),
+        };
+        ui::print_info("Signed As", &format!("{} ({algorithm})", signer.name()));
+    }
     if let Some(hours) = dms_hours {
         ui::print_info("DMS Timeout", &format!("{} hours", hours));
     }
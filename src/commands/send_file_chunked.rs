@@ -1,24 +1,31 @@
-use crate::chunking::split_file_into_chunks;
+use crate::chunking::{plan_chunks, read_chunk};
 use crate::config::Settings;
-use crate::crypto;
+use crate::crypto::keyring;
+use crate::dedup::{get_index_path, ChunkIndex, IndexedChunk};
 use crate::error::{HermesError, Result};
-use crate::progress::ProgressTracker;
-use crate::transfer::SftpClient;
+use crate::progress::{self, ProgressTracker};
+use crate::transfer::journal::get_journal_path;
+use crate::transfer::{resume, TransportPool};
 use crate::ui;
-use chrono::Local;
-use std::fs;
-use std::path::{Path, PathBuf};
+use indicatif::ProgressBar;
+use std::path::Path;
 
 pub fn execute(
     file_path: &str,
     password: Option<&str>,
     ttl_hours: Option<u64>,
     recipients: Option<Vec<String>>,
+    jobs: usize,
 ) -> Result<()> {
     let path = Path::new(file_path);
     if !path.exists() {
         return Err(HermesError::FileNotFound(file_path.to_string()));
     }
+    if password.is_none() && recipients.is_none() {
+        return Err(HermesError::ConfigError(
+            "Either password or recipients required".to_string(),
+        ));
+    }
 
     let file_size = path.metadata()?.len();
     let filename = path
@@ -35,107 +42,122 @@ pub fn execute(
     ));
     ui::print_box_line("");
 
-    let temp_dir = PathBuf::from("./hermes_chunks_temp");
-    if temp_dir.exists() {
-        fs::remove_dir_all(&temp_dir)?;
-    }
-    fs::create_dir_all(&temp_dir)?;
-
-    ui::print_box_line(">> Step 1/4: Splitting file into chunks...");
-    let split_progress = ProgressTracker::new_spinner("Splitting");
-    let manifest = split_file_into_chunks(path, &temp_dir)?;
+    // Chunks are read from the source as they are encrypted; no plaintext touches the disk
+    ui::print_box_line(">> Step 1/3: Hashing chunks...");
+    let split_progress = ProgressTracker::new_spinner("Hashing");
+    let manifest = plan_chunks(path)?;
     split_progress.finish_and_clear();
-    ui::print_box_line(&format!("   ✓ Created {} chunks", manifest.total_chunks));
+    ui::print_box_line(&format!("   ✓ Planned {} chunks", manifest.total_chunks));
     ui::print_box_line("");
 
-    ui::print_box_line(">> Step 2/4: Encrypting chunks...");
+    ui::print_box_line(&format!(
+        ">> Step 2/3: Encrypting and uploading ({jobs} jobs)..."
+    ));
     let config = Settings::load()?;
-    let client = SftpClient::connect(&config)?;
+    let mut pool = TransportPool::connect(&config, jobs.min(manifest.chunks.len()))?;
+    let journal = get_journal_path()?;
+
+    // Remote names reveal neither the file name nor when it was sent
+    let base_remote_path = format!("{}/{}", config.paths.files, manifest.manifest_id);
+
+    // Chunks can be reused only for the same recipient keys and when nothing expires
+    let dedup_set = match (&recipients, ttl_hours) {
+        (Some(recips), None) => {
+            let keys = recips
+                .iter()
+                .map(|name| keyring::recipient_ids(name))
+                .collect::<Result<Vec<_>>>()?;
+            Some(ChunkIndex::set_key(&config.profile, &keys))
+        }
+        _ => None,
+    };
+    let index_path = get_index_path()?;
+    let mut index = match dedup_set {
+        Some(_) => ChunkIndex::load_from_file(&index_path)?,
+        None => ChunkIndex::default(),
+    };
 
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let base_remote_path = format!("{}/{}_{}", config.paths.files, filename, timestamp);
+    let progress = progress::create_upload_progress(file_size);
 
-    let mut encrypted_manifest = manifest.clone();
-    encrypted_manifest.chunks.clear();
-
-    for (index, chunk) in manifest.chunks.iter().enumerate() {
-        let progress_msg = format!(
-            "[{}/{}] Chunk {}",
-            index + 1,
-            manifest.total_chunks,
-            chunk.index + 1
-        );
-        ui::print_box_line(&format!("   {progress_msg}"));
-
-        let chunk_path = temp_dir.join(&chunk.encrypted_path);
-        let chunk_data = fs::read(&chunk_path)?;
-
-        let encrypted = if let Some(recips) = &recipients {
-            crypto::encrypt::encrypt_data_multi(
-                &chunk_data,
-                None,
-                Some(chunk.encrypted_path.clone()),
-                ttl_hours,
-                Some(recips.clone()),
-                false,
-            )?
-        } else if let Some(pwd) = password {
-            crypto::encrypt_data(
-                &chunk_data,
-                pwd,
-                Some(chunk.encrypted_path.clone()),
-                ttl_hours,
-            )?
-        } else {
-            return Err(HermesError::ConfigError(
-                "Either password or recipients required".to_string(),
-            ));
-        };
+    let uploaded = pool.map(&manifest.chunks, |client, chunk| {
+        let mut encrypted_chunk = chunk.clone();
 
-        let remote_chunk_path = format!("{}.chunk.{:03}.enc", base_remote_path, chunk.index + 1);
-        client.upload(&encrypted, &remote_chunk_path)?;
+        let known = dedup_set
+            .as_deref()
+            .and_then(|set| index.get(set, &chunk.hash));
+        if let Some(known) = known {
+            // Only if the server still has it; sweep or delete may have removed it
+            let present = client
+                .stat(&known.remote_path)
+                .is_ok_and(|entry| entry.size == known.encrypted_size);
+            if present {
+                encrypted_chunk.encrypted_path = known.remote_path.clone();
+                encrypted_chunk.origin = Some(known.origin.clone());
+                progress.inc(chunk.size);
+                return Ok((encrypted_chunk, None));
+            }
+        }
+
+        let chunk_data = read_chunk(path, &manifest, chunk)?;
+
+        let encrypted =
+            manifest.encrypt_chunk(chunk, &chunk_data, password, recipients.clone(), ttl_hours)?;
 
-        let mut encrypted_chunk = chunk.clone();
+        let remote_chunk_path = format!("{}.chunk.{:03}.enc", base_remote_path, chunk.index + 1);
+        resume::upload_with_journal(
+            client,
+            &encrypted,
+            &remote_chunk_path,
+            &journal,
+            &ProgressBar::hidden(),
+        )?;
+        progress.inc(chunk.size);
+
+        let indexed = IndexedChunk {
+            remote_path: remote_chunk_path.clone(),
+            encrypted_size: encrypted.len() as u64,
+            origin: manifest.origin_of(chunk),
+        };
         encrypted_chunk.encrypted_path = remote_chunk_path;
-        encrypted_manifest.add_chunk(encrypted_chunk);
+        Ok((encrypted_chunk, Some(indexed)))
+    });
+    progress.finish_and_clear();
 
-        fs::remove_file(&chunk_path)?;
-    }
+    // Results come back in chunk order regardless of which worker finished first
+    let mut encrypted_manifest = manifest.clone();
+    let mut new_chunks = Vec::new();
+    encrypted_manifest.chunks = uploaded?
+        .into_iter()
+        .map(|(chunk, indexed)| {
+            if let Some(indexed) = indexed {
+                new_chunks.push((chunk.hash.clone(), indexed));
+            }
+            chunk
+        })
+        .collect();
+    let reused = encrypted_manifest.chunks.len() - new_chunks.len();
+    ui::print_box_line(&format!(
+        "   ✓ Uploaded {} chunks, reused {reused}",
+        new_chunks.len()
+    ));
 
     ui::print_box_line("");
-    ui::print_box_line(">> Step 3/4: Creating manifest...");
-
-    let manifest_json = encrypted_manifest.to_json()?;
-    let encrypted_manifest_data = if let Some(recips) = &recipients {
-        crypto::encrypt::encrypt_data_multi(
-            manifest_json.as_bytes(),
-            None,
-            Some(format!("{filename}.manifest")),
-            ttl_hours,
-            Some(recips.clone()),
-            false,
-        )?
-    } else if let Some(pwd) = password {
-        crypto::encrypt_data(
-            manifest_json.as_bytes(),
-            pwd,
-            Some(format!("{filename}.manifest")),
-            ttl_hours,
-        )?
-    } else {
-        return Err(HermesError::ConfigError(
-            "Either password or recipients required".to_string(),
-        ));
-    };
+    ui::print_box_line(">> Step 3/3: Creating manifest...");
+
+    let encrypted_manifest_data =
+        encrypted_manifest.encrypt(password, recipients.clone(), ttl_hours)?;
 
     let remote_manifest_path = format!("{base_remote_path}.manifest.enc");
-    client.upload(&encrypted_manifest_data, &remote_manifest_path)?;
+    pool.first()
+        .upload(&encrypted_manifest_data, &remote_manifest_path)?;
     ui::print_box_line(&format!("   ✓ Manifest: {remote_manifest_path}"));
 
-    ui::print_box_line("");
-    ui::print_box_line(">> Step 4/4: Cleanup...");
-    fs::remove_dir_all(&temp_dir)?;
-    ui::print_box_line("   ✓ Temporary files removed");
+    if let Some(set) = &dedup_set {
+        for (hash, indexed) in new_chunks {
+            index.insert(set, hash, indexed);
+        }
+        index.save_to_file(&index_path)?;
+    }
 
     ui::print_box_end();
 
@@ -143,6 +165,7 @@ pub fn execute(
     ui::print_success("CHUNKED ENCRYPTION COMPLETE");
     ui::print_info("Original File", filename);
     ui::print_info("Total Chunks", &encrypted_manifest.total_chunks.to_string());
+    ui::print_info("Reused Chunks", &reused.to_string());
     ui::print_info("File Hash", &encrypted_manifest.file_hash);
     ui::print_info("Manifest", &remote_manifest_path);
     ui::print_status("COMPLETE");
@@ -1,41 +1,58 @@
 use crate::config::Settings;
 use crate::crypto;
 use crate::error::Result;
-use crate::transfer::SftpClient;
+use crate::transfer;
 use crate::ui;
 use chrono::Local;
+use sha2::{Digest, Sha256};
+use std::io::Write;
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute(
     message: &str,
     password: Option<&str>,
     remote_path: Option<&str>,
     ttl_hours: Option<u64>,
     recipients: Option<Vec<String>>,
+    sign_as: Option<&str>,
+    anonymous: bool,
+    editable_recipients: bool,
 ) -> Result<()> {
     ui::print_box_start("MESSAGE_ENCRYPT");
 
     ui::print_box_line(&format!(">> Length: {} chars", message.len()));
 
-    let encrypted = if let Some(recips) = recipients {
+    let signer = sign_as.map(crypto::SenderKey::load).transpose()?;
+    let size = message.len() as u64;
+
+    let mut encryptor = if let Some(recips) = recipients {
         ui::print_box_line(&format!(">> Recipients: {}", recips.join(", ")));
-        ui::print_box_line(">> Encrypting with RSA hybrid encryption...");
-        crypto::encrypt::encrypt_data_multi(
-            message.as_bytes(),
-            None,
-            None,
-            ttl_hours,
-            Some(recips),
-            false, // PQC not yet supported for messages
-        )?
+        ui::print_box_line(">> Encrypting with hybrid public-key encryption...");
+        // PQC not yet supported for messages
+        crypto::Encryptor::with_recipients(Vec::new(), recips, false, None, size, ttl_hours)?
     } else if let Some(pwd) = password {
         ui::print_box_line(">> Encrypting message...");
-        crypto::encrypt_data(message.as_bytes(), pwd, None, ttl_hours)?
+        crypto::Encryptor::with_password(Vec::new(), pwd, None, size, ttl_hours)?
     } else {
         return Err(crate::error::HermesError::ConfigError(
             "Either password or recipients required".to_string(),
         ));
     };
 
+    if anonymous {
+        ui::print_box_line(">> Recipients: hidden");
+        encryptor.hide_recipients()?;
+    }
+    if editable_recipients {
+        encryptor.allow_recipient_edits()?;
+    }
+    if let Some(ref signer) = signer {
+        ui::print_box_line(&format!(">> Signing as: {}", signer.name()));
+        encryptor.sign_as(signer, Sha256::digest(message.as_bytes()).into())?;
+    }
+    encryptor.write_all(message.as_bytes())?;
+    let encrypted = encryptor.finish()?;
+
     if let Some(hours) = ttl_hours {
         ui::print_box_line(&format!(">> Self-destruct: {hours} hours"));
     }
@@ -43,7 +60,7 @@ pub fn execute(
     ui::print_box_line(">> Uploading to SFTP vault...");
 
     let config = Settings::load()?;
-    let client = SftpClient::connect(&config)?;
+    let client = transfer::connect(&config)?;
 
     let final_path = if let Some(custom_path) = remote_path {
         custom_path.to_string()
@@ -61,6 +78,9 @@ pub fn execute(
     ui::print_success("MESSAGE SECURED");
     ui::print_info("Remote", &final_path);
     ui::print_info("Size", &format!("{} bytes", encrypted.len()));
+    if let Some(ref signer) = signer {
+        ui::print_info("Signed As", signer.name());
+    }
     if let Some(hours) = ttl_hours {
         ui::print_info("Expires", &format!("in {hours} hours"));
     } else {
@@ -16,10 +16,7 @@ pub fn execute(file_path: &str, key_name: &str, output: Option<&str>) -> Result<
     ui::print_box_line(&format!(">> File: {}", filename));
     ui::print_box_line(&format!(">> Signer: {}", key_name));
 
-    let keys_dir = dirs::home_dir()
-        .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-        .join(".hermes")
-        .join("keys");
+    let keys_dir = crate::config::keys_dir()?;
 
     let dilithium_key_path = keys_dir.join(format!("{}_dilithium.pem", key_name));
     if !dilithium_key_path.exists() {
@@ -29,14 +26,20 @@ pub fn execute(file_path: &str, key_name: &str, output: Option<&str>) -> Result<
         )));
     }
 
+    let passphrase = crypto::keystore::unlock_passphrase(&[&dilithium_key_path], key_name)?;
+
     ui::print_box_line(">> Loading signing key...");
-    let secret_key = crypto::load_dilithium_secret_key(dilithium_key_path.to_str().unwrap())?;
+    let secret_key = crypto::load_dilithium_secret_key(
+        dilithium_key_path.to_str().unwrap(),
+        passphrase.as_deref(),
+    )?;
 
     ui::print_box_line(">> Reading file...");
     let file_data = fs::read(path)?;
 
-    ui::print_box_line(">> Signing with Dilithium-5...");
-    let signed_data = crypto::sign_message(&file_data, &secret_key);
+    let algorithm = secret_key.algorithm().name();
+    ui::print_box_line(&format!(">> Signing with {algorithm}..."));
+    let signed_data = crypto::sign_message(&file_data, &secret_key)?;
 
     let output_path = if let Some(out) = output {
         out.to_string()
@@ -53,7 +56,7 @@ pub fn execute(file_path: &str, key_name: &str, output: Option<&str>) -> Result<
     ui::print_success("FILE SIGNED");
     ui::print_info("Input", file_path);
     ui::print_info("Signature", &output_path);
-    ui::print_info("Algorithm", "Dilithium-5 (NIST PQC)");
+    ui::print_info("Algorithm", &format!("{algorithm} (NIST PQC)"));
     ui::print_info("Size", &format!("{} bytes", signed_data.len()));
     println!();
 
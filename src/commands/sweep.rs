@@ -0,0 +1,164 @@
+use crate::config::Settings;
+use crate::error::{HermesError, Result};
+use crate::transfer::{self, Transport};
+use crate::ui;
+use chrono::{DateTime, Local, Utc};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// An expired package found by the sweeper
+#[derive(Debug, Clone)]
+pub struct SweptPackage {
+    pub path: String,
+    pub filename: Option<String>,
+    pub expires_at: u64,
+    /// Why the package couldn't be deleted; it is retried on the next pass
+    pub error: Option<String>,
+}
+
+pub fn execute(daemon: bool, interval_minutes: u64, dry_run: bool) -> Result<()> {
+    if !daemon {
+        return run_pass(dry_run);
+    }
+
+    if interval_minutes == 0 {
+        return Err(HermesError::ConfigError(
+            "Sweep interval must be at least 1 minute".to_string(),
+        ));
+    }
+
+    println!("Sweeper running every {interval_minutes} minute(s). Press Ctrl+C to stop.");
+
+    loop {
+        // A dropped connection shouldn't stop the daemon; retry next pass
+        if let Err(e) = run_pass(dry_run) {
+            ui::print_error(&format!("Sweep failed: {e}"));
+        }
+        std::thread::sleep(Duration::from_secs(interval_minutes * 60));
+    }
+}
+
+fn run_pass(dry_run: bool) -> Result<()> {
+    let title = if dry_run { "SWEEP_DRY_RUN" } else { "SWEEP" };
+    ui::print_box_start(title);
+
+    let config = Settings::load()?;
+    let client = transfer::connect(&config)?;
+
+    ui::print_box_line(">> Scanning vault for expired packages...");
+    let swept = sweep(client.as_ref(), &config, dry_run)?;
+
+    let mut failed = 0;
+    for package in &swept {
+        match package.error {
+            Some(ref error) => {
+                failed += 1;
+                ui::print_box_line(&format!("   ! Failed: {} ({error})", package.path));
+            }
+            None => ui::print_box_line(&format!("   ✗ {}", package.path)),
+        }
+    }
+
+    ui::print_box_line("");
+    ui::print_box_end();
+
+    println!();
+    if dry_run {
+        ui::print_success("SWEEP DRY RUN COMPLETE");
+        ui::print_info("Would Delete", &swept.len().to_string());
+    } else {
+        ui::print_success("SWEEP COMPLETE");
+        ui::print_info("Deleted", &(swept.len() - failed).to_string());
+        if failed > 0 {
+            ui::print_info("Failed (will retry)", &failed.to_string());
+        }
+        if swept.len() > failed {
+            ui::print_info("Log", &get_sweep_log_path()?.display().to_string());
+        }
+    }
+    ui::print_info(
+        "Time",
+        &Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    );
+    println!();
+
+    Ok(())
+}
+
+/// Find expired packages in every vault directory and shred them. A package that
+/// can't be deleted is returned with its error and doesn't stop the pass; a
+/// directory that can't be listed fails the pass.
+pub fn sweep(
+    client: &dyn Transport,
+    config: &Settings,
+    dry_run: bool,
+) -> Result<Vec<SweptPackage>> {
+    let mut swept = Vec::new();
+
+    for dir in [
+        &config.paths.outbox,
+        &config.paths.inbox,
+        &config.paths.files,
+    ] {
+        for entry in client.list(dir)? {
+            if entry.is_dir || !entry.name.ends_with(".enc") {
+                continue;
+            }
+
+            // Legacy JSON packages and foreign files have no readable header
+            let Ok(header) = transfer::read_package_header(client, &entry.path) else {
+                continue;
+            };
+
+            if !header.is_expired() {
+                continue;
+            }
+
+            let mut package = SweptPackage {
+                path: entry.path.clone(),
+                filename: header.filename.clone(),
+                expires_at: header.expires_at,
+                error: None,
+            };
+
+            if !dry_run {
+                match transfer::shred(client, &entry.path) {
+                    Ok(()) => log_swept(&package)?,
+                    Err(e) => package.error = Some(e.to_string()),
+                }
+            }
+
+            swept.push(package);
+        }
+    }
+
+    Ok(swept)
+}
+
+pub fn get_sweep_log_path() -> Result<PathBuf> {
+    crate::config::state_file("sweep.log")
+}
+
+fn log_swept(package: &SweptPackage) -> Result<()> {
+    let expired = DateTime::<Utc>::from_timestamp(package.expires_at as i64, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default();
+
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_sweep_log_path()?)?;
+
+    writeln!(
+        log,
+        "{}\tdeleted\t{}\t{}\texpired {}",
+        Utc::now().to_rfc3339(),
+        package.path,
+        package.filename.as_deref().unwrap_or("-"),
+        expired
+    )?;
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use crate::config::Settings;
 use crate::error::{HermesError, Result};
-use crate::transfer::SftpClient;
+use crate::transfer;
 use crate::ui;
 use std::path::Path;
 
@@ -97,7 +97,7 @@ pub fn execute(test_connection: bool) -> Result<()> {
     if test_connection {
         ui::print_box_line(">> Testing SFTP connection...");
 
-        match SftpClient::connect(&config) {
+        match transfer::connect(&config) {
             Ok(_) => {
                 ui::print_box_line("✓ Connection successful");
             }
@@ -15,18 +15,12 @@ pub fn execute(signed_file: &str, signer_name: &str, output: Option<&str>) -> Re
     ui::print_box_line(&format!(">> Signed file: {}", signed_file));
     ui::print_box_line(&format!(">> Signer: {}", signer_name));
 
-    let recipients_dir = dirs::home_dir()
-        .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-        .join(".hermes")
-        .join("recipients");
+    let recipients_dir = crate::config::recipients_dir()?;
 
     let dilithium_key_path = recipients_dir.join(format!("{}_dilithium.pub", signer_name));
     if !dilithium_key_path.exists() {
         // Try keys directory (own key)
-        let keys_dir = dirs::home_dir()
-            .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-            .join(".hermes")
-            .join("keys");
+        let keys_dir = crate::config::keys_dir()?;
         let own_key_path = keys_dir.join(format!("{}_dilithium.pub", signer_name));
 
         if !own_key_path.exists() {
@@ -43,11 +37,7 @@ pub fn execute(signed_file: &str, signer_name: &str, output: Option<&str>) -> Re
     {
         recipients_dir.join(format!("{}_dilithium.pub", signer_name))
     } else {
-        dirs::home_dir()
-            .unwrap()
-            .join(".hermes")
-            .join("keys")
-            .join(format!("{}_dilithium.pub", signer_name))
+        crate::config::keys_dir()?.join(format!("{}_dilithium.pub", signer_name))
     };
 
     ui::print_box_line(">> Loading verification key...");
@@ -70,7 +60,10 @@ pub fn execute(signed_file: &str, signer_name: &str, output: Option<&str>) -> Re
     println!();
     ui::print_success("SIGNATURE VALID");
     ui::print_info("Signer", signer_name);
-    ui::print_info("Algorithm", "Dilithium-5 (NIST PQC)");
+    ui::print_info(
+        "Algorithm",
+        &format!("{} (NIST PQC)", public_key.algorithm().name()),
+    );
     ui::print_info("Original size", &format!("{} bytes", original_data.len()));
     if let Some(out) = output {
         ui::print_info("Extracted to", out);
@@ -0,0 +1,34 @@
+use crate::error::{HermesError, Result};
+use std::path::PathBuf;
+
+/// Overrides the Hermes home directory (keys, recipients, registries, config)
+pub const HOME_ENV: &str = "HERMES_HOME";
+
+/// Directory holding keys, recipients and local state: `$HERMES_HOME` or `~/.hermes`
+pub fn hermes_home() -> Result<PathBuf> {
+    if let Some(home) = std::env::var_os(HOME_ENV).filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(home));
+    }
+
+    dirs::home_dir()
+        .map(|home| home.join(".hermes"))
+        .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))
+}
+
+/// Our own keypairs
+pub fn keys_dir() -> Result<PathBuf> {
+    Ok(hermes_home()?.join("keys"))
+}
+
+/// Imported public keys of other people
+pub fn recipients_dir() -> Result<PathBuf> {
+    Ok(hermes_home()?.join("recipients"))
+}
+
+/// Path of a state file in the Hermes home, creating the directory if needed
+pub fn state_file(name: &str) -> Result<PathBuf> {
+    let home = hermes_home()?;
+    std::fs::create_dir_all(&home)?;
+
+    Ok(home.join(name))
+}
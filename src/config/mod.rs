@@ -1,3 +1,5 @@
+pub mod home;
 pub mod settings;
 
+pub use home::*;
 pub use settings::*;
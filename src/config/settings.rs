@@ -1,7 +1,11 @@
+use super::{hermes_home, HOME_ENV};
 use crate::error::{HermesError, Result};
+use crate::notify::NotifierConfig;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SftpConfig {
@@ -9,6 +13,57 @@ pub struct SftpConfig {
     pub port: u16,
     pub username: String,
     pub key_file: Option<String>,
+    #[serde(default)]
+    pub host_key_policy: HostKeyPolicy,
+    /// Authentication methods, tried in order
+    #[serde(default = "default_auth_methods")]
+    pub auth_methods: Vec<AuthMethod>,
+}
+
+/// SSH user authentication method
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMethod {
+    /// Keys held by ssh-agent (or Pageant on Windows)
+    Agent,
+    /// `key_file`, prompting for its passphrase if it is encrypted
+    Publickey,
+    Password,
+    KeyboardInteractive,
+}
+
+impl AuthMethod {
+    pub fn name(self) -> &'static str {
+        match self {
+            AuthMethod::Agent => "agent",
+            AuthMethod::Publickey => "publickey",
+            AuthMethod::Password => "password",
+            AuthMethod::KeyboardInteractive => "keyboard-interactive",
+        }
+    }
+
+    /// Method name as listed by the server
+    pub fn protocol_name(self) -> &'static str {
+        match self {
+            AuthMethod::Agent => "publickey",
+            other => other.name(),
+        }
+    }
+}
+
+fn default_auth_methods() -> Vec<AuthMethod> {
+    vec![AuthMethod::Agent, AuthMethod::Publickey]
+}
+
+/// How to treat server host keys missing from known_hosts
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKeyPolicy {
+    /// Ask to trust unknown hosts and changed keys, remembering the answer
+    #[default]
+    Tofu,
+    /// Only connect to hosts already in known_hosts; abort on any mismatch
+    Strict,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -18,15 +73,90 @@ pub struct PathsConfig {
     pub files: String,
 }
 
+/// Backend used to reach the vault paths
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    #[default]
+    Sftp,
+    /// Vault paths are local directories (shared mount, test directory)
+    Local,
+}
+
+/// Profile used when none is selected and the file has no `default_profile`
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Selects the profile when `--profile` is not given
+pub const PROFILE_ENV: &str = "HERMES_PROFILE";
+
+/// Path of the config file, overriding the platform default
+pub const CONFIG_ENV: &str = "HERMES_CONFIG";
+
+/// Environment variables that override fields of the active profile
+pub const OVERRIDE_ENV: &[&str] = &[
+    "HERMES_TRANSPORT",
+    "HERMES_SFTP_HOST",
+    "HERMES_SFTP_PORT",
+    "HERMES_SFTP_USERNAME",
+    "HERMES_SFTP_KEY_FILE",
+    "HERMES_SFTP_HOST_KEY_POLICY",
+    "HERMES_SFTP_AUTH_METHODS",
+    "HERMES_PATHS_INBOX",
+    "HERMES_PATHS_OUTBOX",
+    "HERMES_PATHS_FILES",
+    "HERMES_NOTIFIERS",
+];
+
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Use `name` instead of the default profile for the rest of the process (`--profile`)
+pub fn select_profile(name: &str) {
+    let _ = SELECTED_PROFILE.set(name.to_string());
+}
+
+/// Profile chosen with `--profile` or `HERMES_PROFILE`, if any
+pub fn selected_profile() -> Option<String> {
+    SELECTED_PROFILE
+        .get()
+        .cloned()
+        .or_else(|| env_var(PROFILE_ENV))
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// One vault server: how to reach it and where its directories live
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Settings {
+pub struct Profile {
+    #[serde(default)]
+    pub transport: TransportKind,
     pub sftp: SftpConfig,
     pub paths: PathsConfig,
 }
 
-impl Settings {
+/// The contents of config.toml
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConfigFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    /// Top-level server settings from before profiles; read as the "default" profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<TransportKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sftp: Option<SftpConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paths: Option<PathsConfig>,
+    /// Default DMS notification channels, used by switches without their own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notifiers: Vec<NotifierConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ConfigFile {
     pub fn load() -> Result<Self> {
-        let config_path = Self::config_file_path()?;
+        let config_path = Self::path()?;
 
         if !config_path.exists() {
             return Err(HermesError::ConfigError(
@@ -42,7 +172,7 @@ impl Settings {
     }
 
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_file_path()?;
+        let config_path = Self::path()?;
 
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent).map_err(|e| {
@@ -59,7 +189,16 @@ impl Settings {
         Ok(())
     }
 
-    fn config_file_path() -> Result<PathBuf> {
+    /// `$HERMES_CONFIG`, then `$HERMES_HOME/config.toml`, then the platform config directory
+    pub fn path() -> Result<PathBuf> {
+        if let Some(path) = env_var(CONFIG_ENV) {
+            return Ok(PathBuf::from(path));
+        }
+
+        if env_var(HOME_ENV).is_some() {
+            return Ok(hermes_home()?.join("config.toml"));
+        }
+
         let config_dir = dirs::config_dir().ok_or_else(|| {
             HermesError::ConfigError("Could not determine config directory".to_string())
         })?;
@@ -67,23 +206,380 @@ impl Settings {
         Ok(config_dir.join("hermes").join("config.toml"))
     }
 
+    /// Names of all profiles, including the top-level one
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.keys().cloned().collect();
+        if self.legacy_profile().is_some() && !self.profiles.contains_key(DEFAULT_PROFILE) {
+            names.insert(0, DEFAULT_PROFILE.to_string());
+        }
+        names
+    }
+
+    /// Profile used when `--profile` is not given
+    pub fn default_profile_name(&self) -> &str {
+        self.default_profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    pub fn profile(&self, name: &str) -> Result<Profile> {
+        if let Some(profile) = self.profiles.get(name) {
+            return Ok(profile.clone());
+        }
+
+        if name == DEFAULT_PROFILE {
+            if let Some(profile) = self.legacy_profile() {
+                return Ok(profile);
+            }
+        }
+
+        let available = self.profile_names();
+        Err(HermesError::ConfigError(if available.is_empty() {
+            format!("Profile '{name}' not found; the config has no profiles")
+        } else {
+            format!(
+                "Profile '{name}' not found (available: {})",
+                available.join(", ")
+            )
+        }))
+    }
+
+    /// Resolve the settings for `name`, or the default profile
+    pub fn settings(&self, name: Option<&str>) -> Result<Settings> {
+        let name = name.unwrap_or_else(|| self.default_profile_name());
+        let profile = self.profile(name)?;
+
+        Ok(Settings {
+            profile: name.to_string(),
+            transport: profile.transport,
+            sftp: profile.sftp,
+            paths: profile.paths,
+            notifiers: self.notifiers.clone(),
+        })
+    }
+
+    fn legacy_profile(&self) -> Option<Profile> {
+        Some(Profile {
+            transport: self.transport.unwrap_or_default(),
+            sftp: self.sftp.clone()?,
+            paths: self.paths.clone()?,
+        })
+    }
+}
+
+/// Settings of the active profile
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Name of the profile these settings came from
+    pub profile: String,
+    pub transport: TransportKind,
+    pub sftp: SftpConfig,
+    pub paths: PathsConfig,
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+impl Settings {
+    /// Load the profile chosen with `--profile`, or the default one, then apply
+    /// `HERMES_*` overrides. Without a config file the overrides apply to the defaults.
+    pub fn load() -> Result<Self> {
+        let lookup = |name: &str| env_var(name);
+        let has_overrides = OVERRIDE_ENV.iter().any(|name| lookup(name).is_some());
+
+        let mut settings = if ConfigFile::path()?.exists() || !has_overrides {
+            ConfigFile::load()?.settings(selected_profile().as_deref())?
+        } else {
+            Self::default_config()
+        };
+
+        settings.apply_overrides(lookup)?;
+        Ok(settings)
+    }
+
+    /// Load the profile `name`: the active settings, overrides included, when it is the
+    /// active profile, or else that profile from the config file as is
+    pub fn load_profile(name: &str) -> Result<Self> {
+        let active = Self::load()?;
+        if active.profile == name {
+            return Ok(active);
+        }
+        ConfigFile::load()?.settings(Some(name))
+    }
+
+    /// Replace fields with the values of the `HERMES_*` variables `lookup` finds
+    pub fn apply_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(value) = lookup("HERMES_TRANSPORT") {
+            self.transport = parse_env("HERMES_TRANSPORT", &value)?;
+        }
+        if let Some(value) = lookup("HERMES_SFTP_HOST") {
+            self.sftp.host = value;
+        }
+        if let Some(value) = lookup("HERMES_SFTP_PORT") {
+            self.sftp.port = value.parse().map_err(|e| {
+                HermesError::ConfigError(format!("Invalid HERMES_SFTP_PORT '{value}': {e}"))
+            })?;
+        }
+        if let Some(value) = lookup("HERMES_SFTP_USERNAME") {
+            self.sftp.username = value;
+        }
+        if let Some(value) = lookup("HERMES_SFTP_KEY_FILE") {
+            // "none" clears a key file set in the config, e.g. for agent-only auth
+            self.sftp.key_file = (value != "none").then_some(value);
+        }
+        if let Some(value) = lookup("HERMES_SFTP_HOST_KEY_POLICY") {
+            self.sftp.host_key_policy = parse_env("HERMES_SFTP_HOST_KEY_POLICY", &value)?;
+        }
+        if let Some(value) = lookup("HERMES_SFTP_AUTH_METHODS") {
+            self.sftp.auth_methods = value
+                .split(',')
+                .map(|method| parse_env("HERMES_SFTP_AUTH_METHODS", method.trim()))
+                .collect::<Result<_>>()?;
+        }
+        if let Some(value) = lookup("HERMES_PATHS_INBOX") {
+            self.paths.inbox = value;
+        }
+        if let Some(value) = lookup("HERMES_PATHS_OUTBOX") {
+            self.paths.outbox = value;
+        }
+        if let Some(value) = lookup("HERMES_PATHS_FILES") {
+            self.paths.files = value;
+        }
+        if let Some(value) = lookup("HERMES_NOTIFIERS") {
+            self.notifiers = serde_json::from_str(&value).map_err(|e| {
+                HermesError::ConfigError(format!("Invalid HERMES_NOTIFIERS (JSON array): {e}"))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Write these settings back as their profile, keeping other profiles
+    pub fn save(&self) -> Result<()> {
+        let mut file = match ConfigFile::path()?.exists() {
+            true => ConfigFile::load()?,
+            false => ConfigFile::default(),
+        };
+        self.merge_into(&mut file);
+
+        file.save()
+    }
+
+    /// Store these settings as their profile in `file`. Notifiers are shared by every
+    /// profile, so only a new file takes them; an existing file keeps its own.
+    fn merge_into(&self, file: &mut ConfigFile) {
+        if self.profile == DEFAULT_PROFILE && !file.profiles.contains_key(DEFAULT_PROFILE) {
+            file.transport = Some(self.transport);
+            file.sftp = Some(self.sftp.clone());
+            file.paths = Some(self.paths.clone());
+            if file.notifiers.is_empty() {
+                file.notifiers = self.notifiers.clone();
+            }
+        } else {
+            file.profiles.insert(
+                self.profile.clone(),
+                Profile {
+                    transport: self.transport,
+                    sftp: self.sftp.clone(),
+                    paths: self.paths.clone(),
+                },
+            );
+        }
+    }
+
     #[must_use]
     pub fn default_config() -> Self {
-        let username = std::env::var("USERNAME").unwrap_or_else(|_| "user".to_string());
-        let key_path = format!("C:\\Users\\{username}\\.ssh\\hermes_key");
+        let username = env_var("USER")
+            .or_else(|| env_var("USERNAME"))
+            .unwrap_or_else(|| "user".to_string());
+        let key_path = dirs::home_dir()
+            .map(|home| home.join(".ssh").join("hermes_key"))
+            .map(|path| path.to_string_lossy().into_owned());
+        let vault_dir = |name: &str| {
+            if cfg!(windows) {
+                format!("C:\\hermes_vault\\{name}")
+            } else {
+                format!("/srv/hermes_vault/{name}")
+            }
+        };
 
         Self {
+            profile: selected_profile().unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+            transport: TransportKind::Sftp,
             sftp: SftpConfig {
                 host: "localhost".to_string(),
                 port: 22,
                 username,
-                key_file: Some(key_path),
+                key_file: key_path,
+                host_key_policy: HostKeyPolicy::Tofu,
+                auth_methods: default_auth_methods(),
+            },
+            paths: PathsConfig {
+                inbox: vault_dir("inbox"),
+                outbox: vault_dir("outbox"),
+                files: vault_dir("files"),
+            },
+            notifiers: Vec::new(),
+        }
+    }
+}
+
+/// Parse an enum override the same way the config file spells it
+fn parse_env<T: serde::de::DeserializeOwned>(name: &str, value: &str) -> Result<T> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|e| HermesError::ConfigError(format!("Invalid {name} '{value}': {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY: &str = r#"
+[sftp]
+host = "vault.example.com"
+port = 22
+username = "alice"
+
+[paths]
+inbox = "/vault/inbox"
+outbox = "/vault/outbox"
+files = "/vault/files"
+"#;
+
+    fn profile(host: &str) -> Profile {
+        Profile {
+            transport: TransportKind::Sftp,
+            sftp: SftpConfig {
+                host: host.to_string(),
+                port: 22,
+                username: "deploy".to_string(),
+                key_file: None,
+                host_key_policy: HostKeyPolicy::Strict,
+                auth_methods: vec![AuthMethod::Agent],
             },
             paths: PathsConfig {
-                inbox: "C:\\hermes_vault\\inbox".to_string(),
-                outbox: "C:\\hermes_vault\\outbox".to_string(),
-                files: "C:\\hermes_vault\\files".to_string(),
+                inbox: "/in".to_string(),
+                outbox: "/out".to_string(),
+                files: "/files".to_string(),
             },
         }
     }
+
+    #[test]
+    fn test_legacy_config_is_default_profile() {
+        let file: ConfigFile = toml::from_str(LEGACY).unwrap();
+        let settings = file.settings(None).unwrap();
+
+        assert_eq!(settings.profile, DEFAULT_PROFILE);
+        assert_eq!(settings.sftp.host, "vault.example.com");
+        assert_eq!(settings.sftp.auth_methods, default_auth_methods());
+        assert_eq!(file.profile_names(), vec![DEFAULT_PROFILE]);
+    }
+
+    #[test]
+    fn test_named_profiles_roundtrip_and_selection() {
+        let mut file: ConfigFile = toml::from_str(LEGACY).unwrap();
+        file.profiles
+            .insert("prod".to_string(), profile("prod.example.com"));
+        file.profiles
+            .insert("staging".to_string(), profile("staging.example.com"));
+        file.default_profile = Some("prod".to_string());
+
+        let text = toml::to_string_pretty(&file).unwrap();
+        assert!(text.contains("[profiles.staging.sftp]"));
+        let file: ConfigFile = toml::from_str(&text).unwrap();
+
+        assert_eq!(file.settings(None).unwrap().sftp.host, "prod.example.com");
+        assert_eq!(
+            file.settings(Some("staging")).unwrap().sftp.host,
+            "staging.example.com"
+        );
+        assert_eq!(
+            file.settings(Some(DEFAULT_PROFILE)).unwrap().sftp.host,
+            "vault.example.com"
+        );
+
+        let err = file.settings(Some("qa")).unwrap_err().to_string();
+        assert!(err.contains("default, prod, staging"));
+    }
+
+    #[test]
+    fn test_saving_a_profile_keeps_notifiers() {
+        let mut file: ConfigFile = toml::from_str(LEGACY).unwrap();
+        file.notifiers =
+            serde_json::from_str(r#"[{"type":"webhook","url":"http://hook"}]"#).unwrap();
+
+        let mut work = Settings::default_config();
+        work.profile = "work".to_string();
+        work.merge_into(&mut file);
+
+        let text = toml::to_string_pretty(&file).unwrap();
+        let file: ConfigFile = toml::from_str(&text).unwrap();
+        assert_eq!(file.notifiers.len(), 1);
+        assert_eq!(file.settings(Some("work")).unwrap().notifiers.len(), 1);
+        assert_eq!(file.profile_names(), vec![DEFAULT_PROFILE, "work"]);
+    }
+
+    #[test]
+    fn test_env_overrides_replace_fields() {
+        let file: ConfigFile = toml::from_str(LEGACY).unwrap();
+        let mut settings = file.settings(None).unwrap();
+
+        let env: std::collections::HashMap<&str, &str> = [
+            ("HERMES_TRANSPORT", "local"),
+            ("HERMES_SFTP_PORT", "2222"),
+            ("HERMES_SFTP_KEY_FILE", "none"),
+            ("HERMES_SFTP_HOST_KEY_POLICY", "strict"),
+            ("HERMES_SFTP_AUTH_METHODS", "agent, keyboard-interactive"),
+            ("HERMES_PATHS_INBOX", "/data/inbox"),
+            (
+                "HERMES_NOTIFIERS",
+                r#"[{"type":"webhook","url":"http://hook"}]"#,
+            ),
+        ]
+        .into_iter()
+        .collect();
+        settings
+            .apply_overrides(|name| env.get(name).map(|v| v.to_string()))
+            .unwrap();
+
+        assert_eq!(settings.transport, TransportKind::Local);
+        assert_eq!(settings.sftp.host, "vault.example.com");
+        assert_eq!(settings.sftp.port, 2222);
+        assert_eq!(settings.sftp.key_file, None);
+        assert_eq!(settings.sftp.host_key_policy, HostKeyPolicy::Strict);
+        assert_eq!(
+            settings.sftp.auth_methods,
+            vec![AuthMethod::Agent, AuthMethod::KeyboardInteractive]
+        );
+        assert_eq!(settings.paths.inbox, "/data/inbox");
+        assert_eq!(settings.paths.outbox, "/vault/outbox");
+        assert_eq!(settings.notifiers.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_env_override_is_rejected() {
+        let mut settings = Settings::default_config();
+
+        let err = settings
+            .apply_overrides(|name| (name == "HERMES_SFTP_PORT").then(|| "ssh".to_string()))
+            .unwrap_err();
+        assert!(err.to_string().contains("HERMES_SFTP_PORT"));
+
+        let err = settings
+            .apply_overrides(|name| (name == "HERMES_TRANSPORT").then(|| "ftp".to_string()))
+            .unwrap_err();
+        assert!(err.to_string().contains("HERMES_TRANSPORT"));
+    }
+
+    #[test]
+    fn test_default_config_uses_platform_paths() {
+        let settings = Settings::default_config();
+
+        if cfg!(windows) {
+            assert!(settings.paths.inbox.starts_with("C:\\"));
+        } else {
+            assert_eq!(settings.paths.inbox, "/srv/hermes_vault/inbox");
+        }
+        if let Some(key_file) = settings.sftp.key_file {
+            assert!(key_file.ends_with("hermes_key"));
+            assert!(!key_file.starts_with("C:") || cfg!(windows));
+        }
+    }
 }
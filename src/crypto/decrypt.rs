@@ -1,6 +1,8 @@
-use crate::crypto::encrypt::EncryptedPackage;
+use crate::crypto::encrypt::{EncryptedPackage, RecipientKey};
+use crate::crypto::identity::IdentityPrivateKey;
+use crate::crypto::keyring;
 use crate::error::{HermesError, Result};
-use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
 use aes_gcm::{Aes256Gcm, Nonce};
 use argon2::password_hash::{PasswordHasher, SaltString};
 use argon2::Argon2;
@@ -10,96 +12,242 @@ use std::io::Read;
 
 pub fn decrypt_data(encrypted: &[u8], password: &str) -> Result<Vec<u8>> {
     let package = EncryptedPackage::from_bytes(encrypted)?;
+    let key = password_key(&package, password)?;
+    decrypt_with_key(&package, &key)
+}
 
-    let key = if package.is_multi_recipient() {
-        return Err(HermesError::DecryptionFailed);
-    } else {
-        let salt_str =
-            String::from_utf8(package.salt.clone()).map_err(|_| HermesError::DecryptionFailed)?;
-        let salt = SaltString::from_b64(&salt_str).map_err(|_| HermesError::DecryptionFailed)?;
-
-        derive_key(password, &salt)?
-    };
+pub fn decrypt_data_multi(encrypted: &[u8], recipient_name: &str) -> Result<Vec<u8>> {
+    decrypt_data_multi_bound(encrypted, recipient_name, &[])
+}
 
-    decrypt_with_key(encrypted, &key)
+/// Decrypt a package made by [`crate::crypto::encrypt::encrypt_data_bound`] with a password.
+pub fn decrypt_data_bound(encrypted: &[u8], password: &str, context: &[u8]) -> Result<Vec<u8>> {
+    let package = EncryptedPackage::from_bytes(encrypted)?;
+    let key = password_key(&package, password)?;
+    decrypt_with_key_bound(&package, &key, context)
 }
 
-pub fn decrypt_data_multi(encrypted: &[u8], recipient_name: &str) -> Result<Vec<u8>> {
+/// Decrypt a bound package with the local keys of `recipient_name`.
+pub fn decrypt_data_multi_bound(
+    encrypted: &[u8],
+    recipient_name: &str,
+    context: &[u8],
+) -> Result<Vec<u8>> {
     let package = EncryptedPackage::from_bytes(encrypted)?;
+    let candidates = recipient_keys(&package, Some(recipient_name))?;
+    first_that_opens(&candidates, |key| decrypt_with_key_bound(&package, key, context))
+}
 
-    if !package.is_multi_recipient() {
+/// Try each candidate data key in turn; authentication rejects the wrong ones
+pub(crate) fn first_that_opens<T>(
+    candidates: &[[u8; 32]],
+    mut open: impl FnMut(&[u8; 32]) -> Result<T>,
+) -> Result<T> {
+    let mut last_error = HermesError::DecryptionFailed;
+    for key in candidates {
+        match open(key) {
+            Ok(opened) => return Ok(opened),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// Derive the data key of a password-encrypted package.
+pub(crate) fn password_key(package: &EncryptedPackage, password: &str) -> Result<[u8; 32]> {
+    if package.is_multi_recipient() {
         return Err(HermesError::DecryptionFailed);
     }
 
-    let key_dir = dirs::home_dir()
-        .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-        .join(".hermes")
-        .join("keys");
+    let salt_str =
+        String::from_utf8(package.salt.clone()).map_err(|_| HermesError::DecryptionFailed)?;
+    let salt = SaltString::from_b64(&salt_str).map_err(|_| HermesError::DecryptionFailed)?;
+
+    derive_key(password, &salt)
+}
 
-    let private_key_path = key_dir.join(format!("{recipient_name}.pem"));
-    if !private_key_path.exists() {
-        return Err(HermesError::ConfigError(format!(
-            "Private key not found for: {recipient_name}"
-        )));
+/// Candidate data keys of a multi-recipient package: every slot that one of our keys
+/// (only `local_key`, if given) can unwrap. Slots are matched by key ID, by name on
+/// older packages, and tried one by one on anonymous packages.
+pub(crate) fn recipient_keys(
+    package: &EncryptedPackage,
+    local_key: Option<&str>,
+) -> Result<Vec<[u8; 32]>> {
+    if !package.is_multi_recipient() {
+        return Err(HermesError::DecryptionFailed);
     }
 
-    let private_key = crate::crypto::load_private_key(private_key_path.to_str().unwrap())?;
-
-    let recipient = package
-        .recipients
-        .iter()
-        .find(|r| r.name == recipient_name)
-        .ok_or(HermesError::DecryptionFailed)?;
-
-    // Hybrid decryption: Try Kyber first if PQC is enabled, then verify with RSA
-    let data_key = if package.is_pqc_enabled() && recipient.pq_encrypted_key.is_some() {
-        let kyber_key_path = key_dir.join(format!("{recipient_name}_kyber.pem"));
-        if !kyber_key_path.exists() {
-            return Err(HermesError::ConfigError(format!(
-                "Kyber private key not found for: {recipient_name}. This file requires PQC keys."
-            )));
+    let mut candidates = Vec::new();
+    let mut matched = false;
+
+    for key in keyring::local_keys(local_key)? {
+        let slots: Vec<&RecipientKey> = package
+            .recipients
+            .iter()
+            .filter(|slot| {
+                if package.is_anonymous() {
+                    true
+                } else if package.has_key_ids() {
+                    key.matches(&slot.name)
+                } else {
+                    slot.name == key.name
+                }
+            })
+            .collect();
+        if slots.is_empty() {
+            continue;
         }
+        matched = true;
 
-        let kyber_secret = crate::crypto::load_kyber_secret_key(kyber_key_path.to_str().unwrap())?;
-        let pq_encrypted = recipient.pq_encrypted_key.as_ref().unwrap();
+        let secrets = SlotKeys::load(&key.name, package.is_pqc_enabled())?;
+        for slot in slots {
+            match secrets.unwrap(package, slot) {
+                Ok(data_key) => candidates.push(data_key),
+                // On anonymous packages most slots belong to someone else
+                Err(_) if package.is_anonymous() => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
 
-        // Decrypt with Kyber
-        let kyber_key = crate::crypto::decrypt_with_kyber(pq_encrypted, &kyber_secret)?;
+    if !matched && local_key.is_none() {
+        return Err(HermesError::ConfigError(
+            "None of your keys is a recipient of this package".to_string(),
+        ));
+    }
+    if candidates.is_empty() {
+        return Err(HermesError::DecryptionFailed);
+    }
 
-        // Also decrypt with RSA for verification (hybrid security)
-        let rsa_key_bytes =
-            crate::crypto::decrypt_key_with_private(&recipient.encrypted_key, &private_key)?;
-        let mut rsa_key = [0u8; 32];
-        rsa_key.copy_from_slice(&rsa_key_bytes);
+    Ok(candidates)
+}
 
-        // Both keys must match for hybrid security
-        if kyber_key != rsa_key {
-            return Err(HermesError::DecryptionFailed);
-        }
+/// Private keys of one local identity, for unwrapping recipient slots
+struct SlotKeys {
+    name: String,
+    identity: IdentityPrivateKey,
+    kyber: Option<crate::crypto::KyberSecretKey>,
+}
 
-        kyber_key
-    } else {
-        // Legacy v1 or non-PQC: Use RSA only
-        let data_key_bytes =
-            crate::crypto::decrypt_key_with_private(&recipient.encrypted_key, &private_key)?;
+impl SlotKeys {
+    fn load(name: &str, need_kyber: bool) -> Result<Self> {
+        let key_dir = crate::config::keys_dir()?;
+        let private_key_path = key_dir.join(format!("{name}.pem"));
+        let kyber_key_path = key_dir.join(format!("{name}_kyber.pem"));
 
-        let mut key = [0u8; 32];
-        key.copy_from_slice(&data_key_bytes);
-        key
-    };
+        // Prompt once for a passphrase shared by the identity and Kyber keys
+        let passphrase =
+            crate::crypto::keystore::unlock_passphrase(&[&private_key_path, &kyber_key_path], name)?;
+
+        let identity =
+            IdentityPrivateKey::load(private_key_path.to_str().unwrap(), passphrase.as_deref())?;
+
+        let kyber = if need_kyber && kyber_key_path.exists() {
+            Some(crate::crypto::load_kyber_secret_key(
+                kyber_key_path.to_str().unwrap(),
+                passphrase.as_deref(),
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            identity,
+            kyber,
+        })
+    }
 
-    decrypt_with_key(encrypted, &data_key)
+    fn unwrap(&self, package: &EncryptedPackage, recipient: &RecipientKey) -> Result<[u8; 32]> {
+        match recipient.pq_encrypted_key {
+            Some(ref pq_encrypted) if package.is_pqc_enabled() => {
+                let kyber_secret = self.kyber.as_ref().ok_or_else(|| {
+                    HermesError::ConfigError(format!(
+                        "Kyber private key not found for: {}. This file requires PQC keys.",
+                        self.name
+                    ))
+                })?;
+                if kyber_secret.algorithm() != recipient.pq_algorithm {
+                    return Err(HermesError::DecryptionFailed);
+                }
+
+                // The data key is wrapped under a key derived from both secrets
+                if package.has_hybrid_kem() {
+                    return crate::crypto::hybrid::unwrap(
+                        &recipient.encrypted_key,
+                        pq_encrypted,
+                        &self.identity,
+                        recipient.classical_algorithm,
+                        kyber_secret,
+                    );
+                }
+
+                // Older hybrid packages: the KEM and RSA copies of the key must agree
+                let classical_key = self.unwrap_classical(recipient)?;
+                let kyber_key = crate::crypto::decrypt_with_kyber(pq_encrypted, kyber_secret)?;
+                if kyber_key != classical_key {
+                    return Err(HermesError::DecryptionFailed);
+                }
+
+                Ok(kyber_key)
+            }
+            // Legacy v1 or non-PQC: Use the identity key only
+            _ => self.unwrap_classical(recipient),
+        }
+    }
+
+    fn unwrap_classical(&self, recipient: &RecipientKey) -> Result<[u8; 32]> {
+        self.identity
+            .unwrap_key(&recipient.encrypted_key, recipient.classical_algorithm)
+    }
 }
 
-fn decrypt_with_key(encrypted: &[u8], key: &[u8; 32]) -> Result<Vec<u8>> {
-    let package = EncryptedPackage::from_bytes(encrypted)?;
+pub(crate) fn decrypt_with_key(package: &EncryptedPackage, key: &[u8; 32]) -> Result<Vec<u8>> {
+    decrypt_with_key_bound(package, key, &[])
+}
+
+fn decrypt_with_key_bound(
+    package: &EncryptedPackage,
+    key: &[u8; 32],
+    context: &[u8],
+) -> Result<Vec<u8>> {
+    if package.is_streamed() {
+        // Streams carry no external context; a bound package is never streamed
+        if !context.is_empty() {
+            return Err(HermesError::DecryptionFailed);
+        }
+        let payload = crate::crypto::stream::open_segments(package, key)?;
+        if package.is_signed() {
+            return crate::crypto::sender::open_signed(package, &payload).map(|(_, content)| content);
+        }
+        return Ok(payload);
+    }
+
+    // Sender signatures are only defined for streamed packages
+    if package.is_signed() {
+        return Err(HermesError::DecryptionFailed);
+    }
+
+    // Context only exists for v4+ packages, where the header is authenticated too
+    if !context.is_empty() && !package.has_authenticated_header() {
+        return Err(HermesError::DecryptionFailed);
+    }
 
     let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| HermesError::DecryptionFailed)?;
 
     let nonce = Nonce::from(package.nonce);
 
+    // v4+: a modified header (TTL, filename, flags...) fails authentication
+    let mut aad = package.associated_data();
+    aad.extend_from_slice(context);
     let decrypted = cipher
-        .decrypt(&nonce, package.ciphertext.as_ref())
+        .decrypt(
+            &nonce,
+            Payload {
+                msg: package.ciphertext.as_ref(),
+                aad: &aad,
+            },
+        )
         .map_err(|_| HermesError::DecryptionFailed)?;
 
     let plaintext = if package.compressed() {
@@ -0,0 +1,179 @@
+use crate::crypto::keystore;
+use crate::error::{HermesError, Result};
+use aws_lc_rs::encoding::{AsBigEndian, Curve25519SeedBin};
+use aws_lc_rs::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+pub(crate) const PUBLIC_KEY_LABEL: &str = "ED25519 PUBLIC KEY";
+pub(crate) const PRIVATE_KEY_LABEL: &str = "ED25519 PRIVATE KEY";
+const KEY_LEN: usize = 32;
+
+/// Ed25519 public key, the classical signing key of an X25519 identity
+#[derive(Clone)]
+pub struct Ed25519PublicKey {
+    bytes: [u8; KEY_LEN],
+}
+
+/// Ed25519 private key, stored as its 32-byte seed
+pub struct Ed25519SecretKey {
+    seed: [u8; KEY_LEN],
+}
+
+impl Ed25519PublicKey {
+    fn new(bytes: &[u8]) -> Result<Self> {
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| HermesError::ConfigError("Invalid Ed25519 public key".to_string()))?;
+        Ok(Self { bytes })
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Ed25519SecretKey {
+    fn new(seed: &[u8]) -> Result<Self> {
+        let seed = seed
+            .try_into()
+            .map_err(|_| HermesError::ConfigError("Invalid Ed25519 private key".to_string()))?;
+        Ok(Self { seed })
+    }
+
+    fn key_pair(&self) -> Result<Ed25519KeyPair> {
+        Ed25519KeyPair::from_seed_unchecked(&self.seed)
+            .map_err(|_| HermesError::ConfigError("Invalid Ed25519 private key".to_string()))
+    }
+
+    /// The matching public key
+    pub fn public_key(&self) -> Result<Ed25519PublicKey> {
+        Ed25519PublicKey::new(self.key_pair()?.public_key().as_ref())
+    }
+}
+
+/// Generate an Ed25519 keypair
+pub fn generate_ed25519_keypair() -> Result<(Ed25519PublicKey, Ed25519SecretKey)> {
+    let failed =
+        || HermesError::KeyGenerationFailed("Ed25519 keypair generation failed".to_string());
+
+    let key_pair = Ed25519KeyPair::generate().map_err(|_| failed())?;
+    let seed: Curve25519SeedBin = key_pair
+        .seed()
+        .map_err(|_| failed())?
+        .as_be_bytes()
+        .map_err(|_| failed())?;
+
+    Ok((
+        Ed25519PublicKey::new(key_pair.public_key().as_ref())?,
+        Ed25519SecretKey::new(seed.as_ref())?,
+    ))
+}
+
+/// Sign `message` with Ed25519
+pub fn sign_ed25519(message: &[u8], secret_key: &Ed25519SecretKey) -> Result<Vec<u8>> {
+    Ok(secret_key.key_pair()?.sign(message).as_ref().to_vec())
+}
+
+pub fn verify_ed25519(
+    signature: &[u8],
+    message: &[u8],
+    public_key: &Ed25519PublicKey,
+) -> Result<()> {
+    UnparsedPublicKey::new(&ED25519, &public_key.bytes)
+        .verify(message, signature)
+        .map_err(|_| HermesError::DecryptionFailed)
+}
+
+/// Save an Ed25519 public key to file
+pub fn save_ed25519_public_key(public_key: &Ed25519PublicKey, path: &Path) -> Result<()> {
+    fs::write(
+        path,
+        keystore::encode_pem(PUBLIC_KEY_LABEL, &public_key.bytes),
+    )
+    .map_err(|e| {
+        HermesError::KeyGenerationFailed(format!("Failed to save Ed25519 public key: {e}"))
+    })
+}
+
+/// Save an Ed25519 private key to file, encrypted when a passphrase is given
+pub fn save_ed25519_secret_key(
+    secret_key: &Ed25519SecretKey,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<()> {
+    let pem = keystore::encode_secret_pem(PRIVATE_KEY_LABEL, &secret_key.seed, passphrase)?;
+    keystore::write_secret_file(path, pem.as_bytes()).map_err(|e| {
+        HermesError::KeyGenerationFailed(format!("Failed to save Ed25519 private key: {e}"))
+    })
+}
+
+/// Load an Ed25519 public key from file
+pub fn load_ed25519_public_key(path: &str) -> Result<Ed25519PublicKey> {
+    let content = fs::read_to_string(path)?;
+    let (label, bytes) = keystore::decode_pem(&content)?;
+    if label != PUBLIC_KEY_LABEL {
+        return Err(HermesError::ConfigError(format!(
+            "Not an Ed25519 public key: {path}"
+        )));
+    }
+    Ed25519PublicKey::new(&bytes)
+}
+
+/// Load an Ed25519 private key, decrypting it if it is passphrase-protected
+pub fn load_ed25519_secret_key(path: &str, passphrase: Option<&str>) -> Result<Ed25519SecretKey> {
+    let content = fs::read_to_string(path)?;
+    let (label, bytes) = keystore::decode_secret_pem(&content, passphrase, path)?;
+    if label != PRIVATE_KEY_LABEL {
+        return Err(HermesError::ConfigError(format!(
+            "Not an Ed25519 private key: {path}"
+        )));
+    }
+    Ed25519SecretKey::new(&bytes)
+}
+
+/// Get fingerprint of Ed25519 public key
+pub fn get_ed25519_fingerprint(public_key: &Ed25519PublicKey) -> String {
+    hex::encode(&Sha256::digest(public_key.bytes)[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ed25519_sign_verify() {
+        let (public_key, secret_key) = generate_ed25519_keypair().unwrap();
+        let signature = sign_ed25519(b"message", &secret_key).unwrap();
+        assert_eq!(signature.len(), 64);
+
+        assert!(verify_ed25519(&signature, b"message", &public_key).is_ok());
+        assert!(verify_ed25519(&signature, b"other message", &public_key).is_err());
+
+        let (other_public_key, _) = generate_ed25519_keypair().unwrap();
+        assert!(verify_ed25519(&signature, b"message", &other_public_key).is_err());
+    }
+
+    #[test]
+    fn test_ed25519_key_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let public_path = dir.path().join("alice_ed25519.pub");
+        let secret_path = dir.path().join("alice_ed25519.pem");
+        let (public_key, secret_key) = generate_ed25519_keypair().unwrap();
+
+        save_ed25519_public_key(&public_key, &public_path).unwrap();
+        save_ed25519_secret_key(&secret_key, &secret_path, None).unwrap();
+
+        let loaded_public = load_ed25519_public_key(public_path.to_str().unwrap()).unwrap();
+        let loaded_secret = load_ed25519_secret_key(secret_path.to_str().unwrap(), None).unwrap();
+        assert_eq!(
+            loaded_secret.public_key().unwrap().as_bytes(),
+            public_key.as_bytes()
+        );
+
+        let signature = sign_ed25519(b"message", &loaded_secret).unwrap();
+        assert!(verify_ed25519(&signature, b"message", &loaded_public).is_ok());
+    }
+}
@@ -1,5 +1,7 @@
+use crate::crypto::identity::{ClassicalAlgorithm, IdentityPublicKey};
+use crate::crypto::pqc::KemAlgorithm;
 use crate::error::{HermesError, Result};
-use aes_gcm::aead::Aead;
+use aes_gcm::aead::{Aead, Payload};
 use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
 use argon2::password_hash::{PasswordHasher, SaltString};
 use argon2::Argon2;
@@ -7,21 +9,39 @@ use flate2::write::GzEncoder;
 use flate2::Compression;
 use rsa::rand_core::{OsRng, RngCore};
 use sha2::{Digest, Sha256};
-use std::io::Write;
-
-const MAGIC_BYTES: &[u8; 4] = b"HRMS";
-const VERSION: u8 = 0x02; // v2.0.0 with PQC support
-const FLAG_COMPRESSED: u8 = 0b00000001;
-const FLAG_MULTI_RECIPIENT: u8 = 0b00000010;
-const FLAG_PQC_ENABLED: u8 = 0b00000100;
+use std::io::{Read, Write};
+
+pub(crate) const MAGIC_BYTES: &[u8; 4] = b"HRMS";
+const VERSION: u8 = 0x08; // v8: recipient slots record their classical algorithm
+pub(crate) const STREAM_VERSION: u8 = 0x09; // v9: segmented streaming AEAD, v8 header
+const KEM_SLOT_VERSION: u8 = 0x06; // v6: recipient slots record their post-quantum KEM
+const V7_STREAM_VERSION: u8 = 0x07; // v7: segmented streaming AEAD, v6 header
+const AUTHENTICATED_HEADER_VERSION: u8 = 0x04; // v4: header authenticated as associated data
+const V5_STREAM_VERSION: u8 = 0x05; // v5: segmented streaming AEAD, authenticated header
+const LEGACY_STREAM_VERSION: u8 = 0x03; // v3: segmented streaming AEAD
+pub(crate) const FLAG_COMPRESSED: u8 = 0b00000001;
+pub(crate) const FLAG_MULTI_RECIPIENT: u8 = 0b00000010;
+pub(crate) const FLAG_PQC_ENABLED: u8 = 0b00000100;
+pub(crate) const FLAG_SIGNED: u8 = 0b00001000; // payload starts with a sender signature
+pub(crate) const FLAG_KEY_IDS: u8 = 0b00010000; // recipient slots hold key IDs, not names
+pub(crate) const FLAG_ANONYMOUS: u8 = 0b00100000; // recipient slots carry no identifier
+pub(crate) const FLAG_EDITABLE_RECIPIENTS: u8 = 0b01000000; // slots left out of the associated data
+pub(crate) const FLAG_HYBRID_KEM: u8 = 0b10000000; // PQC slots wrap the key under both secrets
 
 #[derive(Clone)]
 pub struct RecipientKey {
+    /// Key ID (see [`crate::crypto::keyring::key_id`]), a name on older packages,
+    /// or empty on anonymous packages
     pub name: String,
     pub encrypted_key: Vec<u8>,
-    pub pq_encrypted_key: Option<Vec<u8>>, // Kyber-encrypted key for hybrid mode
+    /// How `encrypted_key` is sealed; always RSA PKCS#1 v1.5 before v8
+    pub classical_algorithm: ClassicalAlgorithm,
+    pub pq_encrypted_key: Option<Vec<u8>>, // KEM-encrypted key for hybrid mode
+    /// KEM of `pq_encrypted_key`; always Kyber-768 before v6
+    pub pq_algorithm: KemAlgorithm,
 }
 
+#[derive(Clone)]
 pub struct EncryptedPackage {
     pub magic: [u8; 4],
     pub version: u8,
@@ -39,6 +59,27 @@ pub struct EncryptedPackage {
 impl EncryptedPackage {
     #[must_use]
     pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header_bytes();
+
+        // Streams are a sequence of self-delimiting segments, no length prefix
+        if !self.is_streamed() {
+            bytes.extend_from_slice(&(self.ciphertext.len() as u32).to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.ciphertext);
+
+        bytes
+    }
+
+    /// Serialize everything up to (but not including) the ciphertext.
+    #[must_use]
+    pub fn header_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.fixed_header_bytes();
+        self.write_recipients(&mut bytes);
+        bytes
+    }
+
+    /// The header without its recipient table
+    fn fixed_header_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
 
         bytes.extend_from_slice(&self.magic);
@@ -61,6 +102,10 @@ impl EncryptedPackage {
             bytes.extend_from_slice(&0u16.to_le_bytes());
         }
 
+        bytes
+    }
+
+    fn write_recipients(&self, bytes: &mut Vec<u8>) {
         bytes.extend_from_slice(&(self.recipients.len() as u16).to_le_bytes());
         for recipient in &self.recipients {
             let name_bytes = recipient.name.as_bytes();
@@ -69,6 +114,20 @@ impl EncryptedPackage {
             bytes.extend_from_slice(&(recipient.encrypted_key.len() as u16).to_le_bytes());
             bytes.extend_from_slice(&recipient.encrypted_key);
 
+            // v8: the algorithm of the classical key
+            if records_classical_algorithm(self.version) {
+                bytes.push(recipient.classical_algorithm.id());
+            }
+
+            // v6: the KEM of the PQC key comes first, 0 when there is none
+            if records_slot_algorithms(self.version) {
+                let id = match recipient.pq_encrypted_key {
+                    Some(_) => recipient.pq_algorithm.id(),
+                    None => 0,
+                };
+                bytes.push(id);
+            }
+
             // v2.0.0: Include PQC encrypted key if present
             if self.version >= 0x02 {
                 if let Some(ref pq_key) = recipient.pq_encrypted_key {
@@ -79,11 +138,6 @@ impl EncryptedPackage {
                 }
             }
         }
-
-        bytes.extend_from_slice(&(self.ciphertext.len() as u32).to_le_bytes());
-        bytes.extend_from_slice(&self.ciphertext);
-
-        bytes
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
@@ -102,131 +156,94 @@ impl EncryptedPackage {
     }
 
     fn from_binary(bytes: &[u8]) -> Result<Self> {
-        if bytes.len() < 79 {
-            return Err(HermesError::DecryptionFailed);
-        }
-
-        let mut pos = 0;
+        let mut reader = bytes;
+        let mut package = Self::read_from(&mut reader)?;
 
-        let mut magic = [0u8; 4];
-        magic.copy_from_slice(&bytes[pos..pos + 4]);
-        pos += 4;
+        if package.is_streamed() {
+            package.ciphertext = reader.to_vec();
+        }
 
-        let version = bytes[pos];
-        pos += 1;
+        Ok(package)
+    }
 
-        let flags = bytes[pos];
-        pos += 1;
+    /// Read a binary package from `reader`.
+    ///
+    /// For whole-buffer packages the length-prefixed ciphertext is read as well.
+    /// For streams only the header is consumed and `ciphertext` is left empty;
+    /// the segments that follow are read with [`crate::crypto::Decryptor`].
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut package = Self::read_header(reader)?;
+
+        if !package.is_streamed() {
+            let ciphertext_len = read_u32(reader)? as usize;
+            package.ciphertext = read_vec(reader, ciphertext_len)?;
+        }
 
-        let salt_len = u16::from_le_bytes([bytes[pos], bytes[pos + 1]]) as usize;
-        pos += 2;
+        Ok(package)
+    }
 
-        if pos + salt_len + 12 + 32 + 8 + 8 + 2 > bytes.len() {
+    /// Read only the header of a binary package, leaving `ciphertext` empty.
+    pub fn read_header<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        read_exact(reader, &mut magic)?;
+        if &magic != MAGIC_BYTES {
             return Err(HermesError::DecryptionFailed);
         }
 
-        let salt = bytes[pos..pos + salt_len].to_vec();
-        pos += salt_len;
+        let version = read_u8(reader)?;
+        let flags = read_u8(reader)?;
+
+        let salt_len = read_u16(reader)? as usize;
+        let salt = read_vec(reader, salt_len)?;
 
         let mut nonce = [0u8; 12];
-        nonce.copy_from_slice(&bytes[pos..pos + 12]);
-        pos += 12;
+        read_exact(reader, &mut nonce)?;
 
         let mut checksum = [0u8; 32];
-        checksum.copy_from_slice(&bytes[pos..pos + 32]);
-        pos += 32;
-
-        let original_size = u64::from_le_bytes([
-            bytes[pos],
-            bytes[pos + 1],
-            bytes[pos + 2],
-            bytes[pos + 3],
-            bytes[pos + 4],
-            bytes[pos + 5],
-            bytes[pos + 6],
-            bytes[pos + 7],
-        ]);
-        pos += 8;
-
-        let expires_at = u64::from_le_bytes([
-            bytes[pos],
-            bytes[pos + 1],
-            bytes[pos + 2],
-            bytes[pos + 3],
-            bytes[pos + 4],
-            bytes[pos + 5],
-            bytes[pos + 6],
-            bytes[pos + 7],
-        ]);
-        pos += 8;
-
-        let filename_len = u16::from_le_bytes([bytes[pos], bytes[pos + 1]]) as usize;
-        pos += 2;
+        read_exact(reader, &mut checksum)?;
+
+        let original_size = read_u64(reader)?;
+        let expires_at = read_u64(reader)?;
 
+        let filename_len = read_u16(reader)? as usize;
         let filename = if filename_len > 0 {
-            if pos + filename_len > bytes.len() {
-                return Err(HermesError::DecryptionFailed);
-            }
-            let name_bytes = &bytes[pos..pos + filename_len];
-            pos += filename_len;
-            Some(
-                String::from_utf8(name_bytes.to_vec())
-                    .map_err(|_| HermesError::DecryptionFailed)?,
-            )
+            let name_bytes = read_vec(reader, filename_len)?;
+            Some(String::from_utf8(name_bytes).map_err(|_| HermesError::DecryptionFailed)?)
         } else {
             None
         };
 
-        if pos + 2 > bytes.len() {
-            return Err(HermesError::DecryptionFailed);
-        }
-
-        let num_recipients = u16::from_le_bytes([bytes[pos], bytes[pos + 1]]) as usize;
-        pos += 2;
+        let num_recipients = read_u16(reader)? as usize;
 
         let mut recipients = Vec::new();
         for _ in 0..num_recipients {
-            if pos + 2 > bytes.len() {
-                return Err(HermesError::DecryptionFailed);
-            }
-
-            let name_len = u16::from_le_bytes([bytes[pos], bytes[pos + 1]]) as usize;
-            pos += 2;
-
-            if pos + name_len + 2 > bytes.len() {
-                return Err(HermesError::DecryptionFailed);
-            }
-
-            let name = String::from_utf8(bytes[pos..pos + name_len].to_vec())
+            let name_len = read_u16(reader)? as usize;
+            let name = String::from_utf8(read_vec(reader, name_len)?)
                 .map_err(|_| HermesError::DecryptionFailed)?;
-            pos += name_len;
 
-            let key_len = u16::from_le_bytes([bytes[pos], bytes[pos + 1]]) as usize;
-            pos += 2;
+            let key_len = read_u16(reader)? as usize;
+            let encrypted_key = read_vec(reader, key_len)?;
 
-            if pos + key_len > bytes.len() {
-                return Err(HermesError::DecryptionFailed);
-            }
+            let classical_algorithm = if records_classical_algorithm(version) {
+                ClassicalAlgorithm::from_id(read_u8(reader)?)?
+            } else {
+                ClassicalAlgorithm::RsaPkcs1v15
+            };
 
-            let encrypted_key = bytes[pos..pos + key_len].to_vec();
-            pos += key_len;
+            let pq_algorithm = if records_slot_algorithms(version) {
+                match read_u8(reader)? {
+                    0 => None,
+                    id => Some(KemAlgorithm::from_id(id)?),
+                }
+            } else {
+                Some(KemAlgorithm::Kyber768)
+            };
 
             // v2.0.0: Read PQC encrypted key if version >= 0x02
             let pq_encrypted_key = if version >= 0x02 {
-                if pos + 2 > bytes.len() {
-                    return Err(HermesError::DecryptionFailed);
-                }
-
-                let pq_key_len = u16::from_le_bytes([bytes[pos], bytes[pos + 1]]) as usize;
-                pos += 2;
-
+                let pq_key_len = read_u16(reader)? as usize;
                 if pq_key_len > 0 {
-                    if pos + pq_key_len > bytes.len() {
-                        return Err(HermesError::DecryptionFailed);
-                    }
-                    let pq_key = bytes[pos..pos + pq_key_len].to_vec();
-                    pos += pq_key_len;
-                    Some(pq_key)
+                    Some(read_vec(reader, pq_key_len)?)
                 } else {
                     None
                 }
@@ -234,28 +251,21 @@ impl EncryptedPackage {
                 None
             };
 
+            let pq_algorithm = match (&pq_encrypted_key, pq_algorithm) {
+                (Some(_), Some(algorithm)) => algorithm,
+                (None, _) => KemAlgorithm::Kyber768,
+                (Some(_), None) => return Err(HermesError::DecryptionFailed),
+            };
+
             recipients.push(RecipientKey {
                 name,
                 encrypted_key,
+                classical_algorithm,
                 pq_encrypted_key,
+                pq_algorithm,
             });
         }
 
-        if pos + 4 > bytes.len() {
-            return Err(HermesError::DecryptionFailed);
-        }
-
-        let ciphertext_len =
-            u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
-                as usize;
-        pos += 4;
-
-        if pos + ciphertext_len > bytes.len() {
-            return Err(HermesError::DecryptionFailed);
-        }
-
-        let ciphertext = bytes[pos..pos + ciphertext_len].to_vec();
-
         Ok(EncryptedPackage {
             magic,
             version,
@@ -267,7 +277,7 @@ impl EncryptedPackage {
             expires_at,
             filename,
             recipients,
-            ciphertext,
+            ciphertext: Vec::new(),
         })
     }
 
@@ -364,94 +374,250 @@ impl EncryptedPackage {
     pub fn is_pqc_enabled(&self) -> bool {
         (self.flags & FLAG_PQC_ENABLED) != 0
     }
-}
 
-pub fn encrypt_data(
-    plaintext: &[u8],
-    password: &str,
-    filename: Option<String>,
-    ttl_hours: Option<u64>,
-) -> Result<Vec<u8>> {
-    encrypt_data_multi(plaintext, Some(password), filename, ttl_hours, None, false)
-}
+    /// Whether recipient slots are identified by key ID rather than by name.
+    #[must_use]
+    pub fn has_key_ids(&self) -> bool {
+        (self.flags & FLAG_KEY_IDS) != 0
+    }
 
-pub fn encrypt_data_multi(
-    plaintext: &[u8],
-    password: Option<&str>,
-    filename: Option<String>,
-    ttl_hours: Option<u64>,
-    recipient_names: Option<Vec<String>>,
-    use_pqc: bool,
-) -> Result<Vec<u8>> {
-    let mut data_key = [0u8; 32];
-    let mut flags = 0u8;
-    let salt;
-    let recipients;
+    /// Whether PQC slots wrap the data key under a key derived from both the classical and
+    /// the KEM secret. Older PQC packages hold a separate copy of the key under each.
+    #[must_use]
+    pub fn has_hybrid_kem(&self) -> bool {
+        (self.flags & FLAG_HYBRID_KEM) != 0
+    }
 
-    if let Some(names) = recipient_names {
-        OsRng.fill_bytes(&mut data_key);
+    /// Whether recipient slots were left unlabeled, so recipients must try their keys.
+    #[must_use]
+    pub fn is_anonymous(&self) -> bool {
+        (self.flags & FLAG_ANONYMOUS) != 0
+    }
 
-        salt = vec![0u8; 0];
-        flags |= FLAG_MULTI_RECIPIENT;
+    /// Whether the payload carries a sender signature (streamed packages only).
+    #[must_use]
+    pub fn is_signed(&self) -> bool {
+        (self.flags & FLAG_SIGNED) != 0
+    }
 
-        if use_pqc {
-            flags |= FLAG_PQC_ENABLED;
-        }
+    #[must_use]
+    pub fn is_streamed(&self) -> bool {
+        matches!(
+            self.version,
+            STREAM_VERSION | V7_STREAM_VERSION | V5_STREAM_VERSION | LEGACY_STREAM_VERSION
+        )
+    }
+
+    /// Whether the header is bound to the ciphertext (v4 and later).
+    #[must_use]
+    pub fn has_authenticated_header(&self) -> bool {
+        self.version >= AUTHENTICATED_HEADER_VERSION
+    }
 
-        let recipients_dir = dirs::home_dir()
-            .ok_or_else(|| HermesError::ConfigError("Could not find home directory".to_string()))?
-            .join(".hermes")
-            .join("recipients");
+    /// Whether recipient slots can be added or removed without re-encrypting.
+    #[must_use]
+    pub fn has_editable_recipients(&self) -> bool {
+        self.has_authenticated_header() && (self.flags & FLAG_EDITABLE_RECIPIENTS) != 0
+    }
+
+    /// Associated data for the AEAD: the serialized header on v4+, empty before.
+    /// Packages with editable recipients leave the recipient table out.
+    #[must_use]
+    pub fn associated_data(&self) -> Vec<u8> {
+        if self.has_editable_recipients() {
+            self.fixed_header_bytes()
+        } else if self.has_authenticated_header() {
+            self.header_bytes()
+        } else {
+            Vec::new()
+        }
+    }
 
-        let mut recipient_list = Vec::new();
+    /// Wrap `data_key` for more recipients, labelling their slots like the existing ones.
+    /// The caller must have checked that `data_key` opens this package.
+    pub fn add_recipients(&mut self, data_key: &[u8; 32], names: &[String]) -> Result<()> {
+        self.check_editable()?;
 
         for name in names {
-            let pubkey_path = recipients_dir.join(format!("{name}.pub"));
-            if !pubkey_path.exists() {
+            let mut slot = wrap_for_recipient(data_key, name, self.flags, self.version)?;
+            if slot.pq_encrypted_key.is_some()
+                && slot.pq_algorithm != KemAlgorithm::Kyber768
+                && !records_slot_algorithms(self.version)
+            {
                 return Err(HermesError::ConfigError(format!(
-                    "Recipient public key not found: {name}"
+                    "This package predates ML-KEM and can't hold {name}'s {} key; re-encrypt it instead",
+                    slot.pq_algorithm.name()
                 )));
             }
+            if self.is_anonymous() {
+                slot.name.clear();
+            } else if self.recipients.iter().any(|r| r.name == slot.name) {
+                return Err(HermesError::ConfigError(format!(
+                    "{name} is already a recipient of this package"
+                )));
+            }
+            self.recipients.push(slot);
+        }
 
-            let public_key = crate::crypto::load_public_key(pubkey_path.to_str().unwrap())?;
-            let encrypted_key = crate::crypto::encrypt_key_for_recipient(&data_key, &public_key)?;
+        Ok(())
+    }
 
-            // Hybrid encryption: Also encrypt with Kyber if PQC is enabled
-            let pq_encrypted_key = if use_pqc {
-                let kyber_pubkey_path = recipients_dir.join(format!("{name}_kyber.pub"));
-                if !kyber_pubkey_path.exists() {
-                    return Err(HermesError::ConfigError(format!(
-                        "Kyber public key not found for recipient: {name}. Generate PQC keys with --pqc flag"
-                    )));
-                }
+    /// Drop the slots of the named recipients. They may still hold the data key or a
+    /// copy of the package; only re-encrypting truly revokes access.
+    pub fn remove_recipients(&mut self, names: &[String]) -> Result<()> {
+        self.check_editable()?;
+        if self.is_anonymous() {
+            return Err(HermesError::ConfigError(
+                "Recipients of an anonymous package can't be identified".to_string(),
+            ));
+        }
 
-                let kyber_key = crate::crypto::load_kyber_public_key(kyber_pubkey_path.to_str().unwrap())?;
-                let pq_encrypted = crate::crypto::encrypt_with_kyber(&data_key, &kyber_key)?;
-                Some(pq_encrypted)
-            } else {
-                None
-            };
+        for name in names {
+            let key = crate::crypto::keyring::recipient_ids(name)?;
+            let before = self.recipients.len();
+            self.recipients.retain(|r| !key.matches(&r.name));
+            if self.recipients.len() == before {
+                return Err(HermesError::ConfigError(format!(
+                    "{name} is not a recipient of this package"
+                )));
+            }
+        }
 
-            recipient_list.push(RecipientKey {
-                name,
-                encrypted_key,
-                pq_encrypted_key,
-            });
+        if self.recipients.is_empty() {
+            return Err(HermesError::ConfigError(
+                "Refusing to remove every recipient; nobody could open the package".to_string(),
+            ));
         }
 
-        recipients = recipient_list;
-    } else if let Some(pwd) = password {
-        let salt_string = SaltString::generate(OsRng);
-        let key = derive_key(pwd, &salt_string)?;
-        data_key.copy_from_slice(&key);
-        salt = salt_string.as_str().as_bytes().to_vec();
-        recipients = Vec::new();
-    } else {
+        Ok(())
+    }
+
+    /// Fail unless [`Self::add_recipients`] and [`Self::remove_recipients`] can change this package.
+    pub fn check_editable(&self) -> Result<()> {
+        if !self.is_multi_recipient() {
+            return Err(HermesError::ConfigError(
+                "Password-encrypted packages have no recipients".to_string(),
+            ));
+        }
+        if !self.has_editable_recipients() {
+            return Err(HermesError::ConfigError(
+                "This package authenticates its recipient list; re-encrypt it to change recipients"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Copy a package from `reader` to `writer` with its header replaced by `header`.
+/// The ciphertext is copied as is, so `header` must keep everything but the recipients.
+pub fn replace_header<R: Read, W: Write>(
+    mut reader: R,
+    header: &EncryptedPackage,
+    mut writer: W,
+) -> Result<W> {
+    let old = EncryptedPackage::read_header(&mut reader)?;
+    if old.associated_data() != header.associated_data() {
         return Err(HermesError::EncryptionFailed(
-            "Either password or recipients must be provided".to_string(),
+            "Only the recipients of a package can be replaced".to_string(),
         ));
     }
 
+    writer.write_all(&header.header_bytes())?;
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(writer)
+}
+
+pub(crate) fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
+    reader
+        .read_exact(buf)
+        .map_err(|_| HermesError::DecryptionFailed)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    read_exact(reader, &mut buf)?;
+    Ok(buf[0])
+}
+
+pub(crate) fn read_u16<R: Read>(reader: &mut R) -> Result<u16> {
+    let mut buf = [0u8; 2];
+    read_exact(reader, &mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    read_exact(reader, &mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    read_exact(reader, &mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub(crate) fn read_vec<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+    // Read through `take` so a corrupt length can't trigger a huge allocation
+    let mut buf = Vec::new();
+    reader
+        .take(len as u64)
+        .read_to_end(&mut buf)
+        .map_err(|_| HermesError::DecryptionFailed)?;
+    if buf.len() != len {
+        return Err(HermesError::DecryptionFailed);
+    }
+    Ok(buf)
+}
+
+pub fn encrypt_data(
+    plaintext: &[u8],
+    password: &str,
+    filename: Option<String>,
+    ttl_hours: Option<u64>,
+) -> Result<Vec<u8>> {
+    encrypt_data_multi(plaintext, Some(password), filename, ttl_hours, None, false)
+}
+
+pub fn encrypt_data_multi(
+    plaintext: &[u8],
+    password: Option<&str>,
+    filename: Option<String>,
+    ttl_hours: Option<u64>,
+    recipient_names: Option<Vec<String>>,
+    use_pqc: bool,
+) -> Result<Vec<u8>> {
+    encrypt_data_bound(
+        plaintext,
+        password,
+        filename,
+        ttl_hours,
+        recipient_names,
+        use_pqc,
+        &[],
+    )
+}
+
+/// Like [`encrypt_data_multi`], additionally authenticating `context`, which is not
+/// stored in the package; decryption must supply the same bytes.
+pub fn encrypt_data_bound(
+    plaintext: &[u8],
+    password: Option<&str>,
+    filename: Option<String>,
+    ttl_hours: Option<u64>,
+    recipient_names: Option<Vec<String>>,
+    use_pqc: bool,
+    context: &[u8],
+) -> Result<Vec<u8>> {
+    let KeyMaterial {
+        data_key,
+        mut flags,
+        salt,
+        recipients,
+    } = prepare_key_material(password, recipient_names, use_pqc)?;
+
     let cipher = Aes256Gcm::new_from_slice(&data_key)
         .map_err(|e| HermesError::EncryptionFailed(format!("Cipher creation failed: {e}")))?;
 
@@ -487,21 +653,7 @@ pub fn encrypt_data_multi(
 
     flags |= compression_flag;
 
-    let ciphertext = cipher
-        .encrypt(&nonce, data_to_encrypt.as_ref())
-        .map_err(|e| HermesError::EncryptionFailed(format!("Encryption failed: {e}")))?;
-
-    let expires_at = if let Some(hours) = ttl_hours {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        now + (hours * 3600)
-    } else {
-        0
-    };
-
-    let package = EncryptedPackage {
+    let mut package = EncryptedPackage {
         magic: *MAGIC_BYTES,
         version: VERSION,
         flags,
@@ -509,15 +661,178 @@ pub fn encrypt_data_multi(
         nonce: nonce_bytes,
         checksum,
         original_size,
-        expires_at,
+        expires_at: expiry_timestamp(ttl_hours),
         filename,
         recipients,
-        ciphertext,
+        ciphertext: Vec::new(),
     };
 
+    // Bind the header so TTL, filename, flags and recipients can't be altered
+    let mut aad = package.associated_data();
+    aad.extend_from_slice(context);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: data_to_encrypt.as_ref(),
+                aad: &aad,
+            },
+        )
+        .map_err(|e| HermesError::EncryptionFailed(format!("Encryption failed: {e}")))?;
+
+    // The length-prefixed layout stores the ciphertext length as a u32
+    if ciphertext.len() > u32::MAX as usize {
+        return Err(HermesError::EncryptionFailed(
+            "Data too large for a single package, use the streaming Encryptor".to_string(),
+        ));
+    }
+
+    package.ciphertext = ciphertext;
+
     Ok(package.to_bytes())
 }
 
+/// Data key plus the header fields needed to recover it.
+pub(crate) struct KeyMaterial {
+    pub data_key: [u8; 32],
+    pub flags: u8,
+    pub salt: Vec<u8>,
+    pub recipients: Vec<RecipientKey>,
+}
+
+pub(crate) fn prepare_key_material(
+    password: Option<&str>,
+    recipient_names: Option<Vec<String>>,
+    use_pqc: bool,
+) -> Result<KeyMaterial> {
+    let mut data_key = [0u8; 32];
+    let mut flags = 0u8;
+    let salt;
+    let recipients;
+
+    if let Some(names) = recipient_names {
+        OsRng.fill_bytes(&mut data_key);
+
+        salt = vec![0u8; 0];
+        flags |= FLAG_MULTI_RECIPIENT | FLAG_KEY_IDS;
+
+        if use_pqc {
+            flags |= FLAG_PQC_ENABLED | FLAG_HYBRID_KEM;
+        }
+
+        recipients = names
+            .iter()
+            .map(|name| wrap_for_recipient(&data_key, name, flags, VERSION))
+            .collect::<Result<Vec<_>>>()?;
+    } else if let Some(pwd) = password {
+        let salt_string = SaltString::generate(OsRng);
+        let key = derive_key(pwd, &salt_string)?;
+        data_key.copy_from_slice(&key);
+        salt = salt_string.as_str().as_bytes().to_vec();
+        recipients = Vec::new();
+    } else {
+        return Err(HermesError::EncryptionFailed(
+            "Either password or recipients must be provided".to_string(),
+        ));
+    }
+
+    Ok(KeyMaterial {
+        data_key,
+        flags,
+        salt,
+        recipients,
+    })
+}
+
+/// Wrap `data_key` for the contact `name` of a package of `version`: their identity key,
+/// plus their KEM key on PQC packages (combined with it when `flags` has [`FLAG_HYBRID_KEM`])
+fn wrap_for_recipient(
+    data_key: &[u8; 32],
+    name: &str,
+    flags: u8,
+    version: u8,
+) -> Result<RecipientKey> {
+    let recipients_dir = crate::config::recipients_dir()?;
+
+    let pubkey_path = recipients_dir.join(format!("{name}.pub"));
+    if !pubkey_path.exists() {
+        return Err(HermesError::ConfigError(format!(
+            "Recipient public key not found: {name}"
+        )));
+    }
+
+    let public_key = IdentityPublicKey::load(pubkey_path.to_str().unwrap())?;
+    let classical_algorithm = public_key
+        .slot_algorithm(records_classical_algorithm(version))
+        .map_err(|_| {
+            HermesError::ConfigError(format!(
+                "This package predates {} keys and can't hold {name}'s; re-encrypt it instead",
+                public_key.key_type().name()
+            ))
+        })?;
+
+    // Hybrid encryption: Also use the recipient's KEM if PQC is enabled
+    let kyber_key = if (flags & FLAG_PQC_ENABLED) != 0 {
+        let kyber_pubkey_path = recipients_dir.join(format!("{name}_kyber.pub"));
+        if !kyber_pubkey_path.exists() {
+            return Err(HermesError::ConfigError(format!(
+                "Kyber public key not found for recipient: {name}. Generate PQC keys with --pqc flag"
+            )));
+        }
+
+        Some(crate::crypto::load_kyber_public_key(kyber_pubkey_path.to_str().unwrap())?)
+    } else {
+        None
+    };
+    let (encrypted_key, pq_encrypted_key) = match kyber_key {
+        Some(ref kyber_key) if (flags & FLAG_HYBRID_KEM) != 0 => {
+            let (encrypted_key, pq_encrypted_key) =
+                crate::crypto::hybrid::wrap(data_key, &public_key, classical_algorithm, kyber_key)?;
+            (encrypted_key, Some(pq_encrypted_key))
+        }
+        _ => (
+            public_key.wrap_key(data_key, classical_algorithm)?,
+            kyber_key
+                .as_ref()
+                .map(|kyber_key| crate::crypto::encrypt_with_kyber(data_key, kyber_key))
+                .transpose()?,
+        ),
+    };
+
+    // The slot names the key, not the contact
+    Ok(RecipientKey {
+        name: crate::crypto::keyring::key_id(&public_key, kyber_key.as_ref())?,
+        encrypted_key,
+        classical_algorithm,
+        pq_encrypted_key,
+        pq_algorithm: kyber_key
+            .as_ref()
+            .map_or(KemAlgorithm::Kyber768, |key| key.algorithm()),
+    })
+}
+
+/// Whether recipient slots carry a KEM identifier (v6 and later)
+fn records_slot_algorithms(version: u8) -> bool {
+    version >= KEM_SLOT_VERSION
+}
+
+/// Whether recipient slots carry a classical algorithm identifier (v8 and later)
+fn records_classical_algorithm(version: u8) -> bool {
+    version >= VERSION
+}
+
+pub(crate) fn expiry_timestamp(ttl_hours: Option<u64>) -> u64 {
+    if let Some(hours) = ttl_hours {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        now + (hours * 3600)
+    } else {
+        0
+    }
+}
+
 fn derive_key(password: &str, salt: &SaltString) -> Result<[u8; 32]> {
     let argon2 = Argon2::default();
     let hash = argon2
@@ -544,16 +859,108 @@ mod tests {
         let encrypted = encrypt_data(plaintext, password, None, None).unwrap();
         let package = EncryptedPackage::from_bytes(&encrypted).unwrap();
 
-        assert_eq!(package.version, 0x02);
+        assert_eq!(package.version, VERSION);
+        assert!(package.has_authenticated_header());
         assert!(!package.is_pqc_enabled());
         assert!(!package.is_multi_recipient());
+
+        let decrypted = crate::crypto::decrypt_data(&encrypted, password).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_header_tampering_detected() {
+        let plaintext = b"self-destructing secret";
+        let password = "test_password_123";
+
+        let encrypted =
+            encrypt_data(plaintext, password, Some("a.txt".to_string()), Some(1)).unwrap();
+
+        // Extend the TTL without touching the ciphertext
+        let mut package = EncryptedPackage::from_bytes(&encrypted).unwrap();
+        package.expires_at += 365 * 24 * 3600;
+        assert!(crate::crypto::decrypt_data(&package.to_bytes(), password).is_err());
+
+        // Rename the file
+        let mut package = EncryptedPackage::from_bytes(&encrypted).unwrap();
+        package.filename = Some("b.txt".to_string());
+        assert!(crate::crypto::decrypt_data(&package.to_bytes(), password).is_err());
+
+        // Strip a flag
+        let mut package = EncryptedPackage::from_bytes(&encrypted).unwrap();
+        package.flags ^= FLAG_COMPRESSED;
+        assert!(crate::crypto::decrypt_data(&package.to_bytes(), password).is_err());
+    }
+
+    #[test]
+    fn test_editable_recipients_are_left_out_of_associated_data() {
+        let encrypted = encrypt_data(b"shared later", "pw", None, None).unwrap();
+        let mut package = EncryptedPackage::from_bytes(&encrypted).unwrap();
+        let slot = RecipientKey {
+            name: "0123456789abcdef".to_string(),
+            encrypted_key: vec![1u8; 8],
+            classical_algorithm: ClassicalAlgorithm::RsaPkcs1v15,
+            pq_encrypted_key: None,
+            pq_algorithm: KemAlgorithm::Kyber768,
+        };
+
+        let bound = package.associated_data();
+        package.recipients.push(slot.clone());
+        assert_ne!(package.associated_data(), bound);
+
+        package.flags |= FLAG_EDITABLE_RECIPIENTS;
+        let editable = package.associated_data();
+        package.recipients.push(slot);
+        assert_eq!(package.associated_data(), editable);
+
+        // Only the recipients of a header may be swapped
+        let original = EncryptedPackage::from_bytes(&encrypted).unwrap();
+        assert_eq!(
+            replace_header(&encrypted[..], &original, Vec::new()).unwrap(),
+            encrypted
+        );
+        let mut renamed = original.clone();
+        renamed.filename = Some("other.txt".to_string());
+        assert!(replace_header(&encrypted[..], &renamed, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_v2_package_without_associated_data_decrypts() {
+        let plaintext = b"written by Hermes 2.x";
+        let password = "test_password_123";
+
+        let salt = SaltString::generate(OsRng);
+        let key = derive_key(password, &salt).unwrap();
+        let nonce_bytes = [3u8; 12];
+        let ciphertext = Aes256Gcm::new_from_slice(&key)
+            .unwrap()
+            .encrypt(&Nonce::from(nonce_bytes), plaintext.as_ref())
+            .unwrap();
+
+        let package = EncryptedPackage {
+            magic: *MAGIC_BYTES,
+            version: 0x02,
+            flags: 0,
+            salt: salt.as_str().as_bytes().to_vec(),
+            nonce: nonce_bytes,
+            checksum: Sha256::digest(plaintext).into(),
+            original_size: plaintext.len() as u64,
+            expires_at: 0,
+            filename: None,
+            recipients: vec![],
+            ciphertext,
+        };
+
+        assert!(!package.has_authenticated_header());
+        let decrypted = crate::crypto::decrypt_data(&package.to_bytes(), password).unwrap();
+        assert_eq!(decrypted, plaintext);
     }
 
     #[test]
     fn test_package_serialization_v2() {
         let package = EncryptedPackage {
             magic: *MAGIC_BYTES,
-            version: VERSION,
+            version: 0x02,
             flags: FLAG_MULTI_RECIPIENT | FLAG_PQC_ENABLED,
             salt: vec![],
             nonce: [0u8; 12],
@@ -565,12 +972,16 @@ mod tests {
                 RecipientKey {
                     name: "alice".to_string(),
                     encrypted_key: vec![1, 2, 3, 4],
+                    classical_algorithm: ClassicalAlgorithm::RsaPkcs1v15,
                     pq_encrypted_key: Some(vec![5, 6, 7, 8]),
+                    pq_algorithm: KemAlgorithm::Kyber768,
                 },
                 RecipientKey {
                     name: "bob".to_string(),
                     encrypted_key: vec![9, 10, 11, 12],
+                    classical_algorithm: ClassicalAlgorithm::RsaPkcs1v15,
                     pq_encrypted_key: Some(vec![13, 14, 15, 16]),
+                    pq_algorithm: KemAlgorithm::Kyber768,
                 },
             ],
             ciphertext: vec![17, 18, 19, 20],
@@ -692,8 +1103,99 @@ mod tests {
         assert!(package.is_pqc_enabled());
     }
 
+    #[test]
+    fn test_slots_record_their_kem_from_v6() {
+        let slot = |pq_algorithm| RecipientKey {
+            name: "0123456789abcdef".to_string(),
+            encrypted_key: vec![1, 2, 3, 4],
+            classical_algorithm: ClassicalAlgorithm::RsaPkcs1v15,
+            pq_encrypted_key: Some(vec![5, 6, 7, 8]),
+            pq_algorithm,
+        };
+        let mut package = EncryptedPackage {
+            magic: *MAGIC_BYTES,
+            version: VERSION,
+            flags: FLAG_MULTI_RECIPIENT | FLAG_PQC_ENABLED,
+            salt: vec![],
+            nonce: [0u8; 12],
+            checksum: [0u8; 32],
+            original_size: 4,
+            expires_at: 0,
+            filename: None,
+            recipients: vec![
+                slot(KemAlgorithm::MlKem768),
+                slot(KemAlgorithm::MlKem1024),
+                RecipientKey {
+                    pq_encrypted_key: None,
+                    ..slot(KemAlgorithm::Kyber768)
+                },
+            ],
+            ciphertext: vec![9, 10, 11, 12],
+        };
+
+        let recovered = EncryptedPackage::from_bytes(&package.to_bytes()).unwrap();
+        assert_eq!(recovered.recipients[0].pq_algorithm, KemAlgorithm::MlKem768);
+        assert_eq!(recovered.recipients[1].pq_algorithm, KemAlgorithm::MlKem1024);
+        assert_eq!(recovered.recipients[2].pq_encrypted_key, None);
+        assert_eq!(recovered.ciphertext, package.ciphertext);
+
+        // v4 slots carry no identifier and are read as Kyber-768
+        package.version = AUTHENTICATED_HEADER_VERSION;
+        let recovered = EncryptedPackage::from_bytes(&package.to_bytes()).unwrap();
+        assert_eq!(recovered.recipients[0].pq_algorithm, KemAlgorithm::Kyber768);
+        assert_eq!(recovered.recipients[0].pq_encrypted_key, Some(vec![5, 6, 7, 8]));
+    }
+
+    #[test]
+    fn test_slots_record_their_classical_algorithm_from_v8() {
+        let slot = |classical_algorithm| RecipientKey {
+            name: "0123456789abcdef".to_string(),
+            encrypted_key: vec![1, 2, 3, 4],
+            classical_algorithm,
+            pq_encrypted_key: None,
+            pq_algorithm: KemAlgorithm::Kyber768,
+        };
+        let mut package = EncryptedPackage {
+            magic: *MAGIC_BYTES,
+            version: VERSION,
+            flags: FLAG_MULTI_RECIPIENT,
+            salt: vec![],
+            nonce: [0u8; 12],
+            checksum: [0u8; 32],
+            original_size: 4,
+            expires_at: 0,
+            filename: None,
+            recipients: vec![
+                slot(ClassicalAlgorithm::RsaOaep),
+                slot(ClassicalAlgorithm::X25519),
+            ],
+            ciphertext: vec![9, 10, 11, 12],
+        };
+
+        let recovered = EncryptedPackage::from_bytes(&package.to_bytes()).unwrap();
+        assert_eq!(
+            recovered.recipients[0].classical_algorithm,
+            ClassicalAlgorithm::RsaOaep
+        );
+        assert_eq!(
+            recovered.recipients[1].classical_algorithm,
+            ClassicalAlgorithm::X25519
+        );
+        assert_eq!(recovered.ciphertext, package.ciphertext);
+
+        // v6 slots are always RSA PKCS#1 v1.5
+        package.version = KEM_SLOT_VERSION;
+        let recovered = EncryptedPackage::from_bytes(&package.to_bytes()).unwrap();
+        assert_eq!(
+            recovered.recipients[0].classical_algorithm,
+            ClassicalAlgorithm::RsaPkcs1v15
+        );
+        assert_eq!(recovered.recipients[0].encrypted_key, vec![1, 2, 3, 4]);
+    }
+
     #[test]
     fn test_version_constant() {
-        assert_eq!(VERSION, 0x02);
+        assert_eq!(VERSION, 0x08);
+        assert_eq!(STREAM_VERSION, 0x09);
     }
 }
@@ -0,0 +1,217 @@
+use crate::crypto::identity::{ClassicalAlgorithm, IdentityPrivateKey, IdentityPublicKey};
+use crate::crypto::pqc::{self, KemAlgorithm, KyberPublicKey, KyberSecretKey};
+use crate::error::{HermesError, Result};
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+
+// Hybrid recipient slots don't carry the data key under either scheme alone. The identity
+// key (RSA or X25519) seals a fresh classical secret, the KEM encapsulates a post-quantum
+// one, and HKDF-SHA256 over both derives the key that wraps the data key. Recovering it
+// takes breaking both schemes.
+// Slot layout: encrypted_key = classical ciphertext,
+// pq_encrypted_key = KEM ciphertext || AES-256-GCM(data key).
+const COMBINER_CONTEXT: &[u8] = b"hermes-hybrid-kem-v1";
+const WRAPPED_KEY_LEN: usize = 32 + 16;
+
+/// Wrap `data_key` for a recipient's identity and KEM keys.
+/// Returns the slot's (encrypted_key, pq_encrypted_key).
+pub(crate) fn wrap(
+    data_key: &[u8; 32],
+    identity: &IdentityPublicKey,
+    classical: ClassicalAlgorithm,
+    kem: &KyberPublicKey,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let (classical_ciphertext, classical_secret) = identity.encapsulate(classical)?;
+
+    let (kem_ciphertext, pq_secret) = pqc::encapsulate(kem)?;
+
+    let wrapping_key = combine(
+        &classical_secret,
+        &pq_secret,
+        &classical_ciphertext,
+        &kem_ciphertext,
+        kem.algorithm(),
+    );
+    let wrapped = cipher(&wrapping_key)
+        .encrypt(&Nonce::default(), data_key.as_ref())
+        .map_err(|e| HermesError::EncryptionFailed(format!("Key wrapping failed: {e}")))?;
+
+    let mut pq_encrypted_key = kem_ciphertext;
+    pq_encrypted_key.extend_from_slice(&wrapped);
+
+    Ok((classical_ciphertext, pq_encrypted_key))
+}
+
+/// Recover the data key of a slot made by [`wrap`]
+pub(crate) fn unwrap(
+    encrypted_key: &[u8],
+    pq_encrypted_key: &[u8],
+    identity: &IdentityPrivateKey,
+    classical: ClassicalAlgorithm,
+    kem: &KyberSecretKey,
+) -> Result<[u8; 32]> {
+    let ciphertext_len = kem.algorithm().ciphertext_len();
+    if pq_encrypted_key.len() != ciphertext_len + WRAPPED_KEY_LEN {
+        return Err(HermesError::DecryptionFailed);
+    }
+    let (kem_ciphertext, wrapped) = pq_encrypted_key.split_at(ciphertext_len);
+
+    let classical_secret = identity.decapsulate(encrypted_key, classical)?;
+    let pq_secret = pqc::decapsulate(kem_ciphertext, kem)?;
+
+    let wrapping_key = combine(
+        &classical_secret,
+        &pq_secret,
+        encrypted_key,
+        kem_ciphertext,
+        kem.algorithm(),
+    );
+    cipher(&wrapping_key)
+        .decrypt(&Nonce::default(), wrapped)
+        .map_err(|_| HermesError::DecryptionFailed)?
+        .try_into()
+        .map_err(|_| HermesError::DecryptionFailed)
+}
+
+/// HKDF-SHA256 over both shared secrets. The KEM and both ciphertexts go into the info,
+/// so a wrapping key only opens the slot it was derived for.
+fn combine(
+    classical_secret: &[u8; 32],
+    pq_secret: &[u8; 32],
+    classical_ciphertext: &[u8],
+    kem_ciphertext: &[u8],
+    algorithm: KemAlgorithm,
+) -> [u8; 32] {
+    let mut ikm = classical_secret.to_vec();
+    ikm.extend_from_slice(pq_secret);
+
+    let mut info = COMBINER_CONTEXT.to_vec();
+    info.push(algorithm.id());
+    info.extend_from_slice(&Sha256::digest(classical_ciphertext));
+    info.extend_from_slice(&Sha256::digest(kem_ciphertext));
+
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, &ikm)
+        .expand(&info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Every wrapping key is used once, so a fixed nonce is safe
+fn cipher(wrapping_key: &[u8; 32]) -> Aes256Gcm {
+    Aes256Gcm::new(wrapping_key.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::pqc::generate_kyber_keypair;
+    use crate::crypto::x25519::generate_x25519_keypair;
+    use rsa::rand_core::OsRng;
+    use rsa::{RsaPrivateKey, RsaPublicKey};
+
+    const OAEP: ClassicalAlgorithm = ClassicalAlgorithm::RsaOaep;
+
+    fn rsa_keypair() -> (IdentityPublicKey, IdentityPrivateKey) {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        (
+            IdentityPublicKey::Rsa(RsaPublicKey::from(&private_key)),
+            IdentityPrivateKey::Rsa(private_key),
+        )
+    }
+
+    #[test]
+    fn test_hybrid_wrap_roundtrip() {
+        let (kem_public, kem_secret) = generate_kyber_keypair(KemAlgorithm::MlKem768).unwrap();
+        let data_key = [9u8; 32];
+
+        let (x25519_public, x25519_secret) = generate_x25519_keypair().unwrap();
+        let identities = [
+            (rsa_keypair(), ClassicalAlgorithm::RsaPkcs1v15),
+            (rsa_keypair(), OAEP),
+            (
+                (
+                    IdentityPublicKey::X25519(x25519_public),
+                    IdentityPrivateKey::X25519(x25519_secret),
+                ),
+                ClassicalAlgorithm::X25519,
+            ),
+        ];
+        for ((public_key, private_key), classical) in identities {
+            let (encrypted_key, pq_encrypted_key) =
+                wrap(&data_key, &public_key, classical, &kem_public).unwrap();
+            assert_eq!(
+                pq_encrypted_key.len(),
+                KemAlgorithm::MlKem768.ciphertext_len() + WRAPPED_KEY_LEN
+            );
+            assert_eq!(
+                unwrap(
+                    &encrypted_key,
+                    &pq_encrypted_key,
+                    &private_key,
+                    classical,
+                    &kem_secret
+                )
+                .unwrap(),
+                data_key
+            );
+        }
+    }
+
+    #[test]
+    fn test_neither_secret_alone_recovers_the_key() {
+        let (rsa_public, rsa_private) = rsa_keypair();
+        let (kem_public, kem_secret) = generate_kyber_keypair(KemAlgorithm::MlKem768).unwrap();
+        let data_key = [9u8; 32];
+        let (encrypted_key, pq_encrypted_key) =
+            wrap(&data_key, &rsa_public, OAEP, &kem_public).unwrap();
+
+        // Neither ciphertext holds the data key itself
+        let classical = rsa_private.decapsulate(&encrypted_key, OAEP).unwrap();
+        assert_ne!(classical, data_key);
+        let (kem_ciphertext, wrapped) =
+            pq_encrypted_key.split_at(KemAlgorithm::MlKem768.ciphertext_len());
+        let pq_secret = pqc::decapsulate(kem_ciphertext, &kem_secret).unwrap();
+        let xored: Vec<u8> = wrapped.iter().zip(&pq_secret).map(|(w, s)| w ^ s).collect();
+        assert_ne!(xored, data_key);
+
+        // Either key pair alone fails
+        let (_, other_rsa) = rsa_keypair();
+        let (_, other_kem) = generate_kyber_keypair(KemAlgorithm::MlKem768).unwrap();
+        assert!(unwrap(
+            &encrypted_key,
+            &pq_encrypted_key,
+            &other_rsa,
+            OAEP,
+            &kem_secret
+        )
+        .is_err());
+        assert!(unwrap(
+            &encrypted_key,
+            &pq_encrypted_key,
+            &rsa_private,
+            OAEP,
+            &other_kem
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_slot_halves_cannot_be_mixed() {
+        let (rsa_public, rsa_private) = rsa_keypair();
+        let (kem_public, kem_secret) = generate_kyber_keypair(KemAlgorithm::MlKem1024).unwrap();
+        let (encrypted_key, _) = wrap(&[1u8; 32], &rsa_public, OAEP, &kem_public).unwrap();
+        let (_, pq_encrypted_key) = wrap(&[1u8; 32], &rsa_public, OAEP, &kem_public).unwrap();
+
+        assert!(unwrap(
+            &encrypted_key,
+            &pq_encrypted_key,
+            &rsa_private,
+            OAEP,
+            &kem_secret
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,408 @@
+use crate::crypto::keystore;
+use crate::crypto::rsa::{
+    decrypt_key_oaep, decrypt_key_with_private, encrypt_key_for_recipient, encrypt_key_oaep,
+    get_key_fingerprint, load_private_key, save_private_key,
+};
+use crate::crypto::x25519::{
+    self, generate_x25519_keypair, get_x25519_fingerprint, load_x25519_public_key,
+    load_x25519_secret_key, save_x25519_public_key, save_x25519_secret_key, X25519PublicKey,
+    X25519SecretKey,
+};
+use crate::error::{HermesError, Result};
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use rsa::pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding};
+use rsa::rand_core::{OsRng, RngCore};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use std::fs;
+use std::path::Path;
+
+const RSA_KEY_SIZE: usize = 4096;
+
+/// Classical key type of an identity (`{name}.pem` / `{name}.pub`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyType {
+    /// RSA-4096, readable by every Hermes version
+    #[default]
+    Rsa,
+    /// X25519 key agreement, with an Ed25519 key for signatures
+    X25519,
+}
+
+impl KeyType {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rsa => "RSA-4096",
+            Self::X25519 => "X25519",
+        }
+    }
+}
+
+/// How the classical half of a recipient slot is sealed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassicalAlgorithm {
+    /// RSA with PKCS#1 v1.5 padding; every slot before package v8
+    RsaPkcs1v15,
+    /// RSA-OAEP with SHA-256
+    RsaOaep,
+    /// Ephemeral-static X25519, see [`crate::crypto::x25519`]
+    X25519,
+}
+
+impl ClassicalAlgorithm {
+    /// Identifier stored in recipient slots (package v8 and later)
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Self::RsaPkcs1v15 => 1,
+            Self::RsaOaep => 2,
+            Self::X25519 => 3,
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(Self::RsaPkcs1v15),
+            2 => Ok(Self::RsaOaep),
+            3 => Ok(Self::X25519),
+            _ => Err(HermesError::DecryptionFailed),
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RsaPkcs1v15 => "RSA PKCS#1 v1.5",
+            Self::RsaOaep => "RSA-OAEP",
+            Self::X25519 => "X25519",
+        }
+    }
+}
+
+/// Public key of an identity: what `{name}.pub` holds
+#[derive(Clone)]
+pub enum IdentityPublicKey {
+    Rsa(RsaPublicKey),
+    X25519(X25519PublicKey),
+}
+
+/// Private key of an identity: what `{name}.pem` holds
+#[allow(clippy::large_enum_variant)]
+pub enum IdentityPrivateKey {
+    Rsa(RsaPrivateKey),
+    X25519(X25519SecretKey),
+}
+
+/// Generate an identity keypair and save it to the given paths
+pub fn generate_identity(
+    key_type: KeyType,
+    private_key_path: &Path,
+    public_key_path: &Path,
+    passphrase: Option<&str>,
+) -> Result<IdentityPublicKey> {
+    match key_type {
+        KeyType::Rsa => {
+            let private_key = RsaPrivateKey::new(&mut OsRng, RSA_KEY_SIZE).map_err(|e| {
+                HermesError::KeyGenerationFailed(format!("RSA key generation failed: {e}"))
+            })?;
+            let public_key = IdentityPublicKey::Rsa(RsaPublicKey::from(&private_key));
+
+            IdentityPrivateKey::Rsa(private_key).save(private_key_path, passphrase)?;
+            public_key.save(public_key_path)?;
+            Ok(public_key)
+        }
+        KeyType::X25519 => {
+            let (public_key, secret_key) = generate_x25519_keypair()?;
+            let public_key = IdentityPublicKey::X25519(public_key);
+
+            IdentityPrivateKey::X25519(secret_key).save(private_key_path, passphrase)?;
+            public_key.save(public_key_path)?;
+            Ok(public_key)
+        }
+    }
+}
+
+/// Label of the PEM block in `content`, without the ENCRYPTED prefix of protected keys
+fn pem_label(content: &str) -> Result<String> {
+    let (label, _) = keystore::decode_pem(content)?;
+    Ok(label
+        .strip_prefix("ENCRYPTED ")
+        .map_or(label.clone(), str::to_string))
+}
+
+impl IdentityPublicKey {
+    /// Load `{name}.pub`, whatever its key type
+    pub fn load(path: &str) -> Result<Self> {
+        let pem = fs::read_to_string(path)?;
+        if pem_label(&pem)? == x25519::PUBLIC_KEY_LABEL {
+            return Ok(Self::X25519(load_x25519_public_key(path)?));
+        }
+        RsaPublicKey::from_public_key_pem(&pem)
+            .map(Self::Rsa)
+            .map_err(|_e| HermesError::DecryptionFailed)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        match self {
+            Self::Rsa(public_key) => {
+                let pem = public_key.to_public_key_pem(LineEnding::LF).map_err(|e| {
+                    HermesError::EncryptionFailed(format!("Public key encoding failed: {e}"))
+                })?;
+                fs::write(path, pem.as_bytes())?;
+                Ok(())
+            }
+            Self::X25519(public_key) => save_x25519_public_key(public_key, path),
+        }
+    }
+
+    #[must_use]
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            Self::Rsa(_) => KeyType::Rsa,
+            Self::X25519(_) => KeyType::X25519,
+        }
+    }
+
+    pub fn fingerprint(&self) -> Result<String> {
+        match self {
+            Self::Rsa(public_key) => get_key_fingerprint(public_key),
+            Self::X25519(public_key) => Ok(get_x25519_fingerprint(public_key)),
+        }
+    }
+
+    /// How new recipient slots for this key are sealed. Packages before v8 can't
+    /// record the algorithm and only hold RSA PKCS#1 v1.5 slots.
+    pub(crate) fn slot_algorithm(&self, records_algorithm: bool) -> Result<ClassicalAlgorithm> {
+        match self {
+            Self::Rsa(_) if records_algorithm => Ok(ClassicalAlgorithm::RsaOaep),
+            Self::Rsa(_) => Ok(ClassicalAlgorithm::RsaPkcs1v15),
+            Self::X25519(_) if records_algorithm => Ok(ClassicalAlgorithm::X25519),
+            Self::X25519(_) => Err(HermesError::ConfigError(
+                "This package predates X25519 keys; re-encrypt it instead".to_string(),
+            )),
+        }
+    }
+
+    /// Seal a fresh 32-byte secret to this key, returning (ciphertext, secret)
+    pub(crate) fn encapsulate(&self, algorithm: ClassicalAlgorithm) -> Result<(Vec<u8>, [u8; 32])> {
+        match self {
+            Self::X25519(public_key) if algorithm == ClassicalAlgorithm::X25519 => {
+                let (ephemeral_public, secret) = x25519::encapsulate(public_key)?;
+                Ok((ephemeral_public.to_vec(), secret))
+            }
+            _ => {
+                let mut secret = [0u8; 32];
+                OsRng.fill_bytes(&mut secret);
+                Ok((self.wrap_key(&secret, algorithm)?, secret))
+            }
+        }
+    }
+
+    /// Seal `data_key` to this key: RSA encrypts it directly, X25519 encrypts it under
+    /// the agreed secret as ephemeral public key || AES-256-GCM(data key)
+    pub(crate) fn wrap_key(
+        &self,
+        data_key: &[u8; 32],
+        algorithm: ClassicalAlgorithm,
+    ) -> Result<Vec<u8>> {
+        match (self, algorithm) {
+            (Self::Rsa(public_key), ClassicalAlgorithm::RsaPkcs1v15) => {
+                encrypt_key_for_recipient(data_key, public_key)
+            }
+            (Self::Rsa(public_key), ClassicalAlgorithm::RsaOaep) => {
+                encrypt_key_oaep(data_key, public_key)
+            }
+            (Self::X25519(_), ClassicalAlgorithm::X25519) => {
+                let (mut wrapped, secret) = self.encapsulate(algorithm)?;
+                // Every agreed secret is used once, so a fixed nonce is safe
+                let sealed = Aes256Gcm::new(&secret.into())
+                    .encrypt(&Nonce::default(), data_key.as_ref())
+                    .map_err(|e| {
+                        HermesError::EncryptionFailed(format!("Key wrapping failed: {e}"))
+                    })?;
+                wrapped.extend_from_slice(&sealed);
+                Ok(wrapped)
+            }
+            _ => Err(HermesError::EncryptionFailed(format!(
+                "{} keys can't be used with {}",
+                self.key_type().name(),
+                algorithm.name()
+            ))),
+        }
+    }
+}
+
+impl IdentityPrivateKey {
+    /// Load `{name}.pem`, whatever its key type
+    pub fn load(path: &str, passphrase: Option<&str>) -> Result<Self> {
+        let pem = fs::read_to_string(path)?;
+        if pem_label(&pem)? == x25519::PRIVATE_KEY_LABEL {
+            return Ok(Self::X25519(load_x25519_secret_key(path, passphrase)?));
+        }
+        load_private_key(path, passphrase).map(Self::Rsa)
+    }
+
+    /// Save the key, encrypted when a passphrase is given
+    pub fn save(&self, path: &Path, passphrase: Option<&str>) -> Result<()> {
+        match self {
+            Self::Rsa(private_key) => save_private_key(private_key, path, passphrase),
+            Self::X25519(secret_key) => save_x25519_secret_key(secret_key, path, passphrase),
+        }
+    }
+
+    #[must_use]
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            Self::Rsa(_) => KeyType::Rsa,
+            Self::X25519(_) => KeyType::X25519,
+        }
+    }
+
+    /// Recover the secret of a ciphertext made by [`IdentityPublicKey::encapsulate`]
+    pub(crate) fn decapsulate(
+        &self,
+        ciphertext: &[u8],
+        algorithm: ClassicalAlgorithm,
+    ) -> Result<[u8; 32]> {
+        match self {
+            Self::X25519(secret_key) if algorithm == ClassicalAlgorithm::X25519 => {
+                x25519::decapsulate(ciphertext, secret_key)
+            }
+            _ => self.unwrap_key(ciphertext, algorithm),
+        }
+    }
+
+    /// Recover the data key of a slot made by [`IdentityPublicKey::wrap_key`]
+    pub(crate) fn unwrap_key(
+        &self,
+        wrapped: &[u8],
+        algorithm: ClassicalAlgorithm,
+    ) -> Result<[u8; 32]> {
+        let data_key = match (self, algorithm) {
+            (Self::Rsa(private_key), ClassicalAlgorithm::RsaPkcs1v15) => {
+                decrypt_key_with_private(wrapped, private_key)?
+            }
+            (Self::Rsa(private_key), ClassicalAlgorithm::RsaOaep) => {
+                decrypt_key_oaep(wrapped, private_key)?
+            }
+            (Self::X25519(_), ClassicalAlgorithm::X25519) if wrapped.len() > 32 => {
+                let (ephemeral_public, sealed) = wrapped.split_at(32);
+                let secret = self.decapsulate(ephemeral_public, algorithm)?;
+                Aes256Gcm::new(&secret.into())
+                    .decrypt(&Nonce::default(), sealed)
+                    .map_err(|_| HermesError::DecryptionFailed)?
+            }
+            _ => return Err(HermesError::DecryptionFailed),
+        };
+
+        data_key
+            .try_into()
+            .map_err(|_| HermesError::DecryptionFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rsa_identity() -> (IdentityPublicKey, IdentityPrivateKey) {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        (
+            IdentityPublicKey::Rsa(RsaPublicKey::from(&private_key)),
+            IdentityPrivateKey::Rsa(private_key),
+        )
+    }
+
+    fn x25519_identity() -> (IdentityPublicKey, IdentityPrivateKey) {
+        let (public_key, secret_key) = generate_x25519_keypair().unwrap();
+        (
+            IdentityPublicKey::X25519(public_key),
+            IdentityPrivateKey::X25519(secret_key),
+        )
+    }
+
+    #[test]
+    fn test_wrap_key_roundtrip() {
+        let data_key = [5u8; 32];
+        for (keys, algorithm) in [
+            (rsa_identity(), ClassicalAlgorithm::RsaPkcs1v15),
+            (rsa_identity(), ClassicalAlgorithm::RsaOaep),
+            (x25519_identity(), ClassicalAlgorithm::X25519),
+        ] {
+            let (public_key, private_key) = keys;
+            let wrapped = public_key.wrap_key(&data_key, algorithm).unwrap();
+            assert_eq!(
+                private_key.unwrap_key(&wrapped, algorithm).unwrap(),
+                data_key
+            );
+
+            let (ciphertext, secret) = public_key.encapsulate(algorithm).unwrap();
+            assert_eq!(
+                private_key.decapsulate(&ciphertext, algorithm).unwrap(),
+                secret
+            );
+        }
+    }
+
+    #[test]
+    fn test_padding_and_key_type_must_match() {
+        let data_key = [5u8; 32];
+        let (public_key, private_key) = rsa_identity();
+        let wrapped = public_key
+            .wrap_key(&data_key, ClassicalAlgorithm::RsaOaep)
+            .unwrap();
+        assert!(private_key
+            .unwrap_key(&wrapped, ClassicalAlgorithm::RsaPkcs1v15)
+            .is_err());
+        assert!(public_key
+            .wrap_key(&data_key, ClassicalAlgorithm::X25519)
+            .is_err());
+
+        let (public_key, _) = x25519_identity();
+        assert!(public_key
+            .wrap_key(&data_key, ClassicalAlgorithm::RsaOaep)
+            .is_err());
+        assert!(public_key.slot_algorithm(false).is_err());
+        assert_eq!(
+            public_key.slot_algorithm(true).unwrap(),
+            ClassicalAlgorithm::X25519
+        );
+    }
+
+    #[test]
+    fn test_identity_files_are_loaded_by_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let private_path = dir.path().join("alice.pem");
+        let public_path = dir.path().join("alice.pub");
+
+        let public_key =
+            generate_identity(KeyType::X25519, &private_path, &public_path, Some("pw")).unwrap();
+        let loaded = IdentityPublicKey::load(public_path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.key_type(), KeyType::X25519);
+        assert_eq!(
+            loaded.fingerprint().unwrap(),
+            public_key.fingerprint().unwrap()
+        );
+
+        let private_key =
+            IdentityPrivateKey::load(private_path.to_str().unwrap(), Some("pw")).unwrap();
+        assert_eq!(private_key.key_type(), KeyType::X25519);
+
+        let (rsa_public, rsa_private) = rsa_identity();
+        rsa_public.save(&public_path).unwrap();
+        rsa_private.save(&private_path, None).unwrap();
+        assert_eq!(
+            IdentityPublicKey::load(public_path.to_str().unwrap())
+                .unwrap()
+                .key_type(),
+            KeyType::Rsa
+        );
+        assert_eq!(
+            IdentityPrivateKey::load(private_path.to_str().unwrap(), None)
+                .unwrap()
+                .key_type(),
+            KeyType::Rsa
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::crypto::identity::IdentityPublicKey;
+use crate::crypto::pqc::{get_kyber_fingerprint, load_kyber_public_key, KyberPublicKey};
+use crate::error::{HermesError, Result};
+use std::fs;
+use std::path::Path;
+
+/// Key ID of a recipient slot: the identity key fingerprint, followed by the Kyber key
+/// fingerprint for hybrid slots. Unlike a name, it doesn't change when a contact is renamed.
+pub fn key_id(identity: &IdentityPublicKey, kyber: Option<&KyberPublicKey>) -> Result<String> {
+    let mut id = identity.fingerprint()?;
+    if let Some(kyber) = kyber {
+        id.push_str(&get_kyber_fingerprint(kyber));
+    }
+    Ok(id)
+}
+
+/// A public key known under a name: `{name}.pub`, plus `{name}_kyber.pub` if present
+#[derive(Clone)]
+pub struct NamedKey {
+    pub name: String,
+    identity_id: String,
+    hybrid_id: Option<String>,
+}
+
+impl NamedKey {
+    pub(crate) fn load(dir: &Path, name: &str) -> Result<Self> {
+        let identity = IdentityPublicKey::load(dir.join(format!("{name}.pub")).to_str().unwrap())?;
+        let kyber_path = dir.join(format!("{name}_kyber.pub"));
+        let kyber = if kyber_path.exists() {
+            Some(load_kyber_public_key(kyber_path.to_str().unwrap())?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            identity_id: key_id(&identity, None)?,
+            hybrid_id: kyber
+                .as_ref()
+                .map(|k| key_id(&identity, Some(k)))
+                .transpose()?,
+        })
+    }
+
+    /// Key ID covering every key currently on file: the identity key and the Kyber key if any
+    #[must_use]
+    pub fn current_id(&self) -> &str {
+        self.hybrid_id.as_deref().unwrap_or(&self.identity_id)
+    }
+
+    /// Whether a slot with this key ID was made for this key, with or without Kyber
+    #[must_use]
+    pub fn matches(&self, id: &str) -> bool {
+        self.identity_id == id || self.hybrid_id.as_deref() == Some(id)
+    }
+}
+
+/// Keys in `dir` with an identity public key, optionally only the one called `name`
+fn named_keys(dir: &Path, name: Option<&str>) -> Result<Vec<NamedKey>> {
+    if let Some(name) = name {
+        return Ok(vec![NamedKey::load(dir, name)?]);
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut keys = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".pub"))
+        else {
+            continue;
+        };
+        if name.ends_with("_kyber") || name.ends_with("_dilithium") || name.ends_with("_ed25519") {
+            continue;
+        }
+
+        // Skip unreadable files rather than failing the whole lookup
+        if let Ok(key) = NamedKey::load(dir, name) {
+            keys.push(key);
+        }
+    }
+
+    keys.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(keys)
+}
+
+/// Our own keys that have a private key, or just `name` when given
+pub fn local_keys(name: Option<&str>) -> Result<Vec<NamedKey>> {
+    let dir = crate::config::keys_dir()?;
+
+    if let Some(name) = name {
+        if !dir.join(format!("{name}.pem")).exists() {
+            return Err(HermesError::ConfigError(format!(
+                "Private key not found for: {name}"
+            )));
+        }
+    }
+
+    Ok(named_keys(&dir, name)?
+        .into_iter()
+        .filter(|key| dir.join(format!("{}.pem", key.name)).exists())
+        .collect())
+}
+
+/// Key IDs a package addressed to recipient `name` may carry
+pub fn recipient_ids(name: &str) -> Result<NamedKey> {
+    NamedKey::load(&crate::config::recipients_dir()?, name)
+}
+
+/// Local name of the contact (or own key) with this key ID, if we know it
+pub fn known_name(id: &str) -> Result<Option<String>> {
+    for dir in [crate::config::recipients_dir()?, crate::config::keys_dir()?] {
+        if let Some(key) = named_keys(&dir, None)?.into_iter().find(|k| k.matches(id)) {
+            return Ok(Some(key.name));
+        }
+    }
+    Ok(None)
+}
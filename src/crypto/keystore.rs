@@ -0,0 +1,273 @@
+use crate::error::{HermesError, Result};
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use argon2::Argon2;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Environment variable consulted before prompting for a key passphrase
+pub const PASSPHRASE_ENV: &str = "HERMES_KEY_PASSPHRASE";
+
+/// Keeps prompts from concurrent workers from interleaving
+static PROMPT_LOCK: Mutex<()> = Mutex::new(());
+
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+const ENCRYPTED_PREFIX: &str = "-----BEGIN ENCRYPTED ";
+
+/// Encrypt secret key bytes with a passphrase (Argon2id + AES-256-GCM)
+/// Output layout: salt || nonce || ciphertext, with the PEM label as AAD
+pub fn seal_secret(secret: &[u8], passphrase: &str, label: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_SIZE];
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_wrapping_key(passphrase, &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key)
+        .map_err(|e| HermesError::EncryptionFailed(e.to_string()))?;
+    let ciphertext = cipher
+        .encrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: secret,
+                aad: label.as_bytes(),
+            },
+        )
+        .map_err(|e| HermesError::EncryptionFailed(format!("Key wrapping failed: {e}")))?;
+
+    let mut sealed = Vec::with_capacity(SALT_SIZE + NONCE_SIZE + ciphertext.len());
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypt secret key bytes produced by `seal_secret`
+pub fn open_secret(sealed: &[u8], passphrase: &str, label: &str) -> Result<Vec<u8>> {
+    if sealed.len() < SALT_SIZE + NONCE_SIZE {
+        return Err(HermesError::InvalidPassword);
+    }
+
+    let (salt, rest) = sealed.split_at(SALT_SIZE);
+    let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+
+    let key = derive_wrapping_key(passphrase, salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| HermesError::InvalidPassword)?;
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: label.as_bytes(),
+            },
+        )
+        .map_err(|_| HermesError::InvalidPassword)
+}
+
+fn derive_wrapping_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| HermesError::KeyDerivationFailed)?;
+    Ok(key)
+}
+
+/// Encode bytes as a PEM block with 64-column base64 lines
+pub fn encode_pem(label: &str, bytes: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    let body = encoded
+        .as_bytes()
+        .chunks(64)
+        .map(|c| String::from_utf8_lossy(c).into_owned())
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("-----BEGIN {label}-----\n{body}\n-----END {label}-----\n")
+}
+
+/// Decode a PEM block, returning its label and contents
+pub fn decode_pem(content: &str) -> Result<(String, Vec<u8>)> {
+    let label = content
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("-----BEGIN ")
+                .and_then(|rest| rest.strip_suffix("-----"))
+        })
+        .ok_or_else(|| HermesError::ConfigError("Missing PEM header".to_string()))?
+        .to_string();
+
+    let encoded = content
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect::<String>();
+
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+    Ok((label, bytes))
+}
+
+/// PEM for raw secret key bytes, sealed under `passphrase` when given
+pub fn encode_secret_pem(label: &str, secret: &[u8], passphrase: Option<&str>) -> Result<String> {
+    Ok(match passphrase {
+        Some(passphrase) => {
+            let sealed = seal_secret(secret, passphrase, label)?;
+            encode_pem(&format!("ENCRYPTED {label}"), &sealed)
+        }
+        None => encode_pem(label, secret),
+    })
+}
+
+/// Read a PEM made by [`encode_secret_pem`], returning its inner label and the secret
+pub fn decode_secret_pem(
+    content: &str,
+    passphrase: Option<&str>,
+    path: &str,
+) -> Result<(String, Vec<u8>)> {
+    let (label, bytes) = decode_pem(content)?;
+    match label.strip_prefix("ENCRYPTED ") {
+        Some(inner) => {
+            let passphrase = passphrase.ok_or_else(|| {
+                HermesError::ConfigError(format!("Private key is passphrase-protected: {path}"))
+            })?;
+            Ok((inner.to_string(), open_secret(&bytes, passphrase, inner)?))
+        }
+        None => Ok((label, bytes)),
+    }
+}
+
+/// Check whether a secret key file is passphrase-protected
+pub fn is_protected(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .map(|content| content.trim_start().starts_with(ENCRYPTED_PREFIX))
+        .unwrap_or(false)
+}
+
+/// Write a secret key file readable only by the owner
+pub fn write_secret_file(path: &Path, contents: &[u8]) -> Result<()> {
+    fs::write(path, contents)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(0o600);
+        fs::set_permissions(path, perms)?;
+    }
+
+    Ok(())
+}
+
+/// Ask for a key passphrase, preferring HERMES_KEY_PASSPHRASE when set
+pub fn read_passphrase(key_name: &str) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    let _guard = PROMPT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    if !std::io::stdin().is_terminal() {
+        return Err(HermesError::ConfigError(format!(
+            "Key '{key_name}' is passphrase-protected. Set {PASSPHRASE_ENV} or run interactively"
+        )));
+    }
+
+    Ok(dialoguer::Password::new()
+        .with_prompt(format!("Passphrase for key '{key_name}'"))
+        .interact()?)
+}
+
+/// Prompt for a passphrase once if any of the given key files is protected
+pub fn unlock_passphrase(paths: &[&Path], key_name: &str) -> Result<Option<String>> {
+    if paths.iter().any(|path| is_protected(path)) {
+        Ok(Some(read_passphrase(key_name)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Prompt for a new passphrase with confirmation
+pub fn prompt_new_passphrase(allow_empty: bool) -> Result<String> {
+    let passphrase = dialoguer::Password::new()
+        .with_prompt("New key passphrase")
+        .with_confirmation("Confirm passphrase", "Passphrases do not match")
+        .allow_empty_password(allow_empty)
+        .interact()?;
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let secret = b"kyber secret key bytes";
+        let sealed = seal_secret(secret, "hunter2", "KYBER PRIVATE KEY").unwrap();
+
+        assert_ne!(&sealed[SALT_SIZE + NONCE_SIZE..], secret.as_slice());
+        let opened = open_secret(&sealed, "hunter2", "KYBER PRIVATE KEY").unwrap();
+        assert_eq!(opened, secret);
+    }
+
+    #[test]
+    fn test_open_rejects_wrong_passphrase_or_label() {
+        let sealed = seal_secret(b"secret", "hunter2", "KYBER PRIVATE KEY").unwrap();
+
+        assert!(matches!(
+            open_secret(&sealed, "hunter3", "KYBER PRIVATE KEY"),
+            Err(HermesError::InvalidPassword)
+        ));
+        assert!(open_secret(&sealed, "hunter2", "DILITHIUM PRIVATE KEY").is_err());
+        assert!(open_secret(&sealed[..10], "hunter2", "KYBER PRIVATE KEY").is_err());
+    }
+
+    #[test]
+    fn test_pem_roundtrip_and_protection_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key.pem");
+        let bytes = vec![7u8; 200];
+
+        write_secret_file(&path, encode_pem("KYBER PRIVATE KEY", &bytes).as_bytes()).unwrap();
+        assert!(!is_protected(&path));
+        let (label, decoded) = decode_pem(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(label, "KYBER PRIVATE KEY");
+        assert_eq!(decoded, bytes);
+
+        write_secret_file(
+            &path,
+            encode_pem("ENCRYPTED KYBER PRIVATE KEY", &bytes).as_bytes(),
+        )
+        .unwrap();
+        assert!(is_protected(&path));
+    }
+
+    #[test]
+    fn test_rsa_private_key_protected_roundtrip() {
+        use crate::crypto::rsa::{load_private_key, save_private_key};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alice.pem");
+        let path_str = path.to_str().unwrap();
+        let key = ::rsa::RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+
+        save_private_key(&key, &path, Some("correct horse")).unwrap();
+        assert!(is_protected(&path));
+        assert!(load_private_key(path_str, None).is_err());
+        assert!(matches!(
+            load_private_key(path_str, Some("wrong")),
+            Err(HermesError::InvalidPassword)
+        ));
+        assert_eq!(
+            load_private_key(path_str, Some("correct horse")).unwrap(),
+            key
+        );
+
+        save_private_key(&key, &path, None).unwrap();
+        assert!(!is_protected(&path));
+        assert_eq!(load_private_key(path_str, None).unwrap(), key);
+    }
+}
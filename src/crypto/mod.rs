@@ -1,22 +1,41 @@
 pub mod decrypt;
 pub mod dilithium;
+pub mod ed25519;
 pub mod encrypt;
+pub mod hybrid;
+pub mod identity;
+pub mod keyring;
+pub mod keystore;
 pub mod pqc;
 pub mod rsa;
+pub mod sender;
+pub mod stream;
+pub mod x25519;
 
 pub use decrypt::decrypt_data;
 pub use dilithium::{
     generate_dilithium_keypair, get_dilithium_fingerprint, load_dilithium_public_key,
     load_dilithium_secret_key, save_dilithium_public_key, save_dilithium_secret_key, sign_message,
-    verify_signature, DilithiumPublicKey, DilithiumSecretKey,
+    verify_signature, DilithiumPublicKey, DilithiumSecretKey, SignatureAlgorithm,
+};
+pub use ed25519::{
+    generate_ed25519_keypair, get_ed25519_fingerprint, load_ed25519_public_key,
+    load_ed25519_secret_key, save_ed25519_public_key, save_ed25519_secret_key, Ed25519PublicKey,
+    Ed25519SecretKey,
 };
 pub use encrypt::{encrypt_data, EncryptedPackage};
+pub use identity::{
+    generate_identity, ClassicalAlgorithm, IdentityPrivateKey, IdentityPublicKey, KeyType,
+};
 pub use pqc::{
     decrypt_with_kyber, encrypt_with_kyber, generate_kyber_keypair, get_kyber_fingerprint,
     load_kyber_public_key, load_kyber_secret_key, save_kyber_public_key, save_kyber_secret_key,
-    KyberPublicKey, KyberSecretKey,
+    KemAlgorithm, KyberPublicKey, KyberSecretKey,
 };
 pub use rsa::{
     decrypt_key_with_private, encrypt_key_for_recipient, generate_keypair, get_key_fingerprint,
-    load_private_key, load_public_key,
+    load_private_key, load_public_key, save_private_key,
 };
+pub use sender::SenderKey;
+pub use stream::{Decryptor, Encryptor};
+pub use x25519::{X25519PublicKey, X25519SecretKey};
@@ -1,14 +1,23 @@
+use crate::crypto::keystore;
 use crate::error::{HermesError, Result};
 use rsa::pkcs8::{
     DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding,
 };
+use rsa::pss::{BlindedSigningKey, Signature, VerifyingKey};
 use rsa::rand_core::OsRng;
-use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+use rsa::signature::{RandomizedSigner, SignatureEncoding, Verifier};
+use rsa::{Oaep, Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+use sha2::Sha256;
 use std::fs;
+use std::path::Path;
 
 const RSA_KEY_SIZE: usize = 4096;
 
-pub fn generate_keypair(private_key_path: &str, public_key_path: &str) -> Result<()> {
+pub fn generate_keypair(
+    private_key_path: &str,
+    public_key_path: &str,
+    passphrase: Option<&str>,
+) -> Result<()> {
     let mut rng = OsRng;
 
     let private_key = RsaPrivateKey::new(&mut rng, RSA_KEY_SIZE)
@@ -16,31 +25,48 @@ pub fn generate_keypair(private_key_path: &str, public_key_path: &str) -> Result
 
     let public_key = RsaPublicKey::from(&private_key);
 
-    let private_pem = private_key
-        .to_pkcs8_pem(LineEnding::LF)
-        .map_err(|e| HermesError::EncryptionFailed(format!("Private key encoding failed: {e}")))?;
-
     let public_pem = public_key
         .to_public_key_pem(LineEnding::LF)
         .map_err(|e| HermesError::EncryptionFailed(format!("Public key encoding failed: {e}")))?;
 
-    fs::write(private_key_path, private_pem.as_bytes())?;
+    save_private_key(&private_key, Path::new(private_key_path), passphrase)?;
     fs::write(public_key_path, public_pem.as_bytes())?;
 
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(private_key_path)?.permissions();
-        perms.set_mode(0o600);
-        fs::set_permissions(private_key_path, perms)?;
+    Ok(())
+}
+
+/// Save an RSA private key as PKCS#8 PEM, encrypted when a passphrase is given
+pub fn save_private_key(
+    private_key: &RsaPrivateKey,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<()> {
+    let private_pem = match passphrase {
+        Some(passphrase) => private_key.to_pkcs8_encrypted_pem(
+            OsRng,
+            passphrase.as_bytes(),
+            LineEnding::LF,
+        ),
+        None => private_key.to_pkcs8_pem(LineEnding::LF),
     }
+    .map_err(|e| HermesError::EncryptionFailed(format!("Private key encoding failed: {e}")))?;
 
-    Ok(())
+    keystore::write_secret_file(path, private_pem.as_bytes())
 }
 
-pub fn load_private_key(path: &str) -> Result<RsaPrivateKey> {
+/// Load an RSA private key, decrypting it if it is passphrase-protected
+pub fn load_private_key(path: &str, passphrase: Option<&str>) -> Result<RsaPrivateKey> {
     let pem = fs::read_to_string(path)?;
-    RsaPrivateKey::from_pkcs8_pem(&pem).map_err(|_e| HermesError::DecryptionFailed)
+
+    if !keystore::is_protected(Path::new(path)) {
+        return RsaPrivateKey::from_pkcs8_pem(&pem).map_err(|_e| HermesError::DecryptionFailed);
+    }
+
+    let passphrase = passphrase.ok_or_else(|| {
+        HermesError::ConfigError(format!("Private key is passphrase-protected: {path}"))
+    })?;
+    RsaPrivateKey::from_pkcs8_encrypted_pem(&pem, passphrase.as_bytes())
+        .map_err(|_e| HermesError::InvalidPassword)
 }
 
 pub fn load_public_key(path: &str) -> Result<RsaPublicKey> {
@@ -64,8 +90,35 @@ pub fn decrypt_key_with_private(
         .map_err(|_e| HermesError::DecryptionFailed)
 }
 
+/// Encrypt a key with RSA-OAEP (SHA-256), used by recipient slots from package v8
+pub fn encrypt_key_oaep(key: &[u8], public_key: &RsaPublicKey) -> Result<Vec<u8>> {
+    public_key
+        .encrypt(&mut OsRng, Oaep::new::<Sha256>(), key)
+        .map_err(|e| HermesError::EncryptionFailed(format!("RSA encryption failed: {e}")))
+}
+
+pub fn decrypt_key_oaep(encrypted_key: &[u8], private_key: &RsaPrivateKey) -> Result<Vec<u8>> {
+    private_key
+        .decrypt(Oaep::new::<Sha256>(), encrypted_key)
+        .map_err(|_e| HermesError::DecryptionFailed)
+}
+
+/// Sign `message` with RSA-PSS over SHA-256
+pub fn sign_pss(message: &[u8], private_key: &RsaPrivateKey) -> Vec<u8> {
+    BlindedSigningKey::<Sha256>::new(private_key.clone())
+        .sign_with_rng(&mut OsRng, message)
+        .to_vec()
+}
+
+pub fn verify_pss(signature: &[u8], message: &[u8], public_key: &RsaPublicKey) -> Result<()> {
+    let signature = Signature::try_from(signature).map_err(|_e| HermesError::DecryptionFailed)?;
+    VerifyingKey::<Sha256>::new(public_key.clone())
+        .verify(message, &signature)
+        .map_err(|_e| HermesError::DecryptionFailed)
+}
+
 pub fn get_key_fingerprint(public_key: &RsaPublicKey) -> Result<String> {
-    use sha2::{Digest, Sha256};
+    use sha2::Digest;
 
     let pem = public_key
         .to_public_key_pem(LineEnding::LF)
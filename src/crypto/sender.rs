@@ -0,0 +1,464 @@
+use crate::crypto::dilithium::{
+    load_dilithium_public_key, load_dilithium_secret_key, sign_detached, verify_detached,
+    DilithiumPublicKey, DilithiumSecretKey, SignatureAlgorithm,
+};
+use crate::crypto::ed25519::{
+    load_ed25519_public_key, load_ed25519_secret_key, sign_ed25519, verify_ed25519,
+    Ed25519PublicKey, Ed25519SecretKey,
+};
+use crate::crypto::encrypt::{read_exact, read_u16, read_vec, EncryptedPackage};
+use crate::crypto::identity::{IdentityPrivateKey, IdentityPublicKey};
+use crate::crypto::keystore;
+use crate::crypto::rsa::{sign_pss, verify_pss};
+use crate::error::{HermesError, Result};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+// Signed packages start their plaintext with a signature block:
+// magic, signer name (u16 length), SHA-256 of the signer's Dilithium public key,
+// Dilithium signature (u16 length) and classical signature (u16 length, 0 if absent):
+// RSA-PSS for RSA identities, Ed25519 for X25519 ones.
+// Both signatures cover the header's associated data and the SHA-256 of the content
+// after the block, so a signature can't be moved to another package. Packages made
+// with editable recipients leave the slots out, like they are for the payload itself.
+// The classical signature is required whenever the sender's classical key is known,
+// so a hybrid signature can't be downgraded to ML-DSA alone by stripping it.
+const SIGNATURE_MAGIC: &[u8; 4] = b"HSIG";
+const SIGNATURE_CONTEXT: &[u8] = b"hermes-sender-v1";
+
+/// Identifies a signing public key: SHA-256 of its raw bytes
+fn key_id(public_key: &DilithiumPublicKey) -> [u8; 32] {
+    Sha256::digest(public_key.as_bytes()).into()
+}
+
+fn signed_message(
+    signer: &str,
+    key_id: &[u8; 32],
+    header: &[u8],
+    content_hash: &[u8; 32],
+) -> Vec<u8> {
+    let mut message = SIGNATURE_CONTEXT.to_vec();
+    message.extend_from_slice(&(signer.len() as u16).to_le_bytes());
+    message.extend_from_slice(signer.as_bytes());
+    message.extend_from_slice(key_id);
+    message.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    message.extend_from_slice(header);
+    message.extend_from_slice(content_hash);
+    message
+}
+
+/// Classical key signing alongside ML-DSA
+#[allow(clippy::large_enum_variant)]
+enum ClassicalSigningKey {
+    Rsa(RsaPrivateKey),
+    Ed25519(Ed25519SecretKey),
+}
+
+enum ClassicalVerifyingKey {
+    Rsa(RsaPublicKey),
+    Ed25519(Ed25519PublicKey),
+}
+
+/// Signing keys of a local identity
+pub struct SenderKey {
+    name: String,
+    key_id: [u8; 32],
+    dilithium: DilithiumSecretKey,
+    classical: Option<ClassicalSigningKey>,
+}
+
+impl SenderKey {
+    /// Load the post-quantum signing key of `name`, plus its Ed25519 or RSA key for a
+    /// hybrid signature when present
+    pub fn load(name: &str) -> Result<Self> {
+        let keys_dir = crate::config::keys_dir()?;
+
+        let dilithium_path = keys_dir.join(format!("{name}_dilithium.pem"));
+        let dilithium_public_path = keys_dir.join(format!("{name}_dilithium.pub"));
+        if !dilithium_path.exists() || !dilithium_public_path.exists() {
+            return Err(HermesError::ConfigError(format!(
+                "Dilithium signing key not found for: {name}. Generate with --sign flag"
+            )));
+        }
+
+        let ed25519_path = keys_dir.join(format!("{name}_ed25519.pem"));
+        let identity_path = keys_dir.join(format!("{name}.pem"));
+        let passphrase =
+            keystore::unlock_passphrase(&[&dilithium_path, &ed25519_path, &identity_path], name)?;
+
+        let public_key = load_dilithium_public_key(dilithium_public_path.to_str().unwrap())?;
+        let dilithium =
+            load_dilithium_secret_key(dilithium_path.to_str().unwrap(), passphrase.as_deref())?;
+
+        // X25519 identities can't sign; they come with an Ed25519 key instead
+        let classical = if ed25519_path.exists() {
+            Some(ClassicalSigningKey::Ed25519(load_ed25519_secret_key(
+                ed25519_path.to_str().unwrap(),
+                passphrase.as_deref(),
+            )?))
+        } else if identity_path.exists() {
+            match IdentityPrivateKey::load(identity_path.to_str().unwrap(), passphrase.as_deref())?
+            {
+                IdentityPrivateKey::Rsa(rsa) => Some(ClassicalSigningKey::Rsa(rsa)),
+                IdentityPrivateKey::X25519(_) => None,
+            }
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            key_id: key_id(&public_key),
+            dilithium,
+            classical,
+        })
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The classical signature made alongside ML-DSA, if any
+    #[must_use]
+    pub fn classical_algorithm(&self) -> Option<&'static str> {
+        self.classical.as_ref().map(|key| match key {
+            ClassicalSigningKey::Rsa(_) => "RSA-PSS",
+            ClassicalSigningKey::Ed25519(_) => "Ed25519",
+        })
+    }
+
+    #[must_use]
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.dilithium.algorithm()
+    }
+}
+
+/// The signature block at the start of a signed payload
+pub(crate) struct SenderSignature {
+    signer: String,
+    key_id: [u8; 32],
+    dilithium: Vec<u8>,
+    classical: Option<Vec<u8>>,
+}
+
+impl SenderSignature {
+    pub(crate) fn sign(key: &SenderKey, header: &[u8], content_hash: &[u8; 32]) -> Result<Self> {
+        let message = signed_message(&key.name, &key.key_id, header, content_hash);
+
+        Ok(Self {
+            signer: key.name.clone(),
+            key_id: key.key_id,
+            dilithium: sign_detached(&message, &key.dilithium)?,
+            classical: match &key.classical {
+                Some(ClassicalSigningKey::Rsa(rsa)) => Some(sign_pss(&message, rsa)),
+                Some(ClassicalSigningKey::Ed25519(ed25519)) => {
+                    Some(sign_ed25519(&message, ed25519)?)
+                }
+                None => None,
+            },
+        })
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = SIGNATURE_MAGIC.to_vec();
+
+        bytes.extend_from_slice(&(self.signer.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(self.signer.as_bytes());
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&(self.dilithium.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.dilithium);
+
+        let classical = self.classical.as_deref().unwrap_or_default();
+        bytes.extend_from_slice(&(classical.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(classical);
+
+        bytes
+    }
+
+    pub(crate) fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        read_exact(reader, &mut magic)?;
+        if &magic != SIGNATURE_MAGIC {
+            return Err(HermesError::DecryptionFailed);
+        }
+
+        let signer_len = read_u16(reader)? as usize;
+        let signer = String::from_utf8(read_vec(reader, signer_len)?)
+            .map_err(|_| HermesError::DecryptionFailed)?;
+
+        let mut key_id = [0u8; 32];
+        read_exact(reader, &mut key_id)?;
+
+        let dilithium_len = read_u16(reader)? as usize;
+        let dilithium = read_vec(reader, dilithium_len)?;
+
+        let classical_len = read_u16(reader)? as usize;
+        let classical = if classical_len > 0 {
+            Some(read_vec(reader, classical_len)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            signer,
+            key_id,
+            dilithium,
+            classical,
+        })
+    }
+
+    /// Find the sender's public keys among known recipients (or our own keys)
+    pub(crate) fn resolve(self) -> Result<SenderVerifier> {
+        let dirs = [crate::config::recipients_dir()?, crate::config::keys_dir()?];
+
+        for dir in &dirs {
+            let Some((name, dilithium)) = find_dilithium_key(dir, &self.key_id)? else {
+                continue;
+            };
+
+            let classical = find_classical_key(dir, &name)?;
+            if self.classical.is_some() && classical.is_none() {
+                return Err(HermesError::ConfigError(format!(
+                    "Classical public key not found for sender: {name}"
+                )));
+            }
+
+            return Ok(SenderVerifier {
+                name,
+                signature: self,
+                dilithium,
+                classical,
+            });
+        }
+
+        Err(HermesError::ConfigError(format!(
+            "Unknown sender '{}' (key {}). Add their _dilithium.pub to the recipients directory",
+            self.signer,
+            hex::encode(&self.key_id[..8])
+        )))
+    }
+}
+
+fn find_dilithium_key(
+    dir: &Path,
+    wanted: &[u8; 32],
+) -> Result<Option<(String, DilithiumPublicKey)>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(None);
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix("_dilithium.pub"))
+        else {
+            continue;
+        };
+
+        let Ok(public_key) = load_dilithium_public_key(path.to_str().unwrap()) else {
+            continue;
+        };
+        if &key_id(&public_key) == wanted {
+            return Ok(Some((name.to_string(), public_key)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The sender's classical verifying key: `{name}_ed25519.pub`, or an RSA `{name}.pub`.
+/// None when neither is on file.
+fn find_classical_key(dir: &Path, name: &str) -> Result<Option<ClassicalVerifyingKey>> {
+    let ed25519_path = dir.join(format!("{name}_ed25519.pub"));
+    if ed25519_path.exists() {
+        return Ok(Some(ClassicalVerifyingKey::Ed25519(
+            load_ed25519_public_key(ed25519_path.to_str().unwrap())?,
+        )));
+    }
+
+    let path = dir.join(format!("{name}.pub"));
+    if !path.exists() {
+        return Ok(None);
+    }
+    match IdentityPublicKey::load(path.to_str().unwrap())? {
+        IdentityPublicKey::Rsa(rsa) => Ok(Some(ClassicalVerifyingKey::Rsa(rsa))),
+        IdentityPublicKey::X25519(_) => Ok(None),
+    }
+}
+
+/// A signature whose keys are known, waiting for the content hash
+pub(crate) struct SenderVerifier {
+    name: String,
+    signature: SenderSignature,
+    dilithium: DilithiumPublicKey,
+    classical: Option<ClassicalVerifyingKey>,
+}
+
+impl SenderVerifier {
+    /// Check every signature in the block; returns the sender's local name
+    pub(crate) fn verify(self, header: &[u8], content_hash: &[u8; 32]) -> Result<String> {
+        let signature = &self.signature;
+        let message = signed_message(&signature.signer, &signature.key_id, header, content_hash);
+
+        verify_detached(&signature.dilithium, &message, &self.dilithium)?;
+        match (&signature.classical, &self.classical) {
+            (Some(classical), Some(ClassicalVerifyingKey::Rsa(rsa))) => {
+                verify_pss(classical, &message, rsa)?;
+            }
+            (Some(classical), Some(ClassicalVerifyingKey::Ed25519(ed25519))) => {
+                verify_ed25519(classical, &message, ed25519)?;
+            }
+            (None, None) => {}
+            // A classical signature was stripped, or can't be checked
+            _ => return Err(HermesError::DecryptionFailed),
+        }
+
+        Ok(self.name)
+    }
+}
+
+/// Verify and strip the signature block of a whole in-memory payload
+pub(crate) fn open_signed(package: &EncryptedPackage, payload: &[u8]) -> Result<(String, Vec<u8>)> {
+    let mut content = payload;
+    let verifier = SenderSignature::read_from(&mut content)?.resolve()?;
+
+    let content_hash: [u8; 32] = Sha256::digest(content).into();
+    let sender = verifier.verify(&package.associated_data(), &content_hash)?;
+
+    Ok((sender, content.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::dilithium::generate_dilithium_keypair;
+    use crate::crypto::ed25519::generate_ed25519_keypair;
+    use crate::crypto::Encryptor;
+    use std::io::Write;
+
+    fn keypair(name: &str) -> (SenderKey, DilithiumPublicKey) {
+        let (public_key, secret_key) =
+            generate_dilithium_keypair(SignatureAlgorithm::MlDsa65).unwrap();
+        let key = SenderKey {
+            name: name.to_string(),
+            key_id: key_id(&public_key),
+            dilithium: secret_key,
+            classical: None,
+        };
+        (key, public_key)
+    }
+
+    fn verifier(signature: SenderSignature, public_key: DilithiumPublicKey) -> SenderVerifier {
+        SenderVerifier {
+            name: "alice".to_string(),
+            signature,
+            dilithium: public_key,
+            classical: None,
+        }
+    }
+
+    #[test]
+    fn test_signature_roundtrip_and_binding() {
+        let (key, public_key) = keypair("alice");
+        let hash = [7u8; 32];
+        let bytes = SenderSignature::sign(&key, b"header", &hash)
+            .unwrap()
+            .to_bytes();
+
+        let parsed = || SenderSignature::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(parsed().signer, "alice");
+
+        let (_, public_key_2) = keypair("alice");
+        let public_key = || public_key.clone();
+        assert_eq!(
+            verifier(parsed(), public_key())
+                .verify(b"header", &hash)
+                .unwrap(),
+            "alice"
+        );
+        assert!(verifier(parsed(), public_key())
+            .verify(b"other header", &hash)
+            .is_err());
+        assert!(verifier(parsed(), public_key())
+            .verify(b"header", &[8u8; 32])
+            .is_err());
+        assert!(verifier(parsed(), public_key_2)
+            .verify(b"header", &hash)
+            .is_err());
+    }
+
+    #[test]
+    fn test_ed25519_classical_signature() {
+        let (mut key, public_key) = keypair("alice");
+        let (ed25519_public, ed25519_secret) = generate_ed25519_keypair().unwrap();
+        key.classical = Some(ClassicalSigningKey::Ed25519(ed25519_secret));
+        assert_eq!(key.classical_algorithm(), Some("Ed25519"));
+
+        let hash = [7u8; 32];
+        let bytes = SenderSignature::sign(&key, b"header", &hash)
+            .unwrap()
+            .to_bytes();
+        let with_classical = |classical| SenderVerifier {
+            classical: Some(classical),
+            ..verifier(
+                SenderSignature::read_from(&mut &bytes[..]).unwrap(),
+                public_key.clone(),
+            )
+        };
+
+        assert!(
+            with_classical(ClassicalVerifyingKey::Ed25519(ed25519_public))
+                .verify(b"header", &hash)
+                .is_ok()
+        );
+        let (other_public, _) = generate_ed25519_keypair().unwrap();
+        assert!(with_classical(ClassicalVerifyingKey::Ed25519(other_public))
+            .verify(b"header", &hash)
+            .is_err());
+    }
+
+    #[test]
+    fn test_stripped_classical_signature_is_rejected() {
+        let (mut key, public_key) = keypair("alice");
+        let (ed25519_public, ed25519_secret) = generate_ed25519_keypair().unwrap();
+        key.classical = Some(ClassicalSigningKey::Ed25519(ed25519_secret));
+
+        let hash = [7u8; 32];
+        let mut signature = SenderSignature::sign(&key, b"header", &hash).unwrap();
+        signature.classical = None;
+        let bytes = signature.to_bytes();
+
+        // Valid ML-DSA signature, but the sender's Ed25519 key is on file
+        let stripped = SenderSignature::read_from(&mut &bytes[..]).unwrap();
+        let verifier = SenderVerifier {
+            classical: Some(ClassicalVerifyingKey::Ed25519(ed25519_public)),
+            ..verifier(stripped, public_key)
+        };
+        assert!(verifier.verify(b"header", &hash).is_err());
+    }
+
+    #[test]
+    fn test_encryptor_rejects_content_that_does_not_match_the_signature() {
+        let (key, _) = keypair("alice");
+        let content = b"signed content";
+
+        let mut encryptor =
+            Encryptor::with_password(Vec::new(), "pw", None, content.len() as u64, None).unwrap();
+        encryptor
+            .sign_as(&key, Sha256::digest(content).into())
+            .unwrap();
+        encryptor.write_all(b"other content!").unwrap();
+        assert!(encryptor.finish().is_err());
+
+        let mut encryptor =
+            Encryptor::with_password(Vec::new(), "pw", None, content.len() as u64, None).unwrap();
+        encryptor.write_all(content).unwrap();
+        assert!(encryptor.sign_as(&key, [0u8; 32]).is_err());
+    }
+}
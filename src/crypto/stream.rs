@@ -0,0 +1,655 @@
+use crate::crypto::decrypt::{decrypt_with_key, first_that_opens, password_key, recipient_keys};
+use crate::crypto::encrypt::{
+    expiry_timestamp, prepare_key_material, EncryptedPackage, KeyMaterial, FLAG_ANONYMOUS,
+    FLAG_EDITABLE_RECIPIENTS, FLAG_KEY_IDS, FLAG_SIGNED, MAGIC_BYTES, STREAM_VERSION,
+};
+use crate::crypto::sender::{SenderKey, SenderSignature, SenderVerifier};
+use crate::error::{HermesError, Result};
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use rsa::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+
+/// Plaintext bytes per segment of a streamed package.
+pub const SEGMENT_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+
+// Segmented layout (v3/v5/v7): header (as in v2, without the ciphertext length)
+// followed by AES-256-GCM segments. Every segment but the last holds exactly SEGMENT_SIZE
+// plaintext bytes; the last one is shorter (possibly empty), so a short segment
+// marks the end of the stream. The nonce of segment `i` is the first 7 bytes
+// of the header nonce, `i` as a big-endian u32 and a final-segment flag byte,
+// which rejects reordered, dropped or truncated segments. From v5 every segment
+// also carries the header's associated data (see `EncryptedPackage::associated_data`).
+fn segment_nonce(base: &[u8; 12], counter: u32, last: bool) -> Nonce<aes_gcm::aead::consts::U12> {
+    let mut nonce = *base;
+    nonce[7..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = u8::from(last);
+    Nonce::from(nonce)
+}
+
+fn to_io_error(err: HermesError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Streaming encryptor producing a v7 package.
+///
+/// The header is written before the first segment; plaintext written to the
+/// encryptor is sealed one segment at a time. [`Encryptor::finish`] must be called to
+/// write the final segment, otherwise the stream is rejected as truncated.
+pub struct Encryptor<W: Write> {
+    writer: W,
+    cipher: Aes256Gcm,
+    data_key: [u8; 32],
+    nonce: [u8; 12],
+    /// Header not written yet; it can still be marked as signed
+    pending_header: Option<EncryptedPackage>,
+    aad: Vec<u8>,
+    counter: u32,
+    buffer: Vec<u8>,
+    /// Content hash the sender signed, and the hash of what was actually written
+    signed: Option<([u8; 32], Sha256)>,
+}
+
+impl<W: Write> Encryptor<W> {
+    pub fn with_password(
+        writer: W,
+        password: &str,
+        filename: Option<String>,
+        original_size: u64,
+        ttl_hours: Option<u64>,
+    ) -> Result<Self> {
+        let material = prepare_key_material(Some(password), None, false)?;
+        Self::new(writer, material, filename, original_size, ttl_hours)
+    }
+
+    pub fn with_recipients(
+        writer: W,
+        recipient_names: Vec<String>,
+        use_pqc: bool,
+        filename: Option<String>,
+        original_size: u64,
+        ttl_hours: Option<u64>,
+    ) -> Result<Self> {
+        let material = prepare_key_material(None, Some(recipient_names), use_pqc)?;
+        Self::new(writer, material, filename, original_size, ttl_hours)
+    }
+
+    fn new(
+        writer: W,
+        material: KeyMaterial,
+        filename: Option<String>,
+        original_size: u64,
+        ttl_hours: Option<u64>,
+    ) -> Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(&material.data_key)
+            .map_err(|e| HermesError::EncryptionFailed(format!("Cipher creation failed: {e}")))?;
+
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce[..7]);
+
+        let header = EncryptedPackage {
+            magic: *MAGIC_BYTES,
+            version: STREAM_VERSION,
+            flags: material.flags,
+            salt: material.salt,
+            nonce,
+            checksum: [0u8; 32], // segments are authenticated individually
+            original_size,
+            expires_at: expiry_timestamp(ttl_hours),
+            filename,
+            recipients: material.recipients,
+            ciphertext: Vec::new(),
+        };
+
+        Ok(Self {
+            writer,
+            cipher,
+            data_key: material.data_key,
+            nonce,
+            pending_header: Some(header),
+            aad: Vec::new(),
+            counter: 0,
+            buffer: Vec::with_capacity(SEGMENT_SIZE),
+            signed: None,
+        })
+    }
+
+    /// Leave recipient slots unlabeled, so the package doesn't reveal who it is for.
+    /// Recipients then try their keys against every slot. Call before signing or writing.
+    pub fn hide_recipients(&mut self) -> Result<()> {
+        let header = self
+            .pending_header
+            .as_mut()
+            .filter(|_| self.signed.is_none() && self.buffer.is_empty())
+            .ok_or_else(|| {
+                HermesError::EncryptionFailed(
+                    "Cannot hide recipients after data was written".to_string(),
+                )
+            })?;
+        if !header.is_multi_recipient() {
+            return Err(HermesError::EncryptionFailed(
+                "Only recipient packages can be anonymous".to_string(),
+            ));
+        }
+
+        header.flags = (header.flags & !FLAG_KEY_IDS) | FLAG_ANONYMOUS;
+        for recipient in &mut header.recipients {
+            recipient.name.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Leave the recipient table out of the authenticated header, so slots can later be
+    /// added or removed without re-encrypting. Anyone who can write the package can then
+    /// drop or reorder slots undetected. Call before signing or writing.
+    pub fn allow_recipient_edits(&mut self) -> Result<()> {
+        let header = self
+            .pending_header
+            .as_mut()
+            .filter(|_| self.signed.is_none() && self.buffer.is_empty())
+            .ok_or_else(|| {
+                HermesError::EncryptionFailed(
+                    "Cannot allow recipient edits after data was written".to_string(),
+                )
+            })?;
+        if !header.is_multi_recipient() {
+            return Err(HermesError::EncryptionFailed(
+                "Only recipient packages have editable recipients".to_string(),
+            ));
+        }
+
+        header.flags |= FLAG_EDITABLE_RECIPIENTS;
+        Ok(())
+    }
+
+    /// Sign the package as `key`, before any data is written. `content_hash` is the
+    /// SHA-256 of everything that will be written; [`Encryptor::finish`] fails if the
+    /// content turns out to differ.
+    pub fn sign_as(&mut self, key: &SenderKey, content_hash: [u8; 32]) -> Result<()> {
+        let header = self.pending_header.as_mut().ok_or_else(|| {
+            HermesError::EncryptionFailed("Cannot sign after data was written".to_string())
+        })?;
+        if self.signed.is_some() || !self.buffer.is_empty() {
+            return Err(HermesError::EncryptionFailed(
+                "Cannot sign after data was written".to_string(),
+            ));
+        }
+
+        header.flags |= FLAG_SIGNED;
+        let signature = SenderSignature::sign(key, &header.associated_data(), &content_hash)?;
+        self.write_all(&signature.to_bytes())?;
+
+        self.signed = Some((content_hash, Sha256::new()));
+        Ok(())
+    }
+
+    /// The data key, for sealing a Dead Man's Switch release envelope.
+    pub(crate) fn data_key(&self) -> &[u8; 32] {
+        &self.data_key
+    }
+
+    fn seal_segment(&mut self, last: bool) -> Result<()> {
+        if let Some(header) = self.pending_header.take() {
+            self.aad = header.associated_data();
+            self.writer.write_all(&header.header_bytes())?;
+        }
+
+        let nonce = segment_nonce(&self.nonce, self.counter, last);
+        let sealed = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: self.buffer.as_ref(),
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|e| HermesError::EncryptionFailed(format!("Encryption failed: {e}")))?;
+
+        self.writer.write_all(&sealed)?;
+        self.buffer.clear();
+        self.counter = self.counter.checked_add(1).ok_or_else(|| {
+            HermesError::EncryptionFailed("Stream exceeds maximum segment count".to_string())
+        })?;
+
+        Ok(())
+    }
+
+    /// Seal the final segment and return the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        // Leave the stream unterminated rather than seal a signature that doesn't match
+        if let Some((expected, written)) = self.signed.take() {
+            if written.finalize().as_slice() != expected {
+                return Err(HermesError::EncryptionFailed(
+                    "Data changed while it was being signed".to_string(),
+                ));
+            }
+        }
+
+        self.seal_segment(true)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for Encryptor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let take = buf.len().min(SEGMENT_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..take]);
+        if let Some((_, written)) = self.signed.as_mut() {
+            written.update(&buf[..take]);
+        }
+
+        // A full segment is never the last one; finish() seals the (short) tail
+        if self.buffer.len() == SEGMENT_SIZE {
+            self.seal_segment(false).map_err(to_io_error)?;
+        }
+
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads and authenticates the segments of a v3/v5/v7 stream.
+struct SegmentReader<R: Read> {
+    reader: R,
+    cipher: Aes256Gcm,
+    nonce: [u8; 12],
+    aad: Vec<u8>,
+    counter: u32,
+    finished: bool,
+}
+
+impl<R: Read> SegmentReader<R> {
+    fn new(reader: R, header: &EncryptedPackage, key: &[u8; 32]) -> Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| HermesError::DecryptionFailed)?;
+
+        Ok(Self {
+            reader,
+            cipher,
+            nonce: header.nonce,
+            aad: header.associated_data(),
+            counter: 0,
+            finished: false,
+        })
+    }
+
+    /// Open the first segment with whichever candidate key authenticates it,
+    /// returning the reader positioned after it, the segment's plaintext and the key.
+    fn open_first(
+        reader: R,
+        header: &EncryptedPackage,
+        candidates: &[[u8; 32]],
+    ) -> Result<(Self, Vec<u8>, [u8; 32])> {
+        let (first, others) = candidates
+            .split_first()
+            .ok_or(HermesError::DecryptionFailed)?;
+        let mut segments = Self::new(reader, header, first)?;
+        let (sealed, last) = segments.read_sealed()?;
+
+        for key in std::iter::once(first).chain(others) {
+            segments.cipher =
+                Aes256Gcm::new_from_slice(key).map_err(|_| HermesError::DecryptionFailed)?;
+            if let Ok(plaintext) = segments.open(&sealed, last) {
+                return Ok((segments, plaintext, *key));
+            }
+        }
+
+        Err(HermesError::DecryptionFailed)
+    }
+
+    /// Next segment's plaintext, or `None` once the final segment was read.
+    fn next_segment(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let (sealed, last) = self.read_sealed()?;
+        self.open(&sealed, last).map(Some)
+    }
+
+    fn read_sealed(&mut self) -> Result<(Vec<u8>, bool)> {
+        let mut sealed = vec![0u8; SEGMENT_SIZE + TAG_SIZE];
+        let n = read_full(&mut self.reader, &mut sealed)?;
+        sealed.truncate(n);
+
+        let last = n < SEGMENT_SIZE + TAG_SIZE;
+        if last && n < TAG_SIZE {
+            // Stream ended without a final segment
+            return Err(HermesError::DecryptionFailed);
+        }
+
+        Ok((sealed, last))
+    }
+
+    /// Authenticate and decrypt the current segment, then move past it.
+    fn open(&mut self, sealed: &[u8], last: bool) -> Result<Vec<u8>> {
+        let nonce = segment_nonce(&self.nonce, self.counter, last);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                &nonce,
+                Payload {
+                    msg: sealed,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|_| HermesError::DecryptionFailed)?;
+
+        if last {
+            self.finished = true;
+        } else {
+            self.counter = self
+                .counter
+                .checked_add(1)
+                .ok_or(HermesError::DecryptionFailed)?;
+        }
+
+        Ok(plaintext)
+    }
+}
+
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(HermesError::IoError(e)),
+        }
+    }
+    Ok(filled)
+}
+
+/// Decrypt the in-memory segments of a streamed package.
+pub(crate) fn open_segments(package: &EncryptedPackage, key: &[u8; 32]) -> Result<Vec<u8>> {
+    let mut reader = SegmentReader::new(&package.ciphertext[..], package, key)?;
+    let mut plaintext = Vec::new();
+
+    while let Some(segment) = reader.next_segment()? {
+        plaintext.extend_from_slice(&segment);
+    }
+
+    if !reader.reader.is_empty() {
+        return Err(HermesError::DecryptionFailed);
+    }
+
+    Ok(plaintext)
+}
+
+/// Streaming decryptor for Hermes packages.
+///
+/// The header is parsed on construction so it can be inspected (expiry,
+/// recipients) before unlocking. Streamed packages are then decrypted segment
+/// by segment in constant memory; v1/v2/v4 packages are bounded by their format
+/// and are decrypted in one piece on unlock.
+pub struct Decryptor<R: Read> {
+    header: EncryptedPackage,
+    reader: Option<R>,
+    segments: Option<SegmentReader<R>>,
+    plaintext: Vec<u8>,
+    pos: usize,
+    /// Data key that unlocked the package
+    data_key: Option<[u8; 32]>,
+    /// Sender signature to check once the whole content has been read
+    signature: Option<(SenderVerifier, Sha256)>,
+    sender: Option<String>,
+}
+
+impl<R: Read> Decryptor<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| HermesError::DecryptionFailed)?;
+
+        let header = if &magic == MAGIC_BYTES {
+            EncryptedPackage::read_from(&mut (&magic[..]).chain(&mut reader))?
+        } else {
+            // Legacy JSON package
+            let mut bytes = magic.to_vec();
+            reader.read_to_end(&mut bytes)?;
+            EncryptedPackage::from_bytes(&bytes)?
+        };
+
+        Ok(Self {
+            header,
+            reader: Some(reader),
+            segments: None,
+            plaintext: Vec::new(),
+            pos: 0,
+            data_key: None,
+            signature: None,
+            sender: None,
+        })
+    }
+
+    #[must_use]
+    pub fn header(&self) -> &EncryptedPackage {
+        &self.header
+    }
+
+    /// The sender whose signature was verified, once the content has been read to the end
+    #[must_use]
+    pub fn sender(&self) -> Option<&str> {
+        self.sender.as_deref()
+    }
+
+    pub fn unlock_with_password(&mut self, password: &str) -> Result<()> {
+        let key = password_key(&self.header, password)?;
+        self.unlock(&[key])
+    }
+
+    /// Unlock with the local key called `recipient_name`.
+    pub fn unlock_with_recipient(&mut self, recipient_name: &str) -> Result<()> {
+        let candidates = recipient_keys(&self.header, Some(recipient_name))?;
+        self.unlock(&candidates)
+    }
+
+    /// Unlock with whichever local key the package was encrypted for.
+    pub fn unlock_with_own_keys(&mut self) -> Result<()> {
+        let candidates = recipient_keys(&self.header, None)?;
+        self.unlock(&candidates)
+    }
+
+    /// Unlock with a data key recovered elsewhere, e.g. a DMS release envelope.
+    pub fn unlock_with_key(&mut self, key: &[u8; 32]) -> Result<()> {
+        self.unlock(&[*key])
+    }
+
+    /// The data key, once unlocked, for wrapping it for more recipients.
+    pub(crate) fn data_key(&self) -> Option<&[u8; 32]> {
+        self.data_key.as_ref()
+    }
+
+    /// Unlock with the first candidate key that authenticates the start of the payload
+    fn unlock(&mut self, candidates: &[[u8; 32]]) -> Result<()> {
+        if self.reader.is_none() {
+            return Err(HermesError::ConfigError(
+                "Decryptor is already unlocked".to_string(),
+            ));
+        }
+
+        if self.header.is_streamed() {
+            let reader = self.reader.take().unwrap();
+            let (segments, first, key) =
+                SegmentReader::open_first(reader, &self.header, candidates)?;
+            self.segments = Some(segments);
+            self.plaintext = first;
+            self.pos = 0;
+            self.data_key = Some(key);
+        } else {
+            let (plaintext, key) = first_that_opens(candidates, |key| {
+                decrypt_with_key(&self.header, key).map(|plaintext| (plaintext, *key))
+            })?;
+            self.plaintext = plaintext;
+            self.data_key = Some(key);
+            self.header.ciphertext = Vec::new();
+            self.reader = None;
+        }
+
+        // Look the sender up now, so an unknown signer fails before any output is written
+        if self.header.is_signed() {
+            let verifier = SenderSignature::read_from(&mut PayloadReader(self))?.resolve()?;
+            self.signature = Some((verifier, Sha256::new()));
+        }
+
+        Ok(())
+    }
+
+    /// Decrypted payload, including any signature block
+    fn read_payload(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plaintext.len() {
+            let Some(segments) = self.segments.as_mut() else {
+                return Ok(0);
+            };
+
+            match segments.next_segment().map_err(to_io_error)? {
+                Some(segment) => {
+                    self.plaintext = segment;
+                    self.pos = 0;
+                }
+                None => {
+                    // Reject anything appended after the final segment
+                    let mut probe = [0u8; 1];
+                    if read_full(&mut segments.reader, &mut probe).map_err(to_io_error)? != 0 {
+                        return Err(to_io_error(HermesError::DecryptionFailed));
+                    }
+                    self.segments = None;
+                    return Ok(0);
+                }
+            }
+        }
+
+        let n = buf.len().min(self.plaintext.len() - self.pos);
+        buf[..n].copy_from_slice(&self.plaintext[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+struct PayloadReader<'a, R: Read>(&'a mut Decryptor<R>);
+
+impl<R: Read> Read for PayloadReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read_payload(buf)
+    }
+}
+
+impl<R: Read> Read for Decryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.reader.is_some() {
+            return Err(to_io_error(HermesError::ConfigError(
+                "Decryptor must be unlocked before reading".to_string(),
+            )));
+        }
+
+        let n = self.read_payload(buf)?;
+
+        if n > 0 {
+            if let Some((_, hasher)) = self.signature.as_mut() {
+                hasher.update(&buf[..n]);
+            }
+        } else if !buf.is_empty() {
+            if let Some((verifier, hasher)) = self.signature.take() {
+                let content_hash: [u8; 32] = hasher.finalize().into();
+                let sender = verifier
+                    .verify(&self.header.associated_data(), &content_hash)
+                    .map_err(to_io_error)?;
+                self.sender = Some(sender);
+            }
+        }
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypt_stream(plaintext: &[u8], password: &str) -> Vec<u8> {
+        let mut encryptor = Encryptor::with_password(
+            Vec::new(),
+            password,
+            Some("stream.bin".to_string()),
+            plaintext.len() as u64,
+            None,
+        )
+        .unwrap();
+        // Write in odd-sized pieces to exercise segment buffering
+        for piece in plaintext.chunks(7919) {
+            encryptor.write_all(piece).unwrap();
+        }
+        encryptor.finish().unwrap()
+    }
+
+    fn decrypt_stream(encrypted: &[u8], password: &str) -> io::Result<Vec<u8>> {
+        let mut decryptor = Decryptor::new(encrypted).unwrap();
+        decryptor.unlock_with_password(password).unwrap();
+        let mut plaintext = Vec::new();
+        decryptor.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_stream_roundtrip_segment_boundaries() {
+        for len in [0, 1, SEGMENT_SIZE - 1, SEGMENT_SIZE, 3 * SEGMENT_SIZE + 17] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let encrypted = encrypt_stream(&plaintext, "stream_password");
+
+            let package = EncryptedPackage::from_bytes(&encrypted).unwrap();
+            assert_eq!(package.version, STREAM_VERSION);
+            assert_eq!(package.original_size, len as u64);
+            assert_eq!(package.filename, Some("stream.bin".to_string()));
+
+            assert_eq!(
+                decrypt_stream(&encrypted, "stream_password").unwrap(),
+                plaintext
+            );
+            assert_eq!(
+                crate::crypto::decrypt_data(&encrypted, "stream_password").unwrap(),
+                plaintext
+            );
+        }
+    }
+
+    #[test]
+    fn test_stream_rejects_truncation() {
+        let plaintext = vec![7u8; 2 * SEGMENT_SIZE + 100];
+        let encrypted = encrypt_stream(&plaintext, "pw");
+
+        // Dropping the final segment leaves a stream ending on a full segment
+        let truncated = &encrypted[..encrypted.len() - (100 + TAG_SIZE)];
+        assert!(decrypt_stream(truncated, "pw").is_err());
+    }
+
+    #[test]
+    fn test_stream_rejects_tampering_and_trailing_data() {
+        let plaintext = vec![1u8; SEGMENT_SIZE + 10];
+        let mut encrypted = encrypt_stream(&plaintext, "pw");
+
+        let mut appended = encrypted.clone();
+        appended.push(0);
+        assert!(decrypt_stream(&appended, "pw").is_err());
+
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 0x01;
+        assert!(decrypt_stream(&encrypted, "pw").is_err());
+    }
+
+    #[test]
+    fn test_decryptor_reads_whole_buffer_package() {
+        let plaintext = b"legacy whole-buffer package".to_vec();
+        let encrypted = crate::crypto::encrypt_data(&plaintext, "pw", None, None).unwrap();
+
+        assert_eq!(decrypt_stream(&encrypted, "pw").unwrap(), plaintext);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::crypto::keystore;
+use crate::error::{HermesError, Result};
+use aws_lc_rs::agreement::{self, PrivateKey, UnparsedPublicKey, X25519};
+use aws_lc_rs::encoding::{AsBigEndian, Curve25519SeedBin};
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+pub(crate) const PUBLIC_KEY_LABEL: &str = "X25519 PUBLIC KEY";
+pub(crate) const PRIVATE_KEY_LABEL: &str = "X25519 PRIVATE KEY";
+const KEY_LEN: usize = 32;
+
+// X25519 is used as a KEM: the sender agrees on a secret between a fresh ephemeral key
+// and the recipient's key, and sends the ephemeral public key as the ciphertext.
+// HKDF-SHA256 binds the secret to both public keys.
+const KEM_CONTEXT: &[u8] = b"hermes-x25519-v1";
+
+/// X25519 public key of an identity
+#[derive(Clone)]
+pub struct X25519PublicKey {
+    bytes: [u8; KEY_LEN],
+}
+
+/// X25519 private key of an identity
+pub struct X25519SecretKey {
+    bytes: [u8; KEY_LEN],
+}
+
+impl X25519PublicKey {
+    fn new(bytes: &[u8]) -> Result<Self> {
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| HermesError::ConfigError("Invalid X25519 public key".to_string()))?;
+        Ok(Self { bytes })
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl X25519SecretKey {
+    fn new(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; KEY_LEN] = bytes
+            .try_into()
+            .map_err(|_| HermesError::ConfigError("Invalid X25519 private key".to_string()))?;
+        // Reject anything aws-lc wouldn't accept up front, not at first use
+        PrivateKey::from_private_key(&X25519, &bytes)
+            .map_err(|_| HermesError::ConfigError("Invalid X25519 private key".to_string()))?;
+        Ok(Self { bytes })
+    }
+
+    fn private_key(&self) -> Result<PrivateKey> {
+        PrivateKey::from_private_key(&X25519, &self.bytes)
+            .map_err(|_| HermesError::DecryptionFailed)
+    }
+
+    /// The matching public key
+    pub fn public_key(&self) -> Result<X25519PublicKey> {
+        let public = self
+            .private_key()?
+            .compute_public_key()
+            .map_err(|_| HermesError::ConfigError("Invalid X25519 private key".to_string()))?;
+        X25519PublicKey::new(public.as_ref())
+    }
+}
+
+/// Generate an X25519 keypair
+pub fn generate_x25519_keypair() -> Result<(X25519PublicKey, X25519SecretKey)> {
+    let failed =
+        || HermesError::KeyGenerationFailed("X25519 keypair generation failed".to_string());
+
+    let private = PrivateKey::generate(&X25519).map_err(|_| failed())?;
+    let seed: Curve25519SeedBin = private.as_be_bytes().map_err(|_| failed())?;
+    let secret = X25519SecretKey::new(seed.as_ref())?;
+
+    Ok((secret.public_key()?, secret))
+}
+
+/// Agree on a fresh shared secret with `public_key`, returning (ephemeral public key, secret)
+pub(crate) fn encapsulate(public_key: &X25519PublicKey) -> Result<([u8; KEY_LEN], [u8; 32])> {
+    let failed = || HermesError::EncryptionFailed("X25519 key agreement failed".to_string());
+
+    let ephemeral = PrivateKey::generate(&X25519).map_err(|_| failed())?;
+    let ephemeral_public: [u8; KEY_LEN] = ephemeral
+        .compute_public_key()
+        .map_err(|_| failed())?
+        .as_ref()
+        .try_into()
+        .map_err(|_| failed())?;
+
+    let shared = agree(&ephemeral, &public_key.bytes).map_err(|_| failed())?;
+    Ok((
+        ephemeral_public,
+        derive_secret(&shared, &ephemeral_public, &public_key.bytes),
+    ))
+}
+
+/// Recover the shared secret of an ephemeral public key made by [`encapsulate`]
+pub(crate) fn decapsulate(
+    ephemeral_public: &[u8],
+    secret_key: &X25519SecretKey,
+) -> Result<[u8; 32]> {
+    if ephemeral_public.len() != KEY_LEN {
+        return Err(HermesError::DecryptionFailed);
+    }
+
+    let shared = agree(&secret_key.private_key()?, ephemeral_public)?;
+    let public_key = secret_key
+        .public_key()
+        .map_err(|_| HermesError::DecryptionFailed)?;
+    Ok(derive_secret(&shared, ephemeral_public, &public_key.bytes))
+}
+
+/// Raw X25519; aws-lc rejects peer keys that give an all-zero secret
+fn agree(private: &PrivateKey, peer: &[u8]) -> Result<[u8; 32]> {
+    agreement::agree(
+        private,
+        UnparsedPublicKey::new(&X25519, peer),
+        HermesError::DecryptionFailed,
+        |shared| shared.try_into().map_err(|_| HermesError::DecryptionFailed),
+    )
+}
+
+fn derive_secret(shared: &[u8; 32], ephemeral_public: &[u8], recipient_public: &[u8]) -> [u8; 32] {
+    let mut info = KEM_CONTEXT.to_vec();
+    info.extend_from_slice(ephemeral_public);
+    info.extend_from_slice(recipient_public);
+
+    let mut secret = [0u8; 32];
+    Hkdf::<Sha256>::new(None, shared)
+        .expand(&info, &mut secret)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    secret
+}
+
+/// Save an X25519 public key to file
+pub fn save_x25519_public_key(public_key: &X25519PublicKey, path: &Path) -> Result<()> {
+    fs::write(
+        path,
+        keystore::encode_pem(PUBLIC_KEY_LABEL, &public_key.bytes),
+    )
+    .map_err(|e| HermesError::KeyGenerationFailed(format!("Failed to save X25519 public key: {e}")))
+}
+
+/// Save an X25519 private key to file, encrypted when a passphrase is given
+pub fn save_x25519_secret_key(
+    secret_key: &X25519SecretKey,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<()> {
+    let pem = keystore::encode_secret_pem(PRIVATE_KEY_LABEL, &secret_key.bytes, passphrase)?;
+    keystore::write_secret_file(path, pem.as_bytes()).map_err(|e| {
+        HermesError::KeyGenerationFailed(format!("Failed to save X25519 private key: {e}"))
+    })
+}
+
+/// Load an X25519 public key from file
+pub fn load_x25519_public_key(path: &str) -> Result<X25519PublicKey> {
+    let content = fs::read_to_string(path)?;
+    let (label, bytes) = keystore::decode_pem(&content)?;
+    if label != PUBLIC_KEY_LABEL {
+        return Err(HermesError::ConfigError(format!(
+            "Not an X25519 public key: {path}"
+        )));
+    }
+    X25519PublicKey::new(&bytes)
+}
+
+/// Load an X25519 private key, decrypting it if it is passphrase-protected
+pub fn load_x25519_secret_key(path: &str, passphrase: Option<&str>) -> Result<X25519SecretKey> {
+    let content = fs::read_to_string(path)?;
+    let (label, bytes) = keystore::decode_secret_pem(&content, passphrase, path)?;
+    if label != PRIVATE_KEY_LABEL {
+        return Err(HermesError::ConfigError(format!(
+            "Not an X25519 private key: {path}"
+        )));
+    }
+    X25519SecretKey::new(&bytes)
+}
+
+/// Get fingerprint of X25519 public key
+pub fn get_x25519_fingerprint(public_key: &X25519PublicKey) -> String {
+    hex::encode(&Sha256::digest(public_key.bytes)[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_x25519_encapsulate_roundtrip() {
+        let (public_key, secret_key) = generate_x25519_keypair().unwrap();
+        let (ephemeral, secret) = encapsulate(&public_key).unwrap();
+        assert_eq!(decapsulate(&ephemeral, &secret_key).unwrap(), secret);
+
+        let (_, other_secret_key) = generate_x25519_keypair().unwrap();
+        assert_ne!(decapsulate(&ephemeral, &other_secret_key).unwrap(), secret);
+
+        // Low-order points are refused rather than giving a predictable secret
+        assert!(decapsulate(&[0u8; KEY_LEN], &secret_key).is_err());
+        assert!(decapsulate(&ephemeral[..31], &secret_key).is_err());
+    }
+
+    #[test]
+    fn test_x25519_key_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let public_path = dir.path().join("alice.pub");
+        let secret_path = dir.path().join("alice.pem");
+        let (public_key, secret_key) = generate_x25519_keypair().unwrap();
+
+        save_x25519_public_key(&public_key, &public_path).unwrap();
+        save_x25519_secret_key(&secret_key, &secret_path, Some("pw")).unwrap();
+        assert!(keystore::is_protected(&secret_path));
+
+        let loaded = load_x25519_public_key(public_path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.as_bytes(), public_key.as_bytes());
+        assert!(load_x25519_secret_key(secret_path.to_str().unwrap(), None).is_err());
+        let loaded = load_x25519_secret_key(secret_path.to_str().unwrap(), Some("pw")).unwrap();
+        assert_eq!(
+            loaded.public_key().unwrap().as_bytes(),
+            public_key.as_bytes()
+        );
+
+        // A key of another type is refused by its label
+        assert!(load_x25519_public_key(secret_path.to_str().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::chunking::ChunkOrigin;
+use crate::crypto::keyring::NamedKey;
+use crate::error::{HermesError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// An encrypted chunk already on the server
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexedChunk {
+    pub remote_path: String,
+    /// Size of the encrypted package, checked against the server before reuse
+    pub encrypted_size: u64,
+    pub origin: ChunkOrigin,
+}
+
+/// Uploaded chunks by plaintext SHA-256, kept per profile and recipient set since
+/// a chunk can only be reused for exactly the recipient keys it was encrypted for
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ChunkIndex {
+    pub sets: HashMap<String, HashMap<String, IndexedChunk>>,
+}
+
+impl ChunkIndex {
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let mut file = File::open(path)?;
+        let mut json = String::new();
+        file.read_to_string(&mut json)?;
+
+        serde_json::from_str(&json).map_err(HermesError::SerializationError)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(HermesError::SerializationError)?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Key of a recipient set on a profile, by key ID so a replaced or rotated key
+    /// starts a new set; the order of recipients doesn't matter
+    #[must_use]
+    pub fn set_key(profile: &str, recipients: &[NamedKey]) -> String {
+        let mut ids: Vec<_> = recipients.iter().map(NamedKey::current_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        format!("{profile}:{}", ids.join(","))
+    }
+
+    #[must_use]
+    pub fn get(&self, set: &str, hash: &str) -> Option<&IndexedChunk> {
+        self.sets.get(set)?.get(hash)
+    }
+
+    pub fn insert(&mut self, set: &str, hash: String, chunk: IndexedChunk) {
+        self.sets
+            .entry(set.to_string())
+            .or_default()
+            .insert(hash, chunk);
+    }
+}
+
+pub fn get_index_path() -> Result<PathBuf> {
+    crate::config::state_file("chunk_index.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{generate_identity, KeyType};
+
+    fn install_key(dir: &Path, name: &str) -> NamedKey {
+        generate_identity(
+            KeyType::X25519,
+            &dir.join(format!("{name}.pem")),
+            &dir.join(format!("{name}.pub")),
+            None,
+        )
+        .unwrap();
+        NamedKey::load(dir, name).unwrap()
+    }
+
+    #[test]
+    fn test_sets_are_order_independent_and_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chunk_index.json");
+        let alice = install_key(dir.path(), "alice");
+        let bob = install_key(dir.path(), "bob");
+
+        let set = ChunkIndex::set_key("default", &[bob.clone(), alice.clone()]);
+        assert_eq!(
+            set,
+            ChunkIndex::set_key("default", &[alice.clone(), bob.clone()])
+        );
+        assert_ne!(set, ChunkIndex::set_key("work", &[alice.clone(), bob]));
+
+        let mut index = ChunkIndex::default();
+        index.insert(
+            &set,
+            "abc".to_string(),
+            IndexedChunk {
+                remote_path: "/vault/id.chunk.001.enc".to_string(),
+                encrypted_size: 42,
+                origin: ChunkOrigin {
+                    manifest_id: "id".to_string(),
+                    index: 0,
+                    total_chunks: 1,
+                },
+            },
+        );
+        index.save_to_file(&path).unwrap();
+
+        let index = ChunkIndex::load_from_file(&path).unwrap();
+        assert_eq!(index.get(&set, "abc").unwrap().encrypted_size, 42);
+        assert!(index.get(&set, "def").is_none());
+        assert!(index
+            .get(&ChunkIndex::set_key("default", &[alice]), "abc")
+            .is_none());
+    }
+
+    #[test]
+    fn test_replaced_key_starts_a_new_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let before = ChunkIndex::set_key("default", &[install_key(dir.path(), "bob")]);
+
+        // Same contact name, new key
+        let after = ChunkIndex::set_key("default", &[install_key(dir.path(), "bob")]);
+        assert_ne!(before, after);
+    }
+}
@@ -1,20 +1,99 @@
 use crate::error::{HermesError, Result};
+use crate::notify::NotifierConfig;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// What happens when a switch expires
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DmsAction {
+    /// Shred the file on the server
+    #[default]
+    Delete,
+    /// Publish a key envelope, sealed at send time, to the recipients' inbox
+    Release {
+        recipients: Vec<String>,
+        envelope_path: String,
+    },
+    /// Only notify; the file is left in place
+    Notify,
+}
+
+impl DmsAction {
+    pub fn describe(&self) -> String {
+        match self {
+            DmsAction::Delete => "Delete".to_string(),
+            DmsAction::Release { recipients, .. } => {
+                format!("Release to {}", recipients.join(", "))
+            }
+            DmsAction::Notify => "Notify".to_string(),
+        }
+    }
+}
+
+/// Dead Man's Switch options chosen at send time
+#[derive(Debug, Clone, Default)]
+pub struct DmsOptions {
+    pub timeout_hours: u64,
+    pub release_to: Option<Vec<String>>,
+    pub notify_only: bool,
+    /// Extra address for the SMTP notifier
+    pub notification_email: Option<String>,
+    /// Channels for this switch; empty means the global ones from `Settings`
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+/// Contents of a release envelope: the data key of the protected file
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReleaseEnvelope {
+    pub file_path: String,
+    pub data_key: String,
+}
+
+impl ReleaseEnvelope {
+    pub fn key(&self) -> Result<[u8; 32]> {
+        hex::decode(&self.data_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(HermesError::DecryptionFailed)
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DeadManSwitch {
     pub file_path: String,
+    /// Profile of the vault holding the file; switches from before profiles were on "default"
+    #[serde(default = "default_profile")]
+    pub profile: String,
     pub timeout_hours: u64,
     pub last_checkin: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
     pub enabled: bool,
     pub grace_period_hours: u64,
     pub notification_email: Option<String>,
+    #[serde(default)]
+    pub action: DmsAction,
+    /// When the grace-period warning was sent for the current check-in cycle
+    #[serde(default)]
+    pub warned_at: Option<DateTime<Utc>>,
+    /// When the switch fired; fired switches are never acted on again
+    #[serde(default)]
+    pub fired_at: Option<DateTime<Utc>>,
+    /// Error from the last failed attempt to fire, retried on the next run
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// Per-switch notification channels, overriding the global ones
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+fn default_profile() -> String {
+    crate::config::DEFAULT_PROFILE.to_string()
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -27,17 +106,62 @@ impl DeadManSwitch {
         let now = Utc::now();
         Self {
             file_path,
+            profile: default_profile(),
             timeout_hours,
             last_checkin: now,
             created_at: now,
             enabled: true,
             grace_period_hours: timeout_hours / 4,
             notification_email: None,
+            action: DmsAction::Delete,
+            warned_at: None,
+            fired_at: None,
+            last_error: None,
+            notifiers: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_profile(mut self, profile: String) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    #[must_use]
+    pub fn with_action(mut self, action: DmsAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    #[must_use]
+    pub fn with_notifications(
+        mut self,
+        email: Option<String>,
+        notifiers: Vec<NotifierConfig>,
+    ) -> Self {
+        self.notification_email = email;
+        self.notifiers = notifiers;
+        self
+    }
+
+    /// Channels to notify: this switch's own, or else the global defaults. The
+    /// notification email goes through the global SMTP channel when the switch's own
+    /// channels have none.
+    pub fn notifiers(&self, global: &[NotifierConfig]) -> Vec<NotifierConfig> {
+        if self.notifiers.is_empty() {
+            return global.to_vec();
         }
+
+        let mut channels = self.notifiers.clone();
+        if self.notification_email.is_some() && !channels.iter().any(NotifierConfig::is_smtp) {
+            channels.extend(global.iter().filter(|n| n.is_smtp()).cloned());
+        }
+        channels
     }
 
     pub fn checkin(&mut self) {
         self.last_checkin = Utc::now();
+        self.warned_at = None;
     }
 
     pub fn time_until_deletion(&self) -> Duration {
@@ -61,7 +185,20 @@ impl DeadManSwitch {
     }
 
     pub fn needs_warning(&self) -> bool {
-        self.is_in_grace_period() && self.enabled
+        self.is_in_grace_period() && self.enabled && self.warned_at.is_none()
+    }
+
+    pub fn has_fired(&self) -> bool {
+        self.fired_at.is_some()
+    }
+
+    pub fn mark_warned(&mut self) {
+        self.warned_at = Some(Utc::now());
+    }
+
+    pub fn mark_fired(&mut self) {
+        self.fired_at = Some(Utc::now());
+        self.last_error = None;
     }
 
     pub fn disable(&mut self) {
@@ -95,7 +232,7 @@ impl DmsRegistry {
     pub fn get_expired(&self) -> Vec<&DeadManSwitch> {
         self.switches
             .values()
-            .filter(|s| s.enabled && s.is_expired())
+            .filter(|s| s.enabled && !s.has_fired() && s.is_expired())
             .collect()
     }
 
@@ -138,13 +275,49 @@ impl Default for DmsRegistry {
 }
 
 pub fn get_registry_path() -> Result<std::path::PathBuf> {
-    let home = dirs::home_dir()
-        .ok_or_else(|| HermesError::ConfigError("Cannot find home directory".to_string()))?;
+    crate::config::state_file("dms_registry.json")
+}
+
+pub fn get_envelope_dir() -> Result<PathBuf> {
+    let envelope_dir = crate::config::hermes_home()?.join("dms_envelopes");
+    std::fs::create_dir_all(&envelope_dir)?;
 
-    let config_dir = home.join(".hermes");
-    std::fs::create_dir_all(&config_dir)?;
+    Ok(envelope_dir)
+}
+
+/// Seal a file's data key for the release recipients and keep it locally until the switch fires
+pub fn store_release_envelope(
+    file_path: &str,
+    data_key: &[u8; 32],
+    recipients: Vec<String>,
+    use_pqc: bool,
+) -> Result<PathBuf> {
+    let envelope = ReleaseEnvelope {
+        file_path: file_path.to_string(),
+        data_key: hex::encode(data_key),
+    };
+    let json = serde_json::to_vec(&envelope)?;
+
+    let sealed = crate::crypto::encrypt::encrypt_data_multi(
+        &json,
+        None,
+        Some("dms_release.json".to_string()),
+        None,
+        Some(recipients),
+        use_pqc,
+    )?;
+
+    let digest = Sha256::digest(file_path.as_bytes());
+    let path = get_envelope_dir()?.join(format!("{}.enc", hex::encode(&digest[..8])));
+    crate::crypto::keystore::write_secret_file(&path, &sealed)?;
+
+    Ok(path)
+}
 
-    Ok(config_dir.join("dms_registry.json"))
+/// Open a release envelope with a recipient key, returning the protected file's data key
+pub fn open_release_envelope(sealed: &[u8], recipient_name: &str) -> Result<ReleaseEnvelope> {
+    let json = crate::crypto::decrypt::decrypt_data_multi(sealed, recipient_name)?;
+    Ok(serde_json::from_slice(&json)?)
 }
 
 pub fn format_duration(duration: Duration) -> String {
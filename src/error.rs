@@ -49,6 +49,15 @@ pub enum HermesError {
 
     #[error("Remote path not specified")]
     RemotePathNotSpecified,
+
+    #[error("Host key for {host} does not match known_hosts (server sent {fingerprint}) - possible man-in-the-middle attack")]
+    HostKeyMismatch { host: String, fingerprint: String },
+
+    #[error("Host {host} is not in known_hosts (fingerprint {fingerprint})")]
+    HostKeyUnknown { host: String, fingerprint: String },
+
+    #[error("Notification failed: {0}")]
+    NotificationFailed(String),
 }
 
 impl From<dialoguer::Error> for HermesError {
@@ -1,9 +1,12 @@
+pub mod archive;
 pub mod chunking;
 pub mod commands;
 pub mod config;
 pub mod crypto;
+pub mod dedup;
 pub mod dms;
 pub mod error;
+pub mod notify;
 pub mod progress;
 pub mod shamir;
 pub mod steganography;
@@ -8,6 +8,9 @@ use hermes::ui;
 #[command(about = "Military-grade secure file transfer system", long_about = None)]
 #[command(version)]
 pub struct Cli {
+    #[arg(long, global = true, help = "Server profile from config.toml to use")]
+    profile: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -18,10 +21,43 @@ enum Commands {
     Init,
 
     #[command(about = "Display current configuration")]
-    Config,
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
 
     #[command(about = "List all encrypted files in vault")]
-    List,
+    List {
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = commands::list::SortKey::Name,
+            help = "Sort order"
+        )]
+        sort: commands::list::SortKey,
+
+        #[arg(long, help = "Only show expired packages")]
+        expired: bool,
+
+        #[arg(
+            long = "for",
+            value_name = "NAME",
+            help = "Only show packages for this recipient"
+        )]
+        recipient: Option<String>,
+    },
+
+    #[command(about = "Delete expired packages from the vault")]
+    Sweep {
+        #[arg(long, help = "Keep running and sweep periodically")]
+        daemon: bool,
+
+        #[arg(long, default_value_t = 60, help = "Minutes between sweeps in daemon mode")]
+        interval: u64,
+
+        #[arg(long, help = "Show what would be deleted without deleting")]
+        dry_run: bool,
+    },
 
     #[command(about = "Generate shell completion script")]
     Completion {
@@ -35,7 +71,7 @@ enum Commands {
         test_connection: bool,
     },
 
-    #[command(about = "Generate RSA keypair")]
+    #[command(about = "Generate an identity keypair (RSA or X25519)")]
     Keygen {
         #[arg(help = "Name/identifier for this keypair")]
         name: String,
@@ -43,11 +79,38 @@ enum Commands {
         #[arg(short, long, help = "Output directory for keys")]
         output: Option<String>,
 
-        #[arg(long, help = "Generate hybrid keypair with post-quantum Kyber")]
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = hermes::crypto::KeyType::Rsa,
+            help = "Identity key type (x25519 also generates an Ed25519 signing key)"
+        )]
+        key_type: hermes::crypto::KeyType,
+
+        #[arg(long, help = "Generate hybrid keypair with post-quantum ML-KEM")]
         pqc: bool,
 
-        #[arg(long, help = "Generate Dilithium signing keypair")]
+        #[arg(long, help = "Generate ML-DSA signing keypair")]
         sign: bool,
+
+        #[arg(long, help = "Encrypt private keys with a passphrase")]
+        protect: bool,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = hermes::crypto::KemAlgorithm::MlKem768,
+            help = "ML-KEM parameter set for --pqc"
+        )]
+        kem: hermes::crypto::KemAlgorithm,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = hermes::crypto::SignatureAlgorithm::MlDsa65,
+            help = "ML-DSA parameter set for --sign"
+        )]
+        sig_alg: hermes::crypto::SignatureAlgorithm,
     },
 
     #[command(about = "Import recipient's public key")]
@@ -68,16 +131,16 @@ enum Commands {
         output: Option<String>,
     },
 
-    #[command(about = "Import recipient's Kyber (PQC) public key")]
+    #[command(about = "Import recipient's post-quantum (ML-KEM or Kyber) public key")]
     ImportKyberPubkey {
         #[arg(help = "Recipient name/identifier")]
         name: String,
 
-        #[arg(help = "Path to Kyber public key file")]
+        #[arg(help = "Path to _kyber.pub key file")]
         pubkey: String,
     },
 
-    #[command(about = "Export your Kyber (PQC) public key")]
+    #[command(about = "Export your post-quantum (ML-KEM) public key")]
     ExportKyberPubkey {
         #[arg(help = "Your keypair name")]
         name: String,
@@ -86,7 +149,7 @@ enum Commands {
         output: Option<String>,
     },
 
-    #[command(about = "List all RSA keys")]
+    #[command(about = "List all identity keys")]
     ListKeys,
 
     #[command(about = "Split RSA private key into shares (Shamir's Secret Sharing)")]
@@ -127,17 +190,49 @@ enum Commands {
         #[arg(long, help = "Archive old keys before rotation")]
         archive: bool,
 
-        #[arg(long, help = "Also rotate Kyber (PQC) keys")]
+        #[arg(
+            long,
+            value_enum,
+            help = "Switch the identity to this key type (default: keep the current one)"
+        )]
+        key_type: Option<hermes::crypto::KeyType>,
+
+        #[arg(long, help = "Also rotate ML-KEM (PQC) keys")]
         pqc: bool,
 
-        #[arg(long, help = "Also rotate Dilithium (signing) keys")]
+        #[arg(long, help = "Also rotate ML-DSA (signing) keys")]
         sign: bool,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = hermes::crypto::KemAlgorithm::MlKem768,
+            help = "ML-KEM parameter set for --pqc"
+        )]
+        kem: hermes::crypto::KemAlgorithm,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = hermes::crypto::SignatureAlgorithm::MlDsa65,
+            help = "ML-DSA parameter set for --sign"
+        )]
+        sig_alg: hermes::crypto::SignatureAlgorithm,
+    },
+
+    #[command(about = "Change or remove the passphrase on a private key")]
+    KeyPasswd {
+        #[arg(help = "Key name")]
+        name: String,
+
+        #[arg(long, help = "Remove passphrase protection")]
+        remove: bool,
     },
 
     #[command(about = "List archived keys from previous rotations")]
     ListArchivedKeys,
 
-    #[command(about = "Sign a file with Dilithium (post-quantum signature)")]
+    #[command(about = "Sign a file with ML-DSA (post-quantum signature)")]
     SignFile {
         #[arg(help = "Path to file to sign")]
         file_path: String,
@@ -149,7 +244,7 @@ enum Commands {
         output: Option<String>,
     },
 
-    #[command(about = "Verify a Dilithium signature")]
+    #[command(about = "Verify an ML-DSA or Dilithium signature")]
     VerifySignature {
         #[arg(help = "Path to signed file")]
         signed_file: String,
@@ -170,6 +265,15 @@ enum Commands {
     #[command(about = "Show Dead Man's Switch status for all files")]
     DmsStatus,
 
+    #[command(about = "Act on Dead Man's Switches: warn in the grace period, fire when expired")]
+    DmsRun {
+        #[arg(long, help = "Keep running and check periodically")]
+        daemon: bool,
+
+        #[arg(long, default_value_t = 15, help = "Minutes between checks in daemon mode")]
+        interval: u64,
+    },
+
     #[command(about = "Disable Dead Man's Switch for a file")]
     DmsDisable {
         #[arg(help = "Remote file path")]
@@ -199,6 +303,23 @@ enum Commands {
 
         #[arg(long, value_delimiter = ',', help = "Recipients (comma-separated)")]
         recipients: Option<Vec<String>>,
+
+        #[arg(long, help = "Sign as this local key (ML-DSA, plus Ed25519 or RSA-PSS when present)")]
+        sign_as: Option<String>,
+
+        #[arg(
+            long,
+            requires = "recipients",
+            help = "Don't record which keys the package is for; recipients try all their keys"
+        )]
+        anonymous: bool,
+
+        #[arg(
+            long,
+            requires = "recipients",
+            help = "Allow adding or removing recipients later; the recipient list is then not authenticated"
+        )]
+        editable_recipients: bool,
     },
 
     #[command(about = "Receive and decrypt a text message")]
@@ -209,7 +330,7 @@ enum Commands {
         #[arg(short, long, help = "Decryption password (if not using recipient key)")]
         password: Option<String>,
 
-        #[arg(long, help = "Recipient name (for multi-recipient messages)")]
+        #[arg(long, help = "Local key to decrypt with (default: try all your keys)")]
         recipient: Option<String>,
     },
 
@@ -237,8 +358,50 @@ enum Commands {
         #[arg(long, help = "Dead Man's Switch timeout in hours")]
         dms: Option<u64>,
 
+        #[arg(
+            long,
+            value_delimiter = ',',
+            requires = "dms",
+            help = "On DMS expiry, release the file to these recipients instead of deleting it"
+        )]
+        dms_release: Option<Vec<String>>,
+
+        #[arg(
+            long,
+            requires = "dms",
+            conflicts_with = "dms_release",
+            help = "On DMS expiry, only notify; keep the file"
+        )]
+        dms_notify: bool,
+
+        #[arg(long, requires = "dms", help = "Email DMS warnings to this address")]
+        dms_email: Option<String>,
+
+        #[arg(long, requires = "dms", help = "POST DMS events to this webhook URL")]
+        dms_webhook: Option<String>,
+
+        #[arg(long, requires = "dms", help = "Run this shell command on DMS events")]
+        dms_hook: Option<String>,
+
         #[arg(long, help = "Use post-quantum hybrid encryption (requires PQC keys)")]
         pqc: bool,
+
+        #[arg(long, help = "Sign as this local key (ML-DSA, plus Ed25519 or RSA-PSS when present)")]
+        sign_as: Option<String>,
+
+        #[arg(
+            long,
+            requires = "recipients",
+            help = "Don't record which keys the package is for; recipients try all their keys"
+        )]
+        anonymous: bool,
+
+        #[arg(
+            long,
+            requires = "recipients",
+            help = "Allow adding or removing recipients later; the recipient list is then not authenticated"
+        )]
+        editable_recipients: bool,
     },
 
     #[command(about = "Receive and decrypt a file")]
@@ -252,8 +415,11 @@ enum Commands {
         #[arg(short, long, help = "Output file path")]
         output: Option<String>,
 
-        #[arg(long, help = "Recipient name (for multi-recipient files)")]
+        #[arg(long, help = "Local key to decrypt with (default: try all your keys)")]
         recipient: Option<String>,
+
+        #[arg(long, help = "Dead Man's Switch release envelope to unlock with")]
+        envelope: Option<String>,
     },
 
     #[command(about = "Encrypt and send large file in chunks (memory-efficient)")]
@@ -273,6 +439,15 @@ enum Commands {
 
         #[arg(long, value_delimiter = ',', help = "Recipients (comma-separated)")]
         recipients: Option<Vec<String>>,
+
+        #[arg(
+            short,
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u16).range(1..=64),
+            help = "Chunks processed in parallel, each over its own connection"
+        )]
+        jobs: u16,
     },
 
     #[command(about = "Receive and decrypt chunked file")]
@@ -288,6 +463,15 @@ enum Commands {
 
         #[arg(long, help = "Recipient name (for multi-recipient files)")]
         recipient: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u16).range(1..=64),
+            help = "Chunks processed in parallel, each over its own connection"
+        )]
+        jobs: u16,
     },
 
     #[command(about = "Encrypt and send multiple files (batch operation)")]
@@ -320,6 +504,9 @@ enum Commands {
         #[arg(short, long, help = "Include subdirectories")]
         recursive: bool,
 
+        #[arg(long, help = "Send the whole tree as one encrypted tar archive")]
+        archive: bool,
+
         #[arg(
             short = 't',
             long,
@@ -331,6 +518,21 @@ enum Commands {
         recipients: Option<Vec<String>>,
     },
 
+    #[command(about = "Receive and extract a directory sent with send-dir --archive")]
+    RecvDir {
+        #[arg(help = "Remote encrypted archive name")]
+        remote_file: String,
+
+        #[arg(short, long, help = "Decryption password (if not using recipient key)")]
+        password: Option<String>,
+
+        #[arg(short, long, help = "Output directory")]
+        output: Option<String>,
+
+        #[arg(long, help = "Local key to decrypt with (default: try all your keys)")]
+        recipient: Option<String>,
+    },
+
     #[command(about = "Receive and decrypt multiple files (batch operation)")]
     RecvBatch {
         #[arg(help = "Pattern or list of remote files", required = true)]
@@ -388,27 +590,96 @@ enum Commands {
         analyze: bool,
     },
 
-    #[command(about = "Start web UI server")]
+    #[command(about = "Change who can open an existing package")]
+    Package {
+        #[command(subcommand)]
+        action: PackageAction,
+    },
+
+    #[command(about = "Start web UI server (files up to ~75 MB; use send-file for larger ones)")]
     WebUi {
         #[arg(short, long, default_value = "8080", help = "Port to listen on")]
         port: u16,
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigAction {
+    #[command(about = "Make a profile the default")]
+    Use {
+        #[arg(help = "Profile name")]
+        profile: String,
+    },
+
+    #[command(about = "List configured profiles")]
+    Profiles,
+}
+
+#[derive(Subcommand)]
+enum PackageAction {
+    #[command(about = "Wrap a package's data key for more recipients, rewriting only its header")]
+    AddRecipient {
+        #[arg(help = "Encrypted file (vault path, or local path with --local)")]
+        file: String,
+
+        #[arg(long, value_delimiter = ',', required = true, help = "Recipients to add (comma-separated)")]
+        recipients: Vec<String>,
+
+        #[arg(long, help = "Local key to unwrap the data key with (default: try all your keys)")]
+        recipient: Option<String>,
+
+        #[arg(long, help = "The file is on this machine, not in the vault")]
+        local: bool,
+    },
+
+    #[command(about = "Remove recipients from a package, rewriting only its header")]
+    RemoveRecipient {
+        #[arg(help = "Encrypted file (vault path, or local path with --local)")]
+        file: String,
+
+        #[arg(long, value_delimiter = ',', required = true, help = "Recipients to remove (comma-separated)")]
+        recipients: Vec<String>,
+
+        #[arg(long, help = "The file is on this machine, not in the vault")]
+        local: bool,
+    },
+}
+
 fn main() -> Result<()> {
     ui::print_banner();
 
     let cli = Cli::parse();
 
+    if let Some(ref profile) = cli.profile {
+        hermes::config::select_profile(profile);
+    }
+
     match cli.command {
         Commands::Init => {
             commands::init::execute()?;
         }
-        Commands::Config => {
-            commands::config::execute()?;
-        }
-        Commands::List => {
-            commands::list::execute()?;
+        Commands::Config { action } => match action {
+            None => commands::config::execute()?,
+            Some(ConfigAction::Use { profile }) => commands::config::use_profile(&profile)?,
+            Some(ConfigAction::Profiles) => commands::config::list_profiles()?,
+        },
+        Commands::List {
+            sort,
+            expired,
+            recipient,
+        } => {
+            let filter = commands::list::ListFilter {
+                expired_only: expired,
+                recipient,
+            };
+            commands::list::execute(sort, &filter)?;
+        }
+        Commands::Sweep {
+            daemon,
+            interval,
+            dry_run,
+        } => {
+            commands::sweep::execute(daemon, interval, dry_run)?;
         }
         Commands::Completion { shell } => {
             use clap::CommandFactory;
@@ -422,10 +693,23 @@ fn main() -> Result<()> {
         Commands::Keygen {
             name,
             output,
+            key_type,
             pqc,
             sign,
+            protect,
+            kem,
+            sig_alg,
         } => {
-            commands::keygen::execute(&name, output.as_deref(), pqc, sign)?;
+            commands::keygen::execute(
+                &name,
+                output.as_deref(),
+                key_type,
+                pqc,
+                sign,
+                protect,
+                kem,
+                sig_alg,
+            )?;
         }
         Commands::ImportPubkey { name, pubkey } => {
             commands::import_pubkey::execute(&name, &pubkey)?;
@@ -459,10 +743,16 @@ fn main() -> Result<()> {
         Commands::KeyRotate {
             name,
             archive,
+            key_type,
             pqc,
             sign,
+            kem,
+            sig_alg,
         } => {
-            commands::key_rotate::execute(&name, archive, pqc, sign)?;
+            commands::key_rotate::execute(&name, archive, key_type, pqc, sign, kem, sig_alg)?;
+        }
+        Commands::KeyPasswd { name, remove } => {
+            commands::key_passwd::execute(&name, remove)?;
         }
         Commands::ListArchivedKeys => {
             commands::key_list_archived::execute()?;
@@ -487,6 +777,9 @@ fn main() -> Result<()> {
         Commands::DmsStatus => {
             commands::dms_status::execute()?;
         }
+        Commands::DmsRun { daemon, interval } => {
+            commands::dms_run::execute(daemon, interval)?;
+        }
         Commands::DmsDisable { file_path } => {
             commands::dms_disable::execute(&file_path)?;
         }
@@ -499,6 +792,9 @@ fn main() -> Result<()> {
             remote_path,
             ttl,
             recipients,
+            sign_as,
+            anonymous,
+            editable_recipients,
         } => {
             commands::send_msg::execute(
                 &message,
@@ -506,6 +802,9 @@ fn main() -> Result<()> {
                 remote_path.as_deref(),
                 ttl,
                 recipients,
+                sign_as.as_deref(),
+                anonymous,
+                editable_recipients,
             )?;
         }
         Commands::RecvMsg {
@@ -522,8 +821,30 @@ fn main() -> Result<()> {
             ttl,
             recipients,
             dms,
+            dms_release,
+            dms_notify,
+            dms_email,
+            dms_webhook,
+            dms_hook,
             pqc,
+            sign_as,
+            anonymous,
+            editable_recipients,
         } => {
+            use hermes::notify::NotifierConfig;
+
+            let notifiers = dms_webhook
+                .map(|url| NotifierConfig::Webhook { url })
+                .into_iter()
+                .chain(dms_hook.map(|command| NotifierConfig::Command { command }))
+                .collect();
+            let dms = dms.map(|timeout_hours| hermes::dms::DmsOptions {
+                timeout_hours,
+                release_to: dms_release,
+                notify_only: dms_notify,
+                notification_email: dms_email,
+                notifiers,
+            });
             commands::send_file::execute(
                 &file_path,
                 password.as_deref(),
@@ -532,6 +853,9 @@ fn main() -> Result<()> {
                 recipients,
                 dms,
                 pqc,
+                sign_as.as_deref(),
+                anonymous,
+                editable_recipients,
             )?;
         }
         Commands::RecvFile {
@@ -539,12 +863,14 @@ fn main() -> Result<()> {
             password,
             output,
             recipient,
+            envelope,
         } => {
             commands::recv_file::execute(
                 &remote_file,
                 password.as_deref(),
                 output.as_deref(),
                 recipient.as_deref(),
+                envelope.as_deref(),
             )?;
         }
         Commands::SendFileChunked {
@@ -552,20 +878,29 @@ fn main() -> Result<()> {
             password,
             ttl,
             recipients,
+            jobs,
         } => {
-            commands::send_file_chunked::execute(&file_path, password.as_deref(), ttl, recipients)?;
+            commands::send_file_chunked::execute(
+                &file_path,
+                password.as_deref(),
+                ttl,
+                recipients,
+                usize::from(jobs),
+            )?;
         }
         Commands::RecvFileChunked {
             remote_manifest,
             password,
             output,
             recipient,
+            jobs,
         } => {
             commands::recv_file_chunked::execute(
                 &remote_manifest,
                 password.as_deref(),
                 output.as_deref(),
                 recipient.as_deref(),
+                usize::from(jobs),
             )?;
         }
         Commands::SendBatch {
@@ -576,10 +911,39 @@ fn main() -> Result<()> {
         } => {
             commands::send_batch::execute(file_paths, password.as_deref(), ttl, recipients)?;
         }
+        Commands::Package { action } => match action {
+            PackageAction::AddRecipient {
+                file,
+                recipients,
+                recipient,
+                local,
+            } => commands::package::add_recipient(&file, &recipients, recipient.as_deref(), local)?,
+            PackageAction::RemoveRecipient {
+                file,
+                recipients,
+                local,
+            } => commands::package::remove_recipient(&file, &recipients, local)?,
+        },
+
+        Commands::RecvDir {
+            remote_file,
+            password,
+            output,
+            recipient,
+        } => {
+            commands::recv_dir::execute(
+                &remote_file,
+                password.as_deref(),
+                output.as_deref(),
+                recipient.as_deref(),
+            )?;
+        }
+
         Commands::SendDir {
             dir_path,
             password,
             recursive,
+            archive,
             ttl,
             recipients,
         } => {
@@ -589,6 +953,7 @@ fn main() -> Result<()> {
                 ttl,
                 recipients,
                 recursive,
+                archive,
             )?;
         }
         Commands::RecvBatch {
@@ -0,0 +1,390 @@
+use crate::error::{HermesError, Result};
+use chrono::{DateTime, Utc};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport as _};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// A notification channel, configured globally in `Settings` or per switch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotifierConfig {
+    /// Email via an SMTP relay
+    Smtp {
+        host: String,
+        #[serde(default = "default_smtp_port")]
+        port: u16,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        from: String,
+        #[serde(default)]
+        to: Vec<String>,
+        /// Upgrade the connection with STARTTLS
+        #[serde(default = "default_true")]
+        starttls: bool,
+    },
+    /// HTTP POST of a JSON payload
+    Webhook { url: String },
+    /// Shell command run with the event in HERMES_DMS_* variables
+    Command { command: String },
+}
+
+impl NotifierConfig {
+    #[must_use]
+    pub fn is_smtp(&self) -> bool {
+        matches!(self, NotifierConfig::Smtp { .. })
+    }
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    Warning,
+    Fired,
+}
+
+impl NotificationKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            NotificationKind::Warning => "warning",
+            NotificationKind::Fired => "fired",
+        }
+    }
+}
+
+/// A Dead Man's Switch event to deliver
+#[derive(Serialize, Debug, Clone)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub file_path: String,
+    pub action: String,
+    pub deadline: DateTime<Utc>,
+    pub message: String,
+}
+
+impl Notification {
+    fn subject(&self) -> String {
+        match self.kind {
+            NotificationKind::Warning => format!("[Hermes] Check-in required: {}", self.file_path),
+            NotificationKind::Fired => format!("[Hermes] Dead Man's Switch fired: {}", self.file_path),
+        }
+    }
+}
+
+pub trait Notifier {
+    fn notify(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Build the notifier for a channel; `extra_to` adds email recipients to SMTP channels
+pub fn build(config: &NotifierConfig, extra_to: Option<&str>) -> Box<dyn Notifier> {
+    match config {
+        NotifierConfig::Smtp {
+            host,
+            port,
+            username,
+            password,
+            from,
+            to,
+            starttls,
+        } => {
+            let mut to = to.clone();
+            to.extend(extra_to.map(str::to_string));
+            Box::new(SmtpNotifier {
+                host: host.clone(),
+                port: *port,
+                credentials: username
+                    .clone()
+                    .map(|u| Credentials::new(u, password.clone().unwrap_or_default())),
+                from: from.clone(),
+                to,
+                starttls: *starttls,
+            })
+        }
+        NotifierConfig::Webhook { url } => Box::new(WebhookNotifier { url: url.clone() }),
+        NotifierConfig::Command { command } => Box::new(CommandNotifier {
+            command: command.clone(),
+        }),
+    }
+}
+
+/// Deliver to every channel, collecting failures instead of stopping at the first
+pub fn notify_all(
+    configs: &[NotifierConfig],
+    extra_to: Option<&str>,
+    notification: &Notification,
+) -> Result<()> {
+    let errors: Vec<String> = configs
+        .iter()
+        .filter_map(|config| build(config, extra_to).notify(notification).err())
+        .map(|e| e.to_string())
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(HermesError::NotificationFailed(errors.join("; ")))
+    }
+}
+
+pub struct SmtpNotifier {
+    host: String,
+    port: u16,
+    credentials: Option<Credentials>,
+    from: String,
+    to: Vec<String>,
+    starttls: bool,
+}
+
+impl Notifier for SmtpNotifier {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        if self.to.is_empty() {
+            return Err(HermesError::NotificationFailed(
+                "SMTP notifier has no recipients".to_string(),
+            ));
+        }
+
+        let parse = |addr: &str| {
+            addr.parse::<Mailbox>().map_err(|e| {
+                HermesError::NotificationFailed(format!("Invalid email address {addr}: {e}"))
+            })
+        };
+
+        let mut builder = Message::builder()
+            .from(parse(&self.from)?)
+            .subject(notification.subject());
+        for addr in &self.to {
+            builder = builder.to(parse(addr)?);
+        }
+        let email = builder
+            .body(notification.message.clone())
+            .map_err(|e| HermesError::NotificationFailed(format!("Email build failed: {e}")))?;
+
+        let mut transport = if self.starttls {
+            SmtpTransport::starttls_relay(&self.host)
+                .map_err(|e| HermesError::NotificationFailed(format!("SMTP setup failed: {e}")))?
+        } else {
+            SmtpTransport::builder_dangerous(&self.host)
+        }
+        .port(self.port);
+        if let Some(ref credentials) = self.credentials {
+            transport = transport.credentials(credentials.clone());
+        }
+
+        transport
+            .build()
+            .send(&email)
+            .map_err(|e| HermesError::NotificationFailed(format!("SMTP send failed: {e}")))?;
+
+        Ok(())
+    }
+}
+
+pub struct WebhookNotifier {
+    url: String,
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        let body = serde_json::to_string(notification)?;
+
+        ureq::post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&body)
+            .map_err(|e| HermesError::NotificationFailed(format!("Webhook failed: {e}")))?;
+
+        Ok(())
+    }
+}
+
+pub struct CommandNotifier {
+    command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&self, notification: &Notification) -> Result<()> {
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C");
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c");
+            cmd
+        };
+
+        let status = cmd
+            .arg(&self.command)
+            .env("HERMES_DMS_EVENT", notification.kind.as_str())
+            .env("HERMES_DMS_FILE", &notification.file_path)
+            .env("HERMES_DMS_ACTION", &notification.action)
+            .env("HERMES_DMS_DEADLINE", notification.deadline.to_rfc3339())
+            .env("HERMES_DMS_MESSAGE", &notification.message)
+            .status()
+            .map_err(|e| HermesError::NotificationFailed(format!("Hook failed to start: {e}")))?;
+
+        if !status.success() {
+            return Err(HermesError::NotificationFailed(format!(
+                "Hook exited with {status}"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn sample(kind: NotificationKind) -> Notification {
+        Notification {
+            kind,
+            file_path: "/vault/files/will.enc".to_string(),
+            action: "Delete".to_string(),
+            deadline: Utc::now(),
+            message: "Check in within 2h".to_string(),
+        }
+    }
+
+    /// Minimal SMTP server that accepts one message and returns its DATA section
+    fn mock_smtp_server() -> (u16, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut data = String::new();
+
+            writer.write_all(b"220 mock ESMTP\r\n").unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                let command = line.trim_end().to_ascii_uppercase();
+                if command.starts_with("EHLO") || command.starts_with("HELO") {
+                    writer.write_all(b"250 mock\r\n").unwrap();
+                } else if command == "DATA" {
+                    writer.write_all(b"354 go ahead\r\n").unwrap();
+                    let mut body = String::new();
+                    while !body.ends_with("\r\n.\r\n") {
+                        let mut chunk = String::new();
+                        reader.read_line(&mut chunk).unwrap();
+                        body.push_str(&chunk);
+                    }
+                    data = body;
+                    writer.write_all(b"250 queued\r\n").unwrap();
+                } else if command == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    writer.write_all(b"250 ok\r\n").unwrap();
+                }
+                line.clear();
+            }
+            data
+        });
+
+        (port, handle)
+    }
+
+    #[test]
+    fn test_smtp_notifier_against_mock_server() {
+        let (port, server) = mock_smtp_server();
+
+        let config = NotifierConfig::Smtp {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: None,
+            from: "hermes@example.com".to_string(),
+            to: vec![],
+            starttls: false,
+        };
+        build(&config, Some("alice@example.com"))
+            .notify(&sample(NotificationKind::Warning))
+            .unwrap();
+
+        let data = server.join().unwrap();
+        assert!(data.contains("To: alice@example.com"));
+        assert!(data.contains("Check-in required"));
+        assert!(data.contains("Check in within 2h"));
+    }
+
+    #[test]
+    fn test_webhook_notifier_posts_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            let mut line = String::new();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = len.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(body).unwrap()
+        });
+
+        let config = NotifierConfig::Webhook { url };
+        build(&config, None)
+            .notify(&sample(NotificationKind::Fired))
+            .unwrap();
+
+        let payload: serde_json::Value = serde_json::from_str(&server.join().unwrap()).unwrap();
+        assert_eq!(payload["kind"], "fired");
+        assert_eq!(payload["file_path"], "/vault/files/will.enc");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_notifier_passes_event_and_reports_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("event.txt");
+
+        let config = NotifierConfig::Command {
+            command: format!(
+                "echo \"$HERMES_DMS_EVENT $HERMES_DMS_FILE\" > {}",
+                out.display()
+            ),
+        };
+        build(&config, None)
+            .notify(&sample(NotificationKind::Warning))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap().trim(),
+            "warning /vault/files/will.enc"
+        );
+
+        let failing = NotifierConfig::Command {
+            command: "exit 3".to_string(),
+        };
+        assert!(notify_all(&[failing], None, &sample(NotificationKind::Fired)).is_err());
+    }
+}
@@ -84,5 +84,5 @@ pub fn create_compression_spinner() -> ProgressTracker {
 
 #[must_use]
 pub fn create_keygen_spinner() -> ProgressTracker {
-    ProgressTracker::new_spinner("🔑 Generating RSA key")
+    ProgressTracker::new_spinner("🔑 Generating keypair")
 }
@@ -0,0 +1,90 @@
+use crate::error::{HermesError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Serializes read-modify-write cycles of the journal file within this process
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+/// An interrupted or running transfer
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub direction: Direction,
+    pub remote_path: String,
+    /// Partial file being written: remote for uploads, local for downloads
+    pub temp_path: String,
+    pub total_size: u64,
+    /// Identifies the content; a partial file is only resumed if this matches
+    pub fingerprint: String,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TransferJournal {
+    pub entries: HashMap<String, JournalEntry>,
+}
+
+impl TransferJournal {
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let mut file = File::open(path)?;
+        let mut json = String::new();
+        file.read_to_string(&mut json)?;
+
+        serde_json::from_str(&json).map_err(HermesError::SerializationError)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(HermesError::SerializationError)?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Look up `key` in the journal at `path`
+    pub fn lookup(path: &Path, key: &str) -> Result<Option<JournalEntry>> {
+        let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(Self::load_from_file(path)?.entries.remove(key))
+    }
+
+    /// Record or replace `key` in the journal at `path`
+    pub fn record(path: &Path, key: &str, entry: JournalEntry) -> Result<()> {
+        let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut journal = Self::load_from_file(path)?;
+        journal.entries.insert(key.to_string(), entry);
+        journal.save_to_file(path)
+    }
+
+    /// Drop `key` from the journal at `path` once its transfer completed
+    pub fn complete(path: &Path, key: &str) -> Result<()> {
+        let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut journal = Self::load_from_file(path)?;
+        if journal.entries.remove(key).is_some() {
+            journal.save_to_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn get_journal_path() -> Result<PathBuf> {
+    crate::config::state_file("transfers.json")
+}
@@ -0,0 +1,228 @@
+use crate::config::HostKeyPolicy;
+use crate::error::{HermesError, Result};
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use dialoguer::Confirm;
+use ssh2::{CheckResult, HashType, KnownHostFileKind, KnownHostKeyFormat, Session};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+/// Hermes' own known-hosts file; TOFU decisions are recorded here
+pub fn get_known_hosts_path() -> Result<PathBuf> {
+    crate::config::state_file("known_hosts")
+}
+
+/// Check the server's host key after the handshake, before any credentials are sent
+pub fn verify_host_key(
+    session: &Session,
+    host: &str,
+    port: u16,
+    policy: HostKeyPolicy,
+) -> Result<()> {
+    let (key, key_type) = session.host_key().ok_or_else(|| {
+        HermesError::SftpConnectionFailed("Server did not present a host key".to_string())
+    })?;
+    let fingerprint = fingerprint(session);
+    let hermes_file = get_known_hosts_path()?;
+
+    let system_file = dirs::home_dir().map(|home| home.join(".ssh").join("known_hosts"));
+    let files: Vec<&Path> = system_file
+        .as_deref()
+        .into_iter()
+        .chain([hermes_file.as_path()])
+        .collect();
+
+    match lookup(session, &files, host, port, key)? {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => {
+            if policy == HostKeyPolicy::Strict {
+                return Err(HermesError::HostKeyUnknown {
+                    host: host_entry(host, port),
+                    fingerprint,
+                });
+            }
+
+            let question = format!(
+                "The authenticity of host '{}' can't be established.\n{:?} key fingerprint is {}.\nTrust this host?",
+                host_entry(host, port),
+                key_type,
+                fingerprint
+            );
+            if !confirm(&question, true)? {
+                return Err(HermesError::HostKeyUnknown {
+                    host: host_entry(host, port),
+                    fingerprint,
+                });
+            }
+
+            record_host_key(session, &hermes_file, host, port, key, key_type.into())
+        }
+        CheckResult::Mismatch => {
+            let mismatch = HermesError::HostKeyMismatch {
+                host: host_entry(host, port),
+                fingerprint: fingerprint.clone(),
+            };
+            if policy == HostKeyPolicy::Strict {
+                return Err(mismatch);
+            }
+
+            let question = format!(
+                "WARNING: the host key for '{}' has CHANGED. Someone may be intercepting this connection.\nNew {:?} key fingerprint is {}.\nReplace the stored key?",
+                host_entry(host, port),
+                key_type,
+                fingerprint
+            );
+            if !confirm(&question, false)? {
+                return Err(mismatch);
+            }
+
+            record_host_key(session, &hermes_file, host, port, key, key_type.into())
+        }
+        CheckResult::Failure => Err(HermesError::SftpConnectionFailed(
+            "Host key check failed".to_string(),
+        )),
+    }
+}
+
+/// SHA-256 fingerprint in the format printed by OpenSSH
+pub fn fingerprint(session: &Session) -> String {
+    session
+        .host_key_hash(HashType::Sha256)
+        .map(|hash| format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)))
+        .unwrap_or_else(|| "(unavailable)".to_string())
+}
+
+/// Look the key up in every known-hosts file that exists
+fn lookup(
+    session: &Session,
+    files: &[&Path],
+    host: &str,
+    port: u16,
+    key: &[u8],
+) -> Result<CheckResult> {
+    let mut known = session.known_hosts()?;
+
+    for file in files {
+        if file.exists() {
+            // An entry libssh2 cannot parse (e.g. an unsupported key type) stops
+            // reading that file; entries before it still count
+            let _ = known.read_file(file, KnownHostFileKind::OpenSSH);
+        }
+    }
+
+    Ok(known.check_port(host, port, key))
+}
+
+/// Store `key` for the host in `file`, replacing any key it had there
+fn record_host_key(
+    session: &Session,
+    file: &Path,
+    host: &str,
+    port: u16,
+    key: &[u8],
+    format: KnownHostKeyFormat,
+) -> Result<()> {
+    let entry = host_entry(host, port);
+    let mut known = session.known_hosts()?;
+
+    if file.exists() {
+        known.read_file(file, KnownHostFileKind::OpenSSH)?;
+    }
+
+    for stale in known.hosts()? {
+        if stale.name() == Some(entry.as_str()) {
+            known.remove(&stale)?;
+        }
+    }
+
+    known.add(&entry, key, "added by hermes", format)?;
+    known.write_file(file, KnownHostFileKind::OpenSSH)?;
+
+    Ok(())
+}
+
+/// Host name as written in known_hosts; non-standard ports use `[host]:port`
+fn host_entry(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{host}]:{port}")
+    }
+}
+
+fn confirm(question: &str, default: bool) -> Result<bool> {
+    // Unattended runs (daemons, scripts) never trust a key on their own
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    Ok(Confirm::new()
+        .with_prompt(question)
+        .default(default)
+        .interact()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_entry_format() {
+        assert_eq!(host_entry("vault.example.com", 22), "vault.example.com");
+        assert_eq!(
+            host_entry("vault.example.com", 2222),
+            "[vault.example.com]:2222"
+        );
+    }
+
+    #[test]
+    fn test_recorded_key_matches_and_replaces() {
+        let session = Session::new().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("known_hosts");
+        let files = [file.as_path()];
+
+        let old_key = b"\x00\x00\x00\x07ssh-rsa old host key";
+        let new_key = b"\x00\x00\x00\x07ssh-rsa new host key";
+
+        assert!(matches!(
+            lookup(&session, &files, "vault", 2222, old_key).unwrap(),
+            CheckResult::NotFound
+        ));
+
+        record_host_key(
+            &session,
+            &file,
+            "vault",
+            2222,
+            old_key,
+            KnownHostKeyFormat::SshRsa,
+        )
+        .unwrap();
+        assert!(matches!(
+            lookup(&session, &files, "vault", 2222, old_key).unwrap(),
+            CheckResult::Match
+        ));
+        assert!(matches!(
+            lookup(&session, &files, "vault", 2222, new_key).unwrap(),
+            CheckResult::Mismatch
+        ));
+
+        // Accepting a changed key replaces the old entry instead of adding a second one
+        record_host_key(
+            &session,
+            &file,
+            "vault",
+            2222,
+            new_key,
+            KnownHostKeyFormat::SshRsa,
+        )
+        .unwrap();
+        assert!(matches!(
+            lookup(&session, &files, "vault", 2222, new_key).unwrap(),
+            CheckResult::Match
+        ));
+        let contents = std::fs::read_to_string(&file).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        assert!(contents.starts_with("[vault]:2222 ssh-rsa "));
+    }
+}
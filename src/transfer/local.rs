@@ -0,0 +1,116 @@
+use super::{entry_name, RemoteEntry, Transport};
+use crate::error::{HermesError, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Transport backed by a local directory, e.g. a shared NFS mount
+#[derive(Debug, Default)]
+pub struct LocalDirTransport;
+
+impl LocalDirTransport {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn to_entry(path: &Path, metadata: &fs::Metadata) -> RemoteEntry {
+    let path_str = path.to_string_lossy().into_owned();
+    RemoteEntry {
+        name: entry_name(&path_str),
+        path: path_str,
+        size: metadata.len(),
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        is_dir: metadata.is_dir(),
+    }
+}
+
+impl Transport for LocalDirTransport {
+    fn upload(&self, data: &[u8], remote_path: &str) -> Result<()> {
+        fs::write(remote_path, data)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File creation failed: {e}")))
+    }
+
+    fn download(&self, remote_path: &str) -> Result<Vec<u8>> {
+        fs::read(remote_path)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File not found: {e}")))
+    }
+
+    fn list(&self, remote_dir: &str) -> Result<Vec<RemoteEntry>> {
+        let entries = fs::read_dir(remote_dir)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Readdir failed: {e}")))?;
+
+        let mut result = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            result.push(to_entry(&entry.path(), &metadata));
+        }
+
+        Ok(result)
+    }
+
+    fn delete(&self, remote_path: &str) -> Result<()> {
+        fs::remove_file(remote_path)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Delete failed: {e}")))
+    }
+
+    fn overwrite(&self, remote_path: &str, data: &[u8]) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(remote_path)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File not found: {e}")))?;
+
+        file.write_all(data)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    fn stat(&self, remote_path: &str) -> Result<RemoteEntry> {
+        let path = Path::new(remote_path);
+        let metadata = fs::metadata(path)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Stat failed: {e}")))?;
+        Ok(to_entry(path, &metadata))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        fs::rename(from, to)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Rename failed: {e}")))
+    }
+
+    fn create_file(&self, remote_path: &str) -> Result<Box<dyn Write>> {
+        let file = File::create(remote_path)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File creation failed: {e}")))?;
+        Ok(Box::new(file))
+    }
+
+    fn open_file(&self, remote_path: &str) -> Result<(Box<dyn Read>, u64)> {
+        let file = File::open(remote_path)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File not found: {e}")))?;
+        let size = file.metadata()?.len();
+        Ok((Box::new(file), size))
+    }
+
+    fn write_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn Write>> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(remote_path)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File creation failed: {e}")))?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+
+    fn read_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn Read>> {
+        let mut file = File::open(remote_path)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File not found: {e}")))?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+}
@@ -1,3 +1,113 @@
+pub mod auth;
+pub mod journal;
+pub mod known_hosts;
+pub mod local;
+pub mod pool;
+pub mod resume;
 pub mod sftp;
 
+pub use local::*;
+pub use pool::*;
 pub use sftp::*;
+
+use crate::config::{Settings, TransportKind};
+use crate::crypto::EncryptedPackage;
+use crate::error::Result;
+use rand::RngCore;
+use std::io::{BufReader, Read, Write};
+
+/// Bytes overwritten when a file has no parseable package header
+const SHRED_FALLBACK_BYTES: u64 = 64 * 1024;
+
+/// Metadata for a file or directory in the vault
+#[derive(Debug, Clone)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<u64>,
+    pub is_dir: bool,
+}
+
+/// Storage backend for the encrypted vault
+pub trait Transport: Send {
+    fn upload(&self, data: &[u8], remote_path: &str) -> Result<()>;
+
+    fn download(&self, remote_path: &str) -> Result<Vec<u8>>;
+
+    /// List the entries of a directory
+    fn list(&self, remote_dir: &str) -> Result<Vec<RemoteEntry>>;
+
+    fn delete(&self, remote_path: &str) -> Result<()>;
+
+    /// Overwrite the start of an existing file in place, without truncating it
+    fn overwrite(&self, remote_path: &str, data: &[u8]) -> Result<()>;
+
+    fn stat(&self, remote_path: &str) -> Result<RemoteEntry>;
+
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
+
+    /// Create a file for streaming writes
+    fn create_file(&self, remote_path: &str) -> Result<Box<dyn Write>>;
+
+    /// Open a file for streaming reads, returning it with its size
+    fn open_file(&self, remote_path: &str) -> Result<(Box<dyn Read>, u64)>;
+
+    /// Open a file for writing at `offset`, creating it if missing and keeping existing bytes
+    fn write_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn Write>>;
+
+    /// Open a file for streaming reads starting at `offset`
+    fn read_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn Read>>;
+}
+
+/// Open the transport selected in the configuration
+pub fn connect(config: &Settings) -> Result<Box<dyn Transport>> {
+    match config.transport {
+        TransportKind::Sftp => Ok(Box::new(SftpClient::connect(config)?)),
+        TransportKind::Local => Ok(Box::new(LocalDirTransport::new())),
+    }
+}
+
+/// Read just the header of a binary package without downloading its contents
+pub fn read_package_header(transport: &dyn Transport, path: &str) -> Result<EncryptedPackage> {
+    let (file, _) = transport.open_file(path)?;
+    EncryptedPackage::read_header(&mut BufReader::new(file))
+}
+
+/// Destroy a package's salt and key slots with random bytes, then delete it.
+/// Even if the server keeps the unlinked blocks, the ciphertext stays unrecoverable.
+pub fn shred(transport: &dyn Transport, path: &str) -> Result<()> {
+    let len = match read_package_header(transport, path) {
+        Ok(header) => header.header_bytes().len() as u64,
+        Err(_) => transport.stat(path)?.size.min(SHRED_FALLBACK_BYTES),
+    };
+
+    let mut noise = vec![0u8; len as usize];
+    rand::thread_rng().fill_bytes(&mut noise);
+    transport.overwrite(path, &noise)?;
+    transport.delete(path)
+}
+
+/// Move `from` over the existing file `to`. Servers without POSIX rename refuse to
+/// replace a file; `to` is then moved aside first and put back if the move still fails,
+/// so a failure never loses it. Any other failure is returned with `to` untouched.
+pub fn rename_over(transport: &dyn Transport, from: &str, to: &str) -> Result<()> {
+    let Err(e) = transport.rename(from, to) else {
+        return Ok(());
+    };
+    if transport.stat(from).is_err() || transport.stat(to).is_err() {
+        return Err(e);
+    }
+
+    let aside = format!("{to}.old");
+    transport.rename(to, &aside)?;
+    if let Err(e) = transport.rename(from, to) {
+        let _ = transport.rename(&aside, to);
+        return Err(e);
+    }
+    transport.delete(&aside)
+}
+
+pub fn entry_name(path: &str) -> String {
+    path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
+}
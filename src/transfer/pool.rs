@@ -0,0 +1,131 @@
+use super::{connect, Transport};
+use crate::config::Settings;
+use crate::error::Result;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Independent transport connections, one per worker thread
+pub struct TransportPool {
+    transports: Vec<Box<dyn Transport>>,
+}
+
+impl TransportPool {
+    /// Open `jobs` connections one after another, so trust and password prompts don't overlap
+    pub fn connect(config: &Settings, jobs: usize) -> Result<Self> {
+        let transports = (0..jobs.max(1))
+            .map(|_| connect(config))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { transports })
+    }
+
+    pub fn from_transports(transports: Vec<Box<dyn Transport>>) -> Self {
+        Self { transports }
+    }
+
+    pub fn len(&self) -> usize {
+        self.transports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transports.is_empty()
+    }
+
+    /// Any connection, for work that doesn't need the whole pool
+    pub fn first(&self) -> &dyn Transport {
+        self.transports[0].as_ref()
+    }
+
+    /// Run `work` on every item, spread across the connections, and return the
+    /// results in item order. Stops handing out items after the first error.
+    pub fn map<T, R, F>(&mut self, items: &[T], work: F) -> Result<Vec<R>>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&dyn Transport, &T) -> Result<R> + Sync,
+    {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results: Mutex<Vec<(usize, Result<R>)>> = Mutex::new(Vec::with_capacity(items.len()));
+
+        thread::scope(|scope| {
+            for transport in &mut self.transports {
+                let (next, failed, results, work) = (&next, &failed, &results, &work);
+
+                scope.spawn(move || {
+                    let transport = transport.as_mut();
+                    while !failed.load(Ordering::SeqCst) {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+
+                        let result = work(&*transport, item);
+                        if result.is_err() {
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        results
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .push((index, result));
+                    }
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+        results.sort_by_key(|(index, _)| *index);
+
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::HermesError;
+    use crate::transfer::LocalDirTransport;
+
+    fn pool(jobs: usize) -> TransportPool {
+        TransportPool::from_transports(
+            (0..jobs)
+                .map(|_| Box::new(LocalDirTransport::new()) as Box<dyn Transport>)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_map_keeps_item_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let items: Vec<usize> = (0..20).collect();
+
+        let paths = pool(4)
+            .map(&items, |transport, i| {
+                let path = dir.path().join(format!("{i}.bin"));
+                let path = path.to_str().unwrap().to_string();
+                transport.upload(&vec![*i as u8; *i], &path)?;
+                Ok(path)
+            })
+            .unwrap();
+
+        for (i, path) in paths.iter().enumerate() {
+            assert!(path.ends_with(&format!("{i}.bin")));
+            assert_eq!(std::fs::read(path).unwrap().len(), i);
+        }
+    }
+
+    #[test]
+    fn test_map_stops_on_error() {
+        let items: Vec<usize> = (0..100).collect();
+
+        let result = pool(3).map(&items, |_, i| {
+            if *i == 5 {
+                return Err(HermesError::SftpOperationFailed("boom".to_string()));
+            }
+            Ok(*i)
+        });
+
+        assert!(matches!(result, Err(HermesError::SftpOperationFailed(_))));
+    }
+}
@@ -0,0 +1,559 @@
+use super::journal::{get_journal_path, Direction, JournalEntry, TransferJournal};
+use super::Transport;
+use crate::error::{HermesError, Result};
+use crate::ui;
+use chrono::Utc;
+use indicatif::ProgressBar;
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Suffix of partial files; they are renamed into place once complete
+pub const PART_SUFFIX: &str = ".part";
+
+/// Bytes written or read between progress updates
+const PIECE_SIZE: usize = 1024 * 1024;
+
+/// Attempts per transfer before giving up; each attempt continues where the last stopped
+const MAX_ATTEMPTS: usize = 3;
+
+/// Upload to `{remote_path}.part`, resuming an earlier attempt at the same content,
+/// then rename it into place
+pub fn upload(transport: &dyn Transport, data: &[u8], remote_path: &str) -> Result<()> {
+    let pb = ui::create_progress_bar(data.len() as u64);
+    upload_with_journal(transport, data, remote_path, &get_journal_path()?, &pb)
+}
+
+/// Like [`upload`], with an explicit journal and a progress bar, which may be hidden
+pub fn upload_with_journal(
+    transport: &dyn Transport,
+    data: &[u8],
+    remote_path: &str,
+    journal: &Path,
+    pb: &ProgressBar,
+) -> Result<()> {
+    let temp_path = format!("{remote_path}{PART_SUFFIX}");
+    let total = data.len() as u64;
+    let fingerprint = hex::encode(Sha256::digest(data));
+    let key = format!("upload:{remote_path}");
+
+    let remote_offset = || {
+        transport
+            .stat(&temp_path)
+            .map(|entry| entry.size.min(total))
+            .unwrap_or(0)
+    };
+
+    let mut offset = match TransferJournal::lookup(journal, &key)? {
+        Some(entry) if entry.fingerprint == fingerprint => remote_offset(),
+        _ => 0,
+    };
+
+    TransferJournal::record(
+        journal,
+        &key,
+        JournalEntry {
+            direction: Direction::Upload,
+            remote_path: remote_path.to_string(),
+            temp_path: temp_path.clone(),
+            total_size: total,
+            fingerprint,
+            started_at: Utc::now(),
+        },
+    )?;
+
+    pb.set_length(total);
+    pb.set_message(if offset > 0 {
+        "Resuming upload..."
+    } else {
+        "Uploading..."
+    });
+
+    let mut attempt = 1;
+    loop {
+        match write_remote(transport, &temp_path, data, offset, pb) {
+            Ok(()) => break,
+            Err(e) if attempt >= MAX_ATTEMPTS => {
+                pb.abandon();
+                return Err(e);
+            }
+            Err(_) => {
+                attempt += 1;
+                offset = remote_offset();
+            }
+        }
+    }
+
+    pb.finish_and_clear();
+
+    if transport.stat(&temp_path)?.size != total {
+        return Err(HermesError::SftpOperationFailed(format!(
+            "Upload incomplete: {temp_path}"
+        )));
+    }
+
+    super::rename_over(transport, &temp_path, remote_path)?;
+
+    TransferJournal::complete(journal, &key)
+}
+
+fn write_remote(
+    transport: &dyn Transport,
+    temp_path: &str,
+    data: &[u8],
+    offset: u64,
+    pb: &ProgressBar,
+) -> Result<()> {
+    let mut file = if offset == 0 {
+        transport.create_file(temp_path)?
+    } else {
+        transport.write_at(temp_path, offset)?
+    };
+
+    pb.set_position(offset);
+    for piece in data[offset as usize..].chunks(PIECE_SIZE) {
+        file.write_all(piece)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Upload failed: {e}")))?;
+        pb.inc(piece.len() as u64);
+    }
+    file.flush()
+        .map_err(|e| HermesError::SftpOperationFailed(format!("Upload failed: {e}")))?;
+
+    Ok(())
+}
+
+/// Download into memory, continuing from the received offset after a read error
+pub fn download(transport: &dyn Transport, remote_path: &str) -> Result<Vec<u8>> {
+    let (mut file, size) = transport.open_file(remote_path)?;
+
+    let pb = ui::create_progress_bar(size);
+    pb.set_message("Downloading...");
+
+    let mut buffer = Vec::with_capacity(size as usize);
+    let mut attempt = 1;
+    loop {
+        match read_to_end(&mut file, &mut buffer, &pb) {
+            Ok(()) => break,
+            Err(e) if attempt >= MAX_ATTEMPTS => {
+                pb.abandon();
+                return Err(e);
+            }
+            Err(_) => {
+                attempt += 1;
+                file = transport.read_at(remote_path, buffer.len() as u64)?;
+            }
+        }
+    }
+
+    pb.finish_and_clear();
+
+    Ok(buffer)
+}
+
+fn read_to_end(file: &mut dyn Read, buffer: &mut Vec<u8>, pb: &ProgressBar) -> Result<()> {
+    let mut piece = vec![0u8; PIECE_SIZE];
+    loop {
+        let n = file
+            .read(&mut piece)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Download failed: {e}")))?;
+        if n == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&piece[..n]);
+        pb.set_position(buffer.len() as u64);
+    }
+}
+
+/// Download into `{local_path}.part`, resuming an earlier attempt if the remote
+/// file is unchanged, then rename it into place
+pub fn download_to_file(
+    transport: &dyn Transport,
+    remote_path: &str,
+    local_path: &Path,
+) -> Result<()> {
+    let pb = ui::create_progress_bar(0);
+    download_to_file_with_journal(
+        transport,
+        remote_path,
+        local_path,
+        &get_journal_path()?,
+        &pb,
+    )
+}
+
+/// Like [`download_to_file`], with an explicit journal and a progress bar, which may be hidden
+pub fn download_to_file_with_journal(
+    transport: &dyn Transport,
+    remote_path: &str,
+    local_path: &Path,
+    journal: &Path,
+    pb: &ProgressBar,
+) -> Result<()> {
+    let remote = transport.stat(remote_path)?;
+    let total = remote.size;
+    let fingerprint = format!("{}:{}", remote.size, remote.modified.unwrap_or(0));
+    let part_path = part_path(local_path);
+    let key = format!("download:{}", local_path.display());
+
+    let local_offset = || {
+        fs::metadata(&part_path)
+            .map(|m| m.len().min(total))
+            .unwrap_or(0)
+    };
+
+    let mut offset = match TransferJournal::lookup(journal, &key)? {
+        Some(entry) if entry.fingerprint == fingerprint && entry.remote_path == remote_path => {
+            local_offset()
+        }
+        _ => 0,
+    };
+
+    TransferJournal::record(
+        journal,
+        &key,
+        JournalEntry {
+            direction: Direction::Download,
+            remote_path: remote_path.to_string(),
+            temp_path: part_path.display().to_string(),
+            total_size: total,
+            fingerprint,
+            started_at: Utc::now(),
+        },
+    )?;
+
+    pb.set_length(total);
+    pb.set_message(if offset > 0 {
+        "Resuming download..."
+    } else {
+        "Downloading..."
+    });
+
+    let mut attempt = 1;
+    loop {
+        match read_remote(transport, remote_path, &part_path, offset, pb) {
+            Ok(()) => break,
+            Err(e) if attempt >= MAX_ATTEMPTS => {
+                pb.abandon();
+                return Err(e);
+            }
+            Err(_) => {
+                attempt += 1;
+                offset = local_offset();
+            }
+        }
+    }
+
+    pb.finish_and_clear();
+
+    if fs::metadata(&part_path)?.len() != total {
+        return Err(HermesError::SftpOperationFailed(format!(
+            "Download incomplete: {}",
+            part_path.display()
+        )));
+    }
+
+    fs::rename(&part_path, local_path)?;
+    TransferJournal::complete(journal, &key)
+}
+
+fn read_remote(
+    transport: &dyn Transport,
+    remote_path: &str,
+    part_path: &Path,
+    offset: u64,
+    pb: &ProgressBar,
+) -> Result<()> {
+    let mut part = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(offset == 0)
+        .open(part_path)?;
+    // Drop anything past the resume point, e.g. a torn final write
+    part.set_len(offset)?;
+    part.seek(SeekFrom::Start(offset))?;
+
+    let mut remote = transport.read_at(remote_path, offset)?;
+
+    pb.set_position(offset);
+    let mut piece = vec![0u8; PIECE_SIZE];
+    loop {
+        let n = remote
+            .read(&mut piece)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Download failed: {e}")))?;
+        if n == 0 {
+            break;
+        }
+        part.write_all(&piece[..n])?;
+        pb.inc(n as u64);
+    }
+    part.sync_all()?;
+
+    Ok(())
+}
+
+fn part_path(local_path: &Path) -> PathBuf {
+    let mut name = local_path.as_os_str().to_owned();
+    name.push(PART_SUFFIX);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::{LocalDirTransport, RemoteEntry};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Local transport whose writes and reads fail after a byte budget runs out
+    struct FlakyTransport {
+        inner: LocalDirTransport,
+        budget: Arc<AtomicUsize>,
+        renames: Renames,
+    }
+
+    #[derive(Clone, Copy)]
+    enum Renames {
+        Posix,
+        /// Like servers without POSIX rename: an existing destination is not replaced
+        NoReplace,
+        Fail,
+    }
+
+    struct Limited<T> {
+        inner: T,
+        budget: Arc<AtomicUsize>,
+    }
+
+    impl<T: Write> Write for Limited<T> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let allowed = buf.len().min(self.budget.load(Ordering::SeqCst));
+            if allowed == 0 {
+                return Err(std::io::Error::other("connection reset"));
+            }
+            self.budget.fetch_sub(allowed, Ordering::SeqCst);
+            self.inner.write(&buf[..allowed])
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    impl<T: Read> Read for Limited<T> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let allowed = buf.len().min(self.budget.load(Ordering::SeqCst));
+            if allowed == 0 {
+                // Reaching the end of the file is still possible without budget
+                let mut probe = [0u8; 1];
+                return match self.inner.read(&mut probe)? {
+                    0 => Ok(0),
+                    _ => Err(std::io::Error::other("connection reset")),
+                };
+            }
+            let n = self.inner.read(&mut buf[..allowed])?;
+            self.budget.fetch_sub(n, Ordering::SeqCst);
+            Ok(n)
+        }
+    }
+
+    impl FlakyTransport {
+        fn new(budget: usize) -> Self {
+            Self {
+                inner: LocalDirTransport::new(),
+                budget: Arc::new(AtomicUsize::new(budget)),
+                renames: Renames::Posix,
+            }
+        }
+
+        fn with_renames(mut self, renames: Renames) -> Self {
+            self.renames = renames;
+            self
+        }
+
+        fn limit<T>(&self, inner: T) -> Limited<T> {
+            Limited {
+                inner,
+                budget: Arc::clone(&self.budget),
+            }
+        }
+    }
+
+    impl Transport for FlakyTransport {
+        fn upload(&self, data: &[u8], remote_path: &str) -> Result<()> {
+            self.inner.upload(data, remote_path)
+        }
+        fn download(&self, remote_path: &str) -> Result<Vec<u8>> {
+            self.inner.download(remote_path)
+        }
+        fn list(&self, remote_dir: &str) -> Result<Vec<RemoteEntry>> {
+            self.inner.list(remote_dir)
+        }
+        fn delete(&self, remote_path: &str) -> Result<()> {
+            self.inner.delete(remote_path)
+        }
+        fn overwrite(&self, remote_path: &str, data: &[u8]) -> Result<()> {
+            self.inner.overwrite(remote_path, data)
+        }
+        fn stat(&self, remote_path: &str) -> Result<RemoteEntry> {
+            self.inner.stat(remote_path)
+        }
+        fn rename(&self, from: &str, to: &str) -> Result<()> {
+            match self.renames {
+                Renames::NoReplace if Path::new(to).exists() => Err(
+                    HermesError::SftpOperationFailed("Rename failed: file exists".to_string()),
+                ),
+                Renames::Fail => Err(HermesError::SftpOperationFailed(
+                    "Rename failed: permission denied".to_string(),
+                )),
+                _ => self.inner.rename(from, to),
+            }
+        }
+        fn create_file(&self, remote_path: &str) -> Result<Box<dyn Write>> {
+            Ok(Box::new(self.limit(self.inner.create_file(remote_path)?)))
+        }
+        fn open_file(&self, remote_path: &str) -> Result<(Box<dyn Read>, u64)> {
+            let (file, size) = self.inner.open_file(remote_path)?;
+            Ok((Box::new(self.limit(file)), size))
+        }
+        fn write_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn Write>> {
+            Ok(Box::new(
+                self.limit(self.inner.write_at(remote_path, offset)?),
+            ))
+        }
+        fn read_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn Read>> {
+            Ok(Box::new(
+                self.limit(self.inner.read_at(remote_path, offset)?),
+            ))
+        }
+    }
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 256) as u8).collect()
+    }
+
+    #[test]
+    fn test_upload_resumes_in_a_later_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("transfers.json");
+        let remote = dir.path().join("chunk.enc");
+        let remote = remote.to_str().unwrap();
+        let data = sample(3 * PIECE_SIZE + 100);
+
+        // Every attempt of the first run dies after one piece
+        let flaky = FlakyTransport::new(PIECE_SIZE);
+        assert!(
+            upload_with_journal(&flaky, &data, remote, &journal, &ProgressBar::hidden()).is_err()
+        );
+        let partial = fs::metadata(format!("{remote}{PART_SUFFIX}"))
+            .unwrap()
+            .len();
+        assert_eq!(partial, PIECE_SIZE as u64);
+        assert!(!Path::new(remote).exists());
+
+        // The next run only has budget for the remainder
+        let flaky = FlakyTransport::new(data.len() - PIECE_SIZE);
+        upload_with_journal(&flaky, &data, remote, &journal, &ProgressBar::hidden()).unwrap();
+
+        assert_eq!(fs::read(remote).unwrap(), data);
+        assert!(!Path::new(&format!("{remote}{PART_SUFFIX}")).exists());
+        assert!(TransferJournal::load_from_file(&journal)
+            .unwrap()
+            .entries
+            .is_empty());
+    }
+
+    #[test]
+    fn test_upload_replaces_existing_file_only_when_safe() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("transfers.json");
+        let remote = dir.path().join("package.enc");
+        let remote = remote.to_str().unwrap();
+        fs::write(remote, b"original").unwrap();
+
+        // A failed move leaves the original in place
+        let failing = FlakyTransport::new(usize::MAX).with_renames(Renames::Fail);
+        assert!(
+            upload_with_journal(&failing, b"new", remote, &journal, &ProgressBar::hidden())
+                .is_err()
+        );
+        assert_eq!(fs::read(remote).unwrap(), b"original");
+
+        // Servers that won't rename over a file still get it replaced
+        let no_replace = FlakyTransport::new(usize::MAX).with_renames(Renames::NoReplace);
+        upload_with_journal(
+            &no_replace,
+            b"new",
+            remote,
+            &journal,
+            &ProgressBar::hidden(),
+        )
+        .unwrap();
+        assert_eq!(fs::read(remote).unwrap(), b"new");
+        assert!(!Path::new(&format!("{remote}.old")).exists());
+    }
+
+    #[test]
+    fn test_upload_restarts_when_content_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("transfers.json");
+        let remote = dir.path().join("chunk.enc");
+        let remote = remote.to_str().unwrap();
+
+        let flaky = FlakyTransport::new(PIECE_SIZE);
+        assert!(upload_with_journal(
+            &flaky,
+            &sample(2 * PIECE_SIZE),
+            remote,
+            &journal,
+            &ProgressBar::hidden()
+        )
+        .is_err());
+
+        let other = vec![0xAB; 2 * PIECE_SIZE];
+        upload_with_journal(
+            &LocalDirTransport::new(),
+            &other,
+            remote,
+            &journal,
+            &ProgressBar::hidden(),
+        )
+        .unwrap();
+        assert_eq!(fs::read(remote).unwrap(), other);
+    }
+
+    #[test]
+    fn test_download_retries_and_resumes_part_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("transfers.json");
+        let remote = dir.path().join("remote.enc");
+        let local = dir.path().join("local.enc");
+        let data = sample(2 * PIECE_SIZE + 5);
+        fs::write(&remote, &data).unwrap();
+        let remote = remote.to_str().unwrap();
+
+        let flaky = FlakyTransport::new(PIECE_SIZE / 2);
+        assert!(download_to_file_with_journal(
+            &flaky,
+            remote,
+            &local,
+            &journal,
+            &ProgressBar::hidden()
+        )
+        .is_err());
+        assert_eq!(
+            fs::metadata(part_path(&local)).unwrap().len(),
+            (PIECE_SIZE / 2) as u64
+        );
+
+        let flaky = FlakyTransport::new(data.len() - PIECE_SIZE / 2);
+        download_to_file_with_journal(&flaky, remote, &local, &journal, &ProgressBar::hidden())
+            .unwrap();
+        assert_eq!(fs::read(&local).unwrap(), data);
+        assert!(!part_path(&local).exists());
+        assert!(TransferJournal::load_from_file(&journal)
+            .unwrap()
+            .entries
+            .is_empty());
+    }
+}
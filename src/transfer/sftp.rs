@@ -1,8 +1,9 @@
+use super::{auth, entry_name, known_hosts, resume, RemoteEntry, Transport};
 use crate::config::Settings;
 use crate::error::{HermesError, Result};
 use crate::ui;
-use ssh2::Session;
-use std::io::{Read, Write};
+use ssh2::{FileStat, OpenFlags, OpenType, Session, Sftp};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::net::TcpStream;
 use std::path::Path;
 
@@ -27,54 +28,51 @@ impl SftpClient {
             .handshake()
             .map_err(|e| HermesError::SftpConnectionFailed(format!("Handshake failed: {e}")))?;
 
-        if let Some(key_file) = &config.sftp.key_file {
-            session
-                .userauth_pubkey_file(&config.sftp.username, None, Path::new(key_file), None)
-                .map_err(|e| {
-                    HermesError::SftpConnectionFailed(format!("Key authentication failed: {e}"))
-                })?;
-        } else {
-            return Err(HermesError::SftpConnectionFailed(
-                "No key file specified".to_string(),
-            ));
-        }
+        // Hide the spinner while a trust prompt may be shown
+        spinner.suspend(|| {
+            known_hosts::verify_host_key(
+                &session,
+                &config.sftp.host,
+                config.sftp.port,
+                config.sftp.host_key_policy,
+            )
+        })?;
+
+        spinner.suspend(|| auth::authenticate(&session, &config.sftp))?;
 
         spinner.finish_and_clear();
 
         Ok(Self { session })
     }
 
-    pub fn upload(&self, data: &[u8], remote_path: &str) -> Result<()> {
-        let sftp = self
-            .session
+    fn sftp(&self) -> Result<Sftp> {
+        self.session
             .sftp()
-            .map_err(|e| HermesError::SftpOperationFailed(format!("SFTP init failed: {e}")))?;
+            .map_err(|e| HermesError::SftpOperationFailed(format!("SFTP init failed: {e}")))
+    }
+}
 
-        let pb = ui::create_progress_bar(data.len() as u64);
-        pb.set_message("Uploading...");
+impl Transport for SftpClient {
+    fn upload(&self, data: &[u8], remote_path: &str) -> Result<()> {
+        resume::upload(self, data, remote_path)
+    }
 
-        let mut remote_file = sftp
-            .create(Path::new(remote_path))
-            .map_err(|e| HermesError::SftpOperationFailed(format!("File creation failed: {e}")))?;
+    fn download(&self, remote_path: &str) -> Result<Vec<u8>> {
+        resume::download(self, remote_path)
+    }
 
-        let chunk_size = 8192;
-        for (i, chunk) in data.chunks(chunk_size).enumerate() {
-            remote_file
-                .write_all(chunk)
-                .map_err(|e| HermesError::SftpOperationFailed(format!("Upload failed: {e}")))?;
-            pb.set_position(((i + 1) * chunk_size).min(data.len()) as u64);
-        }
+    fn create_file(&self, remote_path: &str) -> Result<Box<dyn Write>> {
+        let sftp = self.sftp()?;
 
-        pb.finish_and_clear();
+        let remote_file = sftp
+            .create(Path::new(remote_path))
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File creation failed: {e}")))?;
 
-        Ok(())
+        Ok(Box::new(remote_file))
     }
 
-    pub fn download(&self, remote_path: &str) -> Result<Vec<u8>> {
-        let sftp = self
-            .session
-            .sftp()
-            .map_err(|e| HermesError::SftpOperationFailed(format!("SFTP init failed: {e}")))?;
+    fn open_file(&self, remote_path: &str) -> Result<(Box<dyn Read>, u64)> {
+        let sftp = self.sftp()?;
 
         let mut remote_file = sftp
             .open(Path::new(remote_path))
@@ -84,29 +82,108 @@ impl SftpClient {
             .stat()
             .map_err(|e| HermesError::SftpOperationFailed(format!("Stat failed: {e}")))?;
 
-        let pb = ui::create_progress_bar(stat.size.unwrap_or(0));
-        pb.set_message("Downloading...");
-
-        let mut buffer = Vec::new();
-        let mut temp_buf = [0u8; 8192];
-
-        loop {
-            match remote_file.read(&mut temp_buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    buffer.extend_from_slice(&temp_buf[..n]);
-                    pb.set_position(buffer.len() as u64);
-                }
-                Err(e) => {
-                    return Err(HermesError::SftpOperationFailed(format!(
-                        "Download failed: {e}"
-                    )))
-                }
-            }
-        }
-
-        pb.finish_and_clear();
-
-        Ok(buffer)
+        let size = stat.size.unwrap_or(0);
+        Ok((Box::new(remote_file), size))
+    }
+
+    fn write_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn Write>> {
+        let sftp = self.sftp()?;
+
+        let mut remote_file = sftp
+            .open_mode(
+                Path::new(remote_path),
+                OpenFlags::WRITE | OpenFlags::CREATE,
+                0o644,
+                OpenType::File,
+            )
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File creation failed: {e}")))?;
+
+        remote_file
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Seek failed: {e}")))?;
+
+        Ok(Box::new(remote_file))
+    }
+
+    fn read_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn Read>> {
+        let sftp = self.sftp()?;
+
+        let mut remote_file = sftp
+            .open(Path::new(remote_path))
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File not found: {e}")))?;
+
+        remote_file
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Seek failed: {e}")))?;
+
+        Ok(Box::new(remote_file))
+    }
+
+    fn list(&self, remote_dir: &str) -> Result<Vec<RemoteEntry>> {
+        let sftp = self.sftp()?;
+
+        let entries = sftp
+            .readdir(Path::new(remote_dir))
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Readdir failed: {e}")))?;
+
+        Ok(entries
+            .iter()
+            .map(|(path, stat)| to_entry(&path.to_string_lossy(), stat))
+            .collect())
+    }
+
+    fn delete(&self, remote_path: &str) -> Result<()> {
+        let sftp = self.sftp()?;
+
+        sftp.unlink(Path::new(remote_path))
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Delete failed: {e}")))
+    }
+
+    fn overwrite(&self, remote_path: &str, data: &[u8]) -> Result<()> {
+        let sftp = self.sftp()?;
+
+        let mut remote_file = sftp
+            .open_mode(
+                Path::new(remote_path),
+                OpenFlags::WRITE,
+                0o600,
+                OpenType::File,
+            )
+            .map_err(|e| HermesError::SftpOperationFailed(format!("File not found: {e}")))?;
+
+        remote_file
+            .write_all(data)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Overwrite failed: {e}")))?;
+
+        // fsync is an OpenSSH extension; not every server supports it
+        let _ = remote_file.fsync();
+        Ok(())
+    }
+
+    fn stat(&self, remote_path: &str) -> Result<RemoteEntry> {
+        let sftp = self.sftp()?;
+
+        let stat = sftp
+            .stat(Path::new(remote_path))
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Stat failed: {e}")))?;
+
+        Ok(to_entry(remote_path, &stat))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let sftp = self.sftp()?;
+
+        sftp.rename(Path::new(from), Path::new(to), None)
+            .map_err(|e| HermesError::SftpOperationFailed(format!("Rename failed: {e}")))
+    }
+}
+
+fn to_entry(path: &str, stat: &FileStat) -> RemoteEntry {
+    RemoteEntry {
+        name: entry_name(path),
+        path: path.to_string(),
+        size: stat.size.unwrap_or(0),
+        modified: stat.mtime,
+        is_dir: stat.is_dir(),
     }
 }
@@ -6,7 +6,7 @@ const INNER_WIDTH: usize = BOX_WIDTH - 2; // Subtract 2 for border chars
 pub fn print_banner() {
     let version = env!("CARGO_PKG_VERSION");
     let version_line = format!("SECURE TRANSFER PROTOCOL v{version} [ENCRYPTED]");
-    let features_line = "PQC-HYBRID • AES-256-GCM • DILITHIUM • KYBER";
+    let features_line = "PQC-HYBRID • AES-256-GCM • ML-DSA • ML-KEM";
 
     println!("{}", "╔═══════════════════════════════════════════════════════════╗".cyan());
     println!("{}", "║                                                           ║".cyan());
@@ -50,6 +50,8 @@ pub struct KeyInfo {
 #[derive(Deserialize)]
 pub struct GenerateKeyRequest {
     name: String,
+    #[serde(default)]
+    key_type: crypto::KeyType,
     pqc: bool,
     sign: bool,
 }
@@ -157,6 +159,7 @@ pub struct ConfigInfo {
     keys_dir: String,
     recipients_dir: String,
     config_file: String,
+    profile: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -168,9 +171,9 @@ pub struct ArchivedKeyInfo {
 // API Handlers
 
 pub async fn status() -> impl IntoResponse {
-    let home_dir = match dirs::home_dir() {
-        Some(dir) => dir.join(".hermes"),
-        None => {
+    let home_dir = match crate::config::hermes_home() {
+        Ok(dir) => dir,
+        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<StatusInfo>::error(
@@ -180,7 +183,9 @@ pub async fn status() -> impl IntoResponse {
         }
     };
 
-    let initialized = home_dir.join("config.toml").exists();
+    let initialized = crate::config::ConfigFile::path()
+        .map(|path| path.exists())
+        .unwrap_or(false);
 
     let keys_count = if home_dir.join("keys").exists() {
         fs::read_dir(home_dir.join("keys"))
@@ -229,9 +234,9 @@ pub async fn status() -> impl IntoResponse {
 }
 
 pub async fn list_keys() -> impl IntoResponse {
-    let keys_dir = match dirs::home_dir() {
-        Some(dir) => dir.join(".hermes").join("keys"),
-        None => {
+    let keys_dir = match crate::config::hermes_home() {
+        Ok(dir) => dir.join("keys"),
+        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<Vec<KeyInfo>>::error(
@@ -264,22 +269,29 @@ pub async fn list_keys() -> impl IntoResponse {
         if path.extension().map(|e| e == "pub").unwrap_or(false) {
             let filename = path.file_stem().unwrap().to_string_lossy().to_string();
 
-            // Skip Kyber and Dilithium keys (they're suffixes)
-            if filename.ends_with("_kyber") || filename.ends_with("_dilithium") {
+            // Skip Kyber, Dilithium and Ed25519 keys (they're suffixes)
+            if filename.ends_with("_kyber")
+                || filename.ends_with("_dilithium")
+                || filename.ends_with("_ed25519")
+            {
                 continue;
             }
 
             let has_pqc = keys_dir.join(format!("{}_kyber.pub", filename)).exists();
             let has_signing = keys_dir.join(format!("{}_dilithium.pub", filename)).exists();
 
-            let fingerprint = match crypto::load_public_key(path.to_str().unwrap()) {
-                Ok(key) => crypto::get_key_fingerprint(&key).unwrap_or_default(),
-                Err(_) => "unknown".to_string(),
-            };
+            let (key_type, fingerprint) =
+                match crypto::IdentityPublicKey::load(path.to_str().unwrap()) {
+                    Ok(key) => (
+                        key.key_type().name().to_string(),
+                        key.fingerprint().unwrap_or_default(),
+                    ),
+                    Err(_) => ("unknown".to_string(), "unknown".to_string()),
+                };
 
             keys.push(KeyInfo {
                 name: filename,
-                key_type: "RSA-4096".to_string(),
+                key_type,
                 fingerprint,
                 has_pqc,
                 has_signing,
@@ -291,7 +303,16 @@ pub async fn list_keys() -> impl IntoResponse {
 }
 
 pub async fn generate_key(Json(req): Json<GenerateKeyRequest>) -> impl IntoResponse {
-    match crate::commands::keygen::execute(&req.name, None, req.pqc, req.sign) {
+    match crate::commands::keygen::execute(
+        &req.name,
+        None,
+        req.key_type,
+        req.pqc,
+        req.sign,
+        false,
+        Default::default(),
+        Default::default(),
+    ) {
         Ok(_) => (
             StatusCode::OK,
             Json(ApiResponse::success("Key generated successfully")),
@@ -304,7 +325,15 @@ pub async fn generate_key(Json(req): Json<GenerateKeyRequest>) -> impl IntoRespo
 }
 
 pub async fn rotate_key(Json(req): Json<RotateKeyRequest>) -> impl IntoResponse {
-    match crate::commands::key_rotate::execute(&req.name, req.archive, req.pqc, req.sign) {
+    match crate::commands::key_rotate::execute(
+        &req.name,
+        req.archive,
+        None,
+        req.pqc,
+        req.sign,
+        Default::default(),
+        Default::default(),
+    ) {
         Ok(_) => (
             StatusCode::OK,
             Json(ApiResponse::success("Key rotated successfully")),
@@ -317,9 +346,9 @@ pub async fn rotate_key(Json(req): Json<RotateKeyRequest>) -> impl IntoResponse
 }
 
 pub async fn list_archived_keys() -> impl IntoResponse {
-    let archive_dir = match dirs::home_dir() {
-        Some(dir) => dir.join(".hermes").join("keys").join("archive"),
-        None => {
+    let archive_dir = match crate::config::hermes_home() {
+        Ok(dir) => dir.join("keys").join("archive"),
+        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<Vec<ArchivedKeyInfo>>::error(
@@ -483,8 +512,20 @@ pub async fn decrypt_message(Json(req): Json<DecryptMessageRequest>) -> impl Int
     )
 }
 
+/// Encrypt a file sent as base64. Unlike `send-file`, this works on the whole file in
+/// memory; requests are capped at [`super::server::MAX_BODY_SIZE`], so larger files
+/// must go through the CLI.
 pub async fn encrypt_file(Json(req): Json<EncryptFileRequest>) -> impl IntoResponse {
-    use crate::crypto::encrypt::{encrypt_data, encrypt_data_multi};
+    use crate::crypto::Encryptor;
+    use std::io::Write;
+
+    fn stream_into(
+        mut encryptor: Encryptor<Vec<u8>>,
+        data: &[u8],
+    ) -> crate::error::Result<Vec<u8>> {
+        encryptor.write_all(data)?;
+        encryptor.finish()
+    }
 
     let file_data = match base64::Engine::decode(
         &base64::engine::general_purpose::STANDARD,
@@ -499,26 +540,20 @@ pub async fn encrypt_file(Json(req): Json<EncryptFileRequest>) -> impl IntoRespo
         }
     };
 
-    let encrypted = if let Some(pwd) = req.password {
-        match encrypt_data(&file_data, &pwd, None, None) {
-            Ok(data) => data,
-            Err(e) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ApiResponse::<EncryptFileResponse>::error(e.to_string())),
-                )
-            }
-        }
+    // Files use the segmented stream format, which has no 4 GiB ciphertext limit
+    let result = if let Some(pwd) = req.password {
+        Encryptor::with_password(Vec::new(), &pwd, None, file_data.len() as u64, None)
+            .and_then(|encryptor| stream_into(encryptor, &file_data))
     } else if let Some(recipients) = req.recipients {
-        match encrypt_data_multi(&file_data, None, None, None, Some(recipients), req.pqc) {
-            Ok(data) => data,
-            Err(e) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ApiResponse::<EncryptFileResponse>::error(e.to_string())),
-                )
-            }
-        }
+        Encryptor::with_recipients(
+            Vec::new(),
+            recipients,
+            req.pqc,
+            None,
+            file_data.len() as u64,
+            None,
+        )
+        .and_then(|encryptor| stream_into(encryptor, &file_data))
     } else {
         return (
             StatusCode::BAD_REQUEST,
@@ -528,6 +563,16 @@ pub async fn encrypt_file(Json(req): Json<EncryptFileRequest>) -> impl IntoRespo
         );
     };
 
+    let encrypted = match result {
+        Ok(data) => data,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<EncryptFileResponse>::error(e.to_string())),
+            )
+        }
+    };
+
     let size = encrypted.len();
     let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &encrypted);
 
@@ -609,9 +654,9 @@ pub async fn sign_data(Json(req): Json<SignRequest>) -> impl IntoResponse {
             }
         };
 
-    let keys_dir = match dirs::home_dir() {
-        Some(dir) => dir.join(".hermes").join("keys"),
-        None => {
+    let keys_dir = match crate::config::hermes_home() {
+        Ok(dir) => dir.join("keys"),
+        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<SignResponse>::error(
@@ -632,7 +677,11 @@ pub async fn sign_data(Json(req): Json<SignRequest>) -> impl IntoResponse {
         );
     }
 
-    let secret_key = match crypto::load_dilithium_secret_key(key_path.to_str().unwrap()) {
+    let passphrase = std::env::var(crypto::keystore::PASSPHRASE_ENV).ok();
+    let secret_key = match crypto::load_dilithium_secret_key(
+        key_path.to_str().unwrap(),
+        passphrase.as_deref(),
+    ) {
         Ok(k) => k,
         Err(e) => {
             return (
@@ -642,7 +691,15 @@ pub async fn sign_data(Json(req): Json<SignRequest>) -> impl IntoResponse {
         }
     };
 
-    let signed = crypto::sign_message(&data, &secret_key);
+    let signed = match crypto::sign_message(&data, &secret_key) {
+        Ok(s) => s,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<SignResponse>::error(e.to_string())),
+            )
+        }
+    };
     let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &signed);
 
     (
@@ -663,9 +720,9 @@ pub async fn verify_signature(Json(req): Json<VerifyRequest>) -> impl IntoRespon
             }
         };
 
-    let recipients_dir = match dirs::home_dir() {
-        Some(dir) => dir.join(".hermes").join("recipients"),
-        None => {
+    let recipients_dir = match crate::config::hermes_home() {
+        Ok(dir) => dir.join("recipients"),
+        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<VerifyResponse>::error(
@@ -675,9 +732,9 @@ pub async fn verify_signature(Json(req): Json<VerifyRequest>) -> impl IntoRespon
         }
     };
 
-    let keys_dir = match dirs::home_dir() {
-        Some(dir) => dir.join(".hermes").join("keys"),
-        None => {
+    let keys_dir = match crate::config::hermes_home() {
+        Ok(dir) => dir.join("keys"),
+        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<VerifyResponse>::error(
@@ -777,9 +834,9 @@ pub async fn stego_capacity(Json(req): Json<StegoCapacityRequest>) -> impl IntoR
 }
 
 pub async fn get_config() -> impl IntoResponse {
-    let home = match dirs::home_dir() {
-        Some(dir) => dir.join(".hermes"),
-        None => {
+    let home = match crate::config::hermes_home() {
+        Ok(dir) => dir,
+        Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ApiResponse::<ConfigInfo>::error(
@@ -795,7 +852,10 @@ pub async fn get_config() -> impl IntoResponse {
             home_dir: home.to_string_lossy().to_string(),
             keys_dir: home.join("keys").to_string_lossy().to_string(),
             recipients_dir: home.join("recipients").to_string_lossy().to_string(),
-            config_file: home.join("config.toml").to_string_lossy().to_string(),
+            config_file: crate::config::ConfigFile::path()
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            profile: crate::config::Settings::load().ok().map(|s| s.profile),
         })),
     )
 }
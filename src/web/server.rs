@@ -13,6 +13,10 @@ use super::api;
 
 const INDEX_HTML: &str = include_str!("../../static/index.html");
 
+/// Largest request body accepted. API requests carry whole files as base64 and are
+/// handled in memory, so this also caps the files the web UI can encrypt
+pub const MAX_BODY_SIZE: usize = 100 * 1024 * 1024;
+
 pub async fn start_server(port: u16) -> Result<()> {
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -37,7 +41,7 @@ pub async fn start_server(port: u16) -> Result<()> {
         .route("/api/stego/capacity", post(api::stego_capacity))
         .route("/api/config", get(api::get_config))
         .layer(cors)
-        .layer(DefaultBodyLimit::max(100 * 1024 * 1024)); // 100MB max
+        .layer(DefaultBodyLimit::max(MAX_BODY_SIZE));
 
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
     println!("Starting Hermes Web UI on http://{}", addr);
@@ -1 +1,1054 @@
+use hermes::commands;
+use hermes::config::{HostKeyPolicy, PathsConfig, Settings, SftpConfig, TransportKind};
+use hermes::error::{HermesError, Result};
+use hermes::transfer::{LocalDirTransport, RemoteEntry, Transport};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
+/// Point HOME and the config directory at a temporary vault served by the
+/// local transport. Shared by every test in this binary.
+fn vault() -> &'static Path {
+    static VAULT: OnceLock<PathBuf> = OnceLock::new();
+
+    VAULT.get_or_init(|| {
+        let root = tempfile::tempdir().unwrap().keep();
+        std::env::set_var("HOME", &root);
+        std::env::set_var("XDG_CONFIG_HOME", root.join(".config"));
+
+        let dir = |name: &str| {
+            let path = root.join("vault").join(name);
+            fs::create_dir_all(&path).unwrap();
+            path.to_str().unwrap().to_string()
+        };
+
+        Settings {
+            profile: "default".to_string(),
+            transport: TransportKind::Local,
+            sftp: SftpConfig {
+                host: "unused".to_string(),
+                port: 22,
+                username: "unused".to_string(),
+                key_file: None,
+                host_key_policy: HostKeyPolicy::Strict,
+                auth_methods: Vec::new(),
+            },
+            paths: PathsConfig {
+                inbox: dir("inbox"),
+                outbox: dir("outbox"),
+                files: dir("files"),
+            },
+            notifiers: Vec::new(),
+        }
+        .save()
+        .unwrap();
+
+        root
+    })
+}
+
+/// Install a small RSA keypair as both our key and a known recipient
+fn install_recipient(name: &str) {
+    use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+
+    let hermes_dir = vault().join(".hermes");
+    fs::create_dir_all(hermes_dir.join("keys")).unwrap();
+    fs::create_dir_all(hermes_dir.join("recipients")).unwrap();
+
+    let key = rsa::RsaPrivateKey::new(&mut rand::rngs::OsRng, 1024).unwrap();
+    let public = rsa::RsaPublicKey::from(&key);
+    let private_pem = key.to_pkcs8_pem(LineEnding::LF).unwrap();
+    let public_pem = public.to_public_key_pem(LineEnding::LF).unwrap();
+
+    fs::write(hermes_dir.join(format!("keys/{name}.pem")), private_pem.as_bytes()).unwrap();
+    fs::write(hermes_dir.join(format!("keys/{name}.pub")), &public_pem).unwrap();
+    fs::write(hermes_dir.join(format!("recipients/{name}.pub")), &public_pem).unwrap();
+}
+
+fn write_input(name: &str, data: &[u8]) -> String {
+    let path = vault().join(name);
+    fs::write(&path, data).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_send_recv_file_roundtrip() {
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i * 31 % 251) as u8).collect();
+    let input = write_input("report.bin", &data);
+    let remote = vault().join("vault/files/report.enc");
+    let output = vault().join("report.out");
+
+    commands::send_file::execute(
+        &input,
+        Some("correct horse"),
+        Some(remote.to_str().unwrap()),
+        Some(24),
+        None,
+        None,
+        false,
+        None,
+        false,
+        false,
+    )
+    .unwrap();
+    assert!(remote.exists());
+
+    commands::recv_file::execute(
+        "report.enc",
+        Some("correct horse"),
+        Some(output.to_str().unwrap()),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(fs::read(&output).unwrap(), data);
+}
+
+#[test]
+fn test_recv_file_wrong_password_leaves_no_output() {
+    let input = write_input("notes.txt", b"meet at the usual place");
+    let remote = vault().join("vault/files/notes.enc");
+    let output = vault().join("notes.out");
+
+    commands::send_file::execute(
+        &input,
+        Some("right password"),
+        Some(remote.to_str().unwrap()),
+        None,
+        None,
+        None,
+        false,
+        None,
+        false,
+        false,
+    )
+    .unwrap();
+
+    let result = commands::recv_file::execute(
+        "notes.enc",
+        Some("wrong password"),
+        Some(output.to_str().unwrap()),
+        None,
+        None,
+    );
+    assert!(result.is_err());
+    assert!(!output.exists());
+}
+
+#[test]
+fn test_failed_send_leaves_nothing_at_the_remote_path() {
+    let input = write_input("unsent.txt", b"never arrives");
+    let remote = vault().join("vault/files/unsent.enc");
+
+    // No key for this recipient: the send fails after the remote file was opened
+    assert!(commands::send_file::execute(
+        &input,
+        None,
+        Some(remote.to_str().unwrap()),
+        None,
+        Some(vec!["nobody".to_string()]),
+        None,
+        false,
+        None,
+        false,
+        false,
+    )
+    .is_err());
+    assert!(!remote.exists());
+    assert!(!vault().join("vault/files/unsent.enc.part").exists());
+}
+
+#[test]
+fn test_list_reads_remote_headers() {
+    let input = write_input("listed.txt", b"inventory");
+    let remote = vault().join("vault/files/listed.enc");
+
+    commands::send_file::execute(
+        &input,
+        Some("pw"),
+        Some(remote.to_str().unwrap()),
+        Some(1),
+        None,
+        None,
+        false,
+        None,
+        false,
+        false,
+    )
+    .unwrap();
+
+    let header = hermes::transfer::read_package_header(
+        &hermes::transfer::LocalDirTransport::new(),
+        remote.to_str().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(header.filename.as_deref(), Some("listed.txt"));
+    assert!(header.expires_at > 0 && !header.is_expired());
+
+    let filter = commands::list::ListFilter {
+        expired_only: true,
+        recipient: None,
+    };
+    commands::list::execute(commands::list::SortKey::Expires, &filter).unwrap();
+}
+
+#[test]
+fn test_sweep_deletes_only_expired_packages() {
+    let files = vault().join("vault/inbox");
+    let fresh = files.join("fresh.enc");
+    let stale = files.join("stale.enc");
+
+    let encrypted =
+        hermes::crypto::encrypt_data(b"short-lived", "pw", Some("s.txt".to_string()), Some(1))
+            .unwrap();
+    fs::write(&fresh, &encrypted).unwrap();
+
+    let mut package = hermes::crypto::EncryptedPackage::from_bytes(&encrypted).unwrap();
+    package.expires_at = 1;
+    fs::write(&stale, package.to_bytes()).unwrap();
+
+    let config = Settings::load().unwrap();
+    let transport = hermes::transfer::LocalDirTransport::new();
+
+    let swept = commands::sweep::sweep(&transport, &config, true).unwrap();
+    assert_eq!(swept.len(), 1);
+    assert!(stale.exists());
+
+    let swept = commands::sweep::sweep(&transport, &config, false).unwrap();
+    assert_eq!(swept.len(), 1);
+    assert_eq!(swept[0].filename.as_deref(), Some("s.txt"));
+    assert!(!stale.exists());
+    assert!(fresh.exists());
+
+    let log = fs::read_to_string(commands::sweep::get_sweep_log_path().unwrap()).unwrap();
+    assert!(log.contains("stale.enc"));
+}
+
+#[test]
+fn test_sweep_fails_when_a_directory_cannot_be_listed() {
+    let mut config = Settings::load().unwrap();
+    config.paths.files = vault().join("vault/missing").to_str().unwrap().to_string();
+    let transport = hermes::transfer::LocalDirTransport::new();
+
+    assert!(commands::sweep::sweep(&transport, &config, true).is_err());
+}
+
+/// Local transport that can't delete files whose name contains "stuck"
+struct StuckDeletes(LocalDirTransport);
+
+impl Transport for StuckDeletes {
+    fn upload(&self, data: &[u8], remote_path: &str) -> Result<()> {
+        self.0.upload(data, remote_path)
+    }
+    fn download(&self, remote_path: &str) -> Result<Vec<u8>> {
+        self.0.download(remote_path)
+    }
+    fn list(&self, remote_dir: &str) -> Result<Vec<RemoteEntry>> {
+        self.0.list(remote_dir)
+    }
+    fn delete(&self, remote_path: &str) -> Result<()> {
+        if remote_path.contains("stuck") {
+            return Err(HermesError::SftpOperationFailed(
+                "Delete failed: permission denied".to_string(),
+            ));
+        }
+        self.0.delete(remote_path)
+    }
+    fn overwrite(&self, remote_path: &str, data: &[u8]) -> Result<()> {
+        self.0.overwrite(remote_path, data)
+    }
+    fn stat(&self, remote_path: &str) -> Result<RemoteEntry> {
+        self.0.stat(remote_path)
+    }
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.0.rename(from, to)
+    }
+    fn create_file(&self, remote_path: &str) -> Result<Box<dyn std::io::Write>> {
+        self.0.create_file(remote_path)
+    }
+    fn open_file(&self, remote_path: &str) -> Result<(Box<dyn std::io::Read>, u64)> {
+        self.0.open_file(remote_path)
+    }
+    fn write_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn std::io::Write>> {
+        self.0.write_at(remote_path, offset)
+    }
+    fn read_at(&self, remote_path: &str, offset: u64) -> Result<Box<dyn std::io::Read>> {
+        self.0.read_at(remote_path, offset)
+    }
+}
+
+#[test]
+fn test_sweep_continues_past_packages_it_cannot_delete() {
+    // Directories of their own, so other tests' packages aren't swept
+    let root = vault().join("sweep_failures");
+    let mut config = Settings::load().unwrap();
+    for (dir, path) in [
+        ("outbox", &mut config.paths.outbox),
+        ("inbox", &mut config.paths.inbox),
+        ("files", &mut config.paths.files),
+    ] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+        *path = root.join(dir).to_str().unwrap().to_string();
+    }
+
+    let encrypted = hermes::crypto::encrypt_data(b"expired", "pw", None, None).unwrap();
+    let mut package = hermes::crypto::EncryptedPackage::from_bytes(&encrypted).unwrap();
+    package.expires_at = 1;
+    let stuck = root.join("outbox/stuck.enc");
+    let later = [root.join("outbox/later.enc"), root.join("files/later.enc")];
+    for path in later.iter().chain([&stuck]) {
+        fs::write(path, package.to_bytes()).unwrap();
+    }
+
+    let transport = StuckDeletes(LocalDirTransport::new());
+    let swept = commands::sweep::sweep(&transport, &config, false).unwrap();
+
+    assert_eq!(swept.len(), 3);
+    let failed: Vec<_> = swept.iter().filter(|p| p.error.is_some()).collect();
+    assert_eq!(failed.len(), 1);
+    assert!(failed[0].path.ends_with("stuck.enc"));
+    assert!(later.iter().all(|path| !path.exists()));
+}
+
+#[test]
+fn test_dms_run_fires_expired_switch_once() {
+    use hermes::dms::{DeadManSwitch, DmsRegistry};
+
+    let target = vault().join("vault/outbox/dms.enc");
+    let encrypted = hermes::crypto::encrypt_data(b"if I go quiet", "pw", None, None).unwrap();
+    fs::write(&target, encrypted).unwrap();
+    let target = target.to_str().unwrap().to_string();
+
+    let mut switch = DeadManSwitch::new(target.clone(), 4);
+    switch.last_checkin -= chrono::Duration::hours(5);
+    let mut registry = DmsRegistry::new();
+    registry.add(switch);
+
+    let transport = hermes::transfer::LocalDirTransport::new();
+    let config = Settings::load().unwrap();
+    let events = commands::dms_run::run_switches(&mut registry, &transport, &config);
+    assert_eq!(
+        events,
+        vec![commands::dms_run::DmsEvent::Deleted {
+            file_path: target.clone()
+        }]
+    );
+    assert!(!Path::new(&target).exists());
+    assert!(registry.get(&target).unwrap().has_fired());
+
+    // State survives a reload and the switch is not acted on again
+    let registry_path = vault().join("dms_test_registry.json");
+    registry.save_to_file(&registry_path).unwrap();
+    let mut registry = DmsRegistry::load_from_file(&registry_path).unwrap();
+    assert!(commands::dms_run::run_switches(&mut registry, &transport, &config).is_empty());
+}
+
+#[test]
+fn test_dms_run_only_fires_switches_of_the_connected_profile() {
+    use hermes::dms::{DeadManSwitch, DmsRegistry};
+
+    let target = vault().join("vault/outbox/dms_work.enc");
+    fs::write(&target, b"on another server").unwrap();
+    let target = target.to_str().unwrap().to_string();
+
+    let mut switch = DeadManSwitch::new(target.clone(), 4).with_profile("work".to_string());
+    switch.last_checkin -= chrono::Duration::hours(5);
+    let mut registry = DmsRegistry::new();
+    registry.add(switch);
+
+    let transport = hermes::transfer::LocalDirTransport::new();
+    let config = Settings::load().unwrap();
+    assert!(commands::dms_run::run_switches(&mut registry, &transport, &config).is_empty());
+    assert!(Path::new(&target).exists());
+    assert!(!registry.get(&target).unwrap().has_fired());
+
+    // Switches saved before profiles were recorded belong to "default"
+    let legacy: DeadManSwitch = serde_json::from_value(serde_json::json!({
+        "file_path": target,
+        "timeout_hours": 4,
+        "last_checkin": "2025-01-25T12:00:00Z",
+        "created_at": "2025-01-25T12:00:00Z",
+        "enabled": true,
+        "grace_period_hours": 1,
+        "notification_email": null,
+    }))
+    .unwrap();
+    assert_eq!(legacy.profile, "default");
+}
+
+#[test]
+fn test_dms_release_publishes_envelope_for_recipient() {
+    use hermes::dms::{DmsAction, DmsOptions, DmsRegistry};
+
+    install_recipient("executor");
+
+    let data = b"the combination is 12-34-56".to_vec();
+    let input = write_input("will.txt", &data);
+    let remote = vault().join("vault/files/will.enc");
+    let output = vault().join("will.out");
+    let remote_str = remote.to_str().unwrap().to_string();
+
+    commands::send_file::execute(
+        &input,
+        Some("only I know this"),
+        Some(&remote_str),
+        None,
+        None,
+        Some(DmsOptions {
+            timeout_hours: 4,
+            release_to: Some(vec!["executor".to_string()]),
+            notify_only: false,
+            ..Default::default()
+        }),
+        false,
+        None,
+        false,
+        false,
+    )
+    .unwrap();
+
+    // The sender stops checking in
+    let registry_path = hermes::dms::get_registry_path().unwrap();
+    let full_registry = DmsRegistry::load_from_file(&registry_path).unwrap();
+    let mut switch = full_registry.get(&remote_str).unwrap().clone();
+    assert!(matches!(switch.action, DmsAction::Release { .. }));
+    switch.last_checkin -= chrono::Duration::hours(5);
+    let mut registry = DmsRegistry::new();
+    registry.add(switch);
+
+    let inbox = vault().join("vault/inbox");
+    let events = commands::dms_run::run_switches(
+        &mut registry,
+        &hermes::transfer::LocalDirTransport::new(),
+        &Settings::load().unwrap(),
+    );
+    assert_eq!(events.len(), 1);
+    assert!(remote.exists());
+    assert!(inbox.join("will_release.enc").exists());
+
+    commands::recv_file::execute(
+        "will.enc",
+        None,
+        Some(output.to_str().unwrap()),
+        Some("executor"),
+        Some("will_release.enc"),
+    )
+    .unwrap();
+    assert_eq!(fs::read(&output).unwrap(), data);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dms_run_notifies_through_switch_hook() {
+    use hermes::dms::{DeadManSwitch, DmsRegistry};
+    use hermes::notify::NotifierConfig;
+
+    let log = vault().join("dms_hook.log");
+    let hook = NotifierConfig::Command {
+        command: format!("echo \"$HERMES_DMS_EVENT\" >> {}", log.display()),
+    };
+
+    // Inside the grace period, nothing has been sent yet
+    let mut switch = DeadManSwitch::new("/vault/outbox/hooked.enc".to_string(), 8)
+        .with_notifications(None, vec![hook]);
+    switch.last_checkin -= chrono::Duration::hours(7);
+    let mut registry = DmsRegistry::new();
+    registry.add(switch);
+
+    let transport = hermes::transfer::LocalDirTransport::new();
+    let config = Settings::load().unwrap();
+
+    let events = commands::dms_run::run_switches(&mut registry, &transport, &config);
+    assert_eq!(
+        events,
+        vec![commands::dms_run::DmsEvent::Warned {
+            file_path: "/vault/outbox/hooked.enc".to_string()
+        }]
+    );
+    assert_eq!(fs::read_to_string(&log).unwrap(), "warning\n");
+
+    // Warned once per check-in cycle
+    assert!(commands::dms_run::run_switches(&mut registry, &transport, &config).is_empty());
+    assert_eq!(fs::read_to_string(&log).unwrap(), "warning\n");
+}
+
+#[test]
+fn test_dms_email_uses_configured_smtp_next_to_switch_channels() {
+    use hermes::dms::DeadManSwitch;
+    use hermes::notify::NotifierConfig;
+
+    let smtp = NotifierConfig::Smtp {
+        host: "smtp.example.com".to_string(),
+        port: 587,
+        username: None,
+        password: None,
+        from: "hermes@example.com".to_string(),
+        to: Vec::new(),
+        starttls: true,
+    };
+    let global = [
+        smtp.clone(),
+        NotifierConfig::Webhook {
+            url: "https://hooks.example.com/global".to_string(),
+        },
+    ];
+    let own = NotifierConfig::Webhook {
+        url: "https://hooks.example.com/switch".to_string(),
+    };
+    let kinds = |channels: Vec<NotifierConfig>| -> Vec<bool> {
+        channels.iter().map(NotifierConfig::is_smtp).collect()
+    };
+
+    // The switch's own channels replace the global ones...
+    let switch = DeadManSwitch::new("/vault/files/mail.enc".to_string(), 8)
+        .with_notifications(None, vec![own.clone()]);
+    assert_eq!(kinds(switch.notifiers(&global)), [false]);
+
+    // ...but an email still goes out through the configured SMTP relay
+    let switch = switch.with_notifications(Some("me@example.com".to_string()), vec![own]);
+    assert_eq!(kinds(switch.notifiers(&global)), [false, true]);
+}
+
+#[test]
+fn test_send_recv_dir_archive_roundtrip() {
+    let src = vault().join("photos");
+    fs::create_dir_all(src.join("2024/summer")).unwrap();
+    fs::write(src.join("index.txt"), b"album").unwrap();
+    fs::write(src.join("2024/summer/beach.raw"), vec![7u8; 100_000]).unwrap();
+
+    commands::send_dir::execute(
+        src.to_str().unwrap(),
+        Some("correct horse"),
+        None,
+        None,
+        false,
+        true,
+    )
+    .unwrap();
+
+    let remote = fs::read_dir(vault().join("vault/files"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("photos_") && name.ends_with(".tar.enc")
+        })
+        .expect("archive uploaded");
+
+    let output = vault().join("photos.out");
+    commands::recv_dir::execute(
+        remote.to_str().unwrap(),
+        Some("correct horse"),
+        Some(output.to_str().unwrap()),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(fs::read(output.join("index.txt")).unwrap(), b"album");
+    assert_eq!(
+        fs::read(output.join("2024/summer/beach.raw")).unwrap(),
+        vec![7u8; 100_000]
+    );
+
+    // A wrong password must not leave a half-extracted directory behind
+    let rejected = vault().join("photos.rejected");
+    assert!(commands::recv_dir::execute(
+        remote.to_str().unwrap(),
+        Some("wrong password"),
+        Some(rejected.to_str().unwrap()),
+        None,
+    )
+    .is_err());
+    assert!(!rejected.exists());
+}
+
+/// Give `name` an ML-DSA signing key, known to us as a sender
+fn install_signing_key(name: &str) {
+    let hermes_dir = vault().join(".hermes");
+    fs::create_dir_all(hermes_dir.join("keys")).unwrap();
+
+    let (public, secret) =
+        hermes::crypto::generate_dilithium_keypair(Default::default()).unwrap();
+    hermes::crypto::save_dilithium_secret_key(
+        &secret,
+        &hermes_dir.join(format!("keys/{name}_dilithium.pem")),
+        None,
+    )
+    .unwrap();
+    hermes::crypto::save_dilithium_public_key(
+        &public,
+        &hermes_dir.join(format!("keys/{name}_dilithium.pub")),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_signed_file_verifies_sender() {
+    install_recipient("signer");
+    install_signing_key("signer");
+    let input = write_input("contract.txt", b"terms and conditions");
+    let remote = vault().join("vault/files/contract.enc");
+    let output = vault().join("contract.out");
+
+    commands::send_file::execute(
+        &input,
+        Some("pw"),
+        Some(remote.to_str().unwrap()),
+        None,
+        None,
+        None,
+        false,
+        Some("signer"),
+        false,
+        false,
+    )
+    .unwrap();
+
+    let header = hermes::transfer::read_package_header(
+        &hermes::transfer::LocalDirTransport::new(),
+        remote.to_str().unwrap(),
+    )
+    .unwrap();
+    assert!(header.is_signed());
+
+    // Signature and content are checked on every decryption path
+    let encrypted = fs::read(&remote).unwrap();
+    assert_eq!(
+        hermes::crypto::decrypt_data(&encrypted, "pw").unwrap(),
+        b"terms and conditions"
+    );
+
+    let mut decryptor = hermes::crypto::Decryptor::new(&encrypted[..]).unwrap();
+    decryptor.unlock_with_password("pw").unwrap();
+    let mut content = Vec::new();
+    std::io::Read::read_to_end(&mut decryptor, &mut content).unwrap();
+    assert_eq!(decryptor.sender(), Some("signer"));
+
+    commands::recv_file::execute(
+        "contract.enc",
+        Some("pw"),
+        Some(output.to_str().unwrap()),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(fs::read(&output).unwrap(), b"terms and conditions");
+}
+
+#[test]
+fn test_signed_message_from_unknown_sender_is_rejected() {
+    install_signing_key("stranger");
+    let remote = vault().join("vault/outbox/stranger.enc");
+
+    commands::send_msg::execute(
+        "trust me",
+        Some("pw"),
+        Some(remote.to_str().unwrap()),
+        None,
+        None,
+        Some("stranger"),
+        false,
+        false,
+    )
+    .unwrap();
+    commands::recv_msg::execute(remote.to_str().unwrap(), Some("pw"), None).unwrap();
+
+    // Once we no longer know the signing key, the message can't be authenticated
+    let keys = vault().join(".hermes/keys");
+    fs::remove_file(keys.join("stranger_dilithium.pub")).unwrap();
+    assert!(commands::recv_msg::execute(remote.to_str().unwrap(), Some("pw"), None).is_err());
+}
+
+#[test]
+fn test_recipient_slots_use_key_ids() {
+    install_recipient("keyid_a");
+    install_recipient("keyid_b");
+    let input = write_input("ids.txt", b"for a and b");
+    let remote = vault().join("vault/files/ids.enc");
+    let output = vault().join("ids.out");
+
+    commands::send_file::execute(
+        &input,
+        None,
+        Some(remote.to_str().unwrap()),
+        None,
+        Some(vec!["keyid_a".to_string(), "keyid_b".to_string()]),
+        None,
+        false,
+        None,
+        false,
+        false,
+    )
+    .unwrap();
+
+    let header = hermes::transfer::read_package_header(
+        &hermes::transfer::LocalDirTransport::new(),
+        remote.to_str().unwrap(),
+    )
+    .unwrap();
+    assert!(header.has_key_ids());
+    assert_eq!(header.recipients.len(), 2);
+    assert!(header
+        .recipients
+        .iter()
+        .all(|r| !r.name.is_empty() && !r.name.starts_with("keyid_")));
+
+    // No --recipient: every local key is tried
+    commands::recv_file::execute(
+        remote.to_str().unwrap(),
+        None,
+        Some(output.to_str().unwrap()),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(fs::read(&output).unwrap(), b"for a and b");
+}
+
+#[test]
+fn test_anonymous_package_hides_recipients() {
+    install_recipient("hidden_a");
+    let input = write_input("hidden.txt", b"nobody knows");
+    let remote = vault().join("vault/files/hidden.enc");
+    let output = vault().join("hidden.out");
+
+    commands::send_file::execute(
+        &input,
+        None,
+        Some(remote.to_str().unwrap()),
+        None,
+        Some(vec!["hidden_a".to_string()]),
+        None,
+        false,
+        None,
+        true,
+        false,
+    )
+    .unwrap();
+
+    let header = hermes::transfer::read_package_header(
+        &hermes::transfer::LocalDirTransport::new(),
+        remote.to_str().unwrap(),
+    )
+    .unwrap();
+    assert!(header.is_anonymous());
+    assert!(!header.has_key_ids());
+    assert!(header.recipients.iter().all(|r| r.name.is_empty()));
+
+    commands::recv_file::execute(
+        remote.to_str().unwrap(),
+        None,
+        Some(output.to_str().unwrap()),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(fs::read(&output).unwrap(), b"nobody knows");
+
+    // A key that isn't a recipient can't open it
+    install_recipient("hidden_b");
+    let other = vault().join("hidden.other");
+    assert!(commands::recv_file::execute(
+        remote.to_str().unwrap(),
+        None,
+        Some(other.to_str().unwrap()),
+        Some("hidden_b"),
+        None,
+    )
+    .is_err());
+    assert!(!other.exists());
+}
+
+#[test]
+fn test_package_add_and_remove_recipient_keeps_payload() {
+    install_recipient("pkg_owner");
+    install_recipient("pkg_new");
+    let input = write_input("handover.txt", b"archived project notes");
+    let remote = vault().join("vault/files/handover.enc");
+    let output = vault().join("handover.out");
+
+    commands::send_file::execute(
+        &input,
+        None,
+        Some(remote.to_str().unwrap()),
+        None,
+        Some(vec!["pkg_owner".to_string()]),
+        None,
+        false,
+        None,
+        false,
+        true,
+    )
+    .unwrap();
+
+    // Recipient count and everything after the header
+    let split = |path: &Path| {
+        let bytes = fs::read(path).unwrap();
+        let header = hermes::crypto::EncryptedPackage::read_header(&mut &bytes[..]).unwrap();
+        let payload = bytes[header.header_bytes().len()..].to_vec();
+        (header.recipients.len(), payload)
+    };
+    let (_, original_payload) = split(&remote);
+
+    commands::package::add_recipient(
+        remote.to_str().unwrap(),
+        &["pkg_new".to_string()],
+        Some("pkg_owner"),
+        false,
+    )
+    .unwrap();
+    assert_eq!(split(&remote), (2, original_payload.clone()));
+
+    commands::recv_file::execute(
+        remote.to_str().unwrap(),
+        None,
+        Some(output.to_str().unwrap()),
+        Some("pkg_new"),
+        None,
+    )
+    .unwrap();
+    assert_eq!(fs::read(&output).unwrap(), b"archived project notes");
+
+    // A local copy can be edited too
+    let local_copy = vault().join("handover_copy.enc");
+    fs::copy(&remote, &local_copy).unwrap();
+    commands::package::remove_recipient(
+        local_copy.to_str().unwrap(),
+        &["pkg_new".to_string()],
+        true,
+    )
+    .unwrap();
+    assert_eq!(split(&local_copy), (1, original_payload));
+
+    let encrypted = fs::read(&local_copy).unwrap();
+    let mut decryptor = hermes::crypto::Decryptor::new(&encrypted[..]).unwrap();
+    assert!(decryptor.unlock_with_recipient("pkg_new").is_err());
+    let mut decryptor = hermes::crypto::Decryptor::new(&encrypted[..]).unwrap();
+    decryptor.unlock_with_recipient("pkg_owner").unwrap();
+
+    // The last recipient can't be removed, and nobody can be added twice
+    assert!(commands::package::remove_recipient(
+        local_copy.to_str().unwrap(),
+        &["pkg_owner".to_string()],
+        true,
+    )
+    .is_err());
+    assert!(commands::package::add_recipient(
+        local_copy.to_str().unwrap(),
+        &["pkg_owner".to_string()],
+        None,
+        true,
+    )
+    .is_err());
+
+    // Password packages have no recipients to change
+    let password_package = vault().join("password.enc");
+    fs::write(
+        &password_package,
+        hermes::crypto::encrypt_data(b"pw only", "pw", None, None).unwrap(),
+    )
+    .unwrap();
+    assert!(commands::package::add_recipient(
+        password_package.to_str().unwrap(),
+        &["pkg_new".to_string()],
+        None,
+        true,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_default_package_authenticates_recipient_list() {
+    install_recipient("table_a");
+    install_recipient("table_b");
+    let input = write_input("table.txt", b"fixed audience");
+    let remote = vault().join("vault/files/table.enc");
+
+    commands::send_file::execute(
+        &input,
+        None,
+        Some(remote.to_str().unwrap()),
+        None,
+        Some(vec!["table_a".to_string(), "table_b".to_string()]),
+        None,
+        false,
+        None,
+        false,
+        false,
+    )
+    .unwrap();
+
+    let encrypted = fs::read(&remote).unwrap();
+    let header = hermes::crypto::EncryptedPackage::read_header(&mut &encrypted[..]).unwrap();
+    assert!(!header.has_editable_recipients());
+    let payload = &encrypted[header.header_bytes().len()..];
+
+    // Dropping or reordering slots breaks authentication for every recipient
+    let mut dropped = header.clone();
+    dropped.recipients.pop();
+    let mut reordered = header.clone();
+    reordered.recipients.reverse();
+    for tampered in [dropped, reordered] {
+        let mut bytes = tampered.header_bytes();
+        bytes.extend_from_slice(payload);
+        let mut decryptor = hermes::crypto::Decryptor::new(&bytes[..]).unwrap();
+        let opened = decryptor.unlock_with_recipient("table_a").and_then(|()| {
+            std::io::Read::read_to_end(&mut decryptor, &mut Vec::new())?;
+            Ok(())
+        });
+        assert!(opened.is_err());
+    }
+
+    // Nor can the recipient list be edited in place
+    assert!(commands::package::remove_recipient(
+        remote.to_str().unwrap(),
+        &["table_b".to_string()],
+        true,
+    )
+    .is_err());
+}
+
+/// Give `name` an ML-KEM key next to its RSA key, shared as a contact
+fn install_pq_recipient(name: &str, kem: hermes::crypto::KemAlgorithm) {
+    install_recipient(name);
+    let hermes_dir = vault().join(".hermes");
+
+    let (public, secret) = hermes::crypto::generate_kyber_keypair(kem).unwrap();
+    hermes::crypto::save_kyber_secret_key(
+        &secret,
+        &hermes_dir.join(format!("keys/{name}_kyber.pem")),
+        None,
+    )
+    .unwrap();
+    for dir in ["keys", "recipients"] {
+        hermes::crypto::save_kyber_public_key(
+            &public,
+            &hermes_dir.join(format!("{dir}/{name}_kyber.pub")),
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_pqc_slots_record_each_recipients_kem() {
+    use hermes::crypto::KemAlgorithm;
+
+    install_pq_recipient("mlkem_768", KemAlgorithm::MlKem768);
+    install_pq_recipient("mlkem_1024", KemAlgorithm::MlKem1024);
+    let input = write_input("pq.txt", b"quantum-safe");
+    let remote = vault().join("vault/files/pq.enc");
+
+    commands::send_file::execute(
+        &input,
+        None,
+        Some(remote.to_str().unwrap()),
+        None,
+        Some(vec!["mlkem_768".to_string(), "mlkem_1024".to_string()]),
+        None,
+        true,
+        None,
+        false,
+        false,
+    )
+    .unwrap();
+
+    let header = hermes::transfer::read_package_header(
+        &hermes::transfer::LocalDirTransport::new(),
+        remote.to_str().unwrap(),
+    )
+    .unwrap();
+    assert!(header.is_pqc_enabled());
+    assert!(header.has_hybrid_kem());
+    let algorithms: Vec<_> = header.recipients.iter().map(|r| r.pq_algorithm).collect();
+    assert_eq!(
+        algorithms,
+        [KemAlgorithm::MlKem768, KemAlgorithm::MlKem1024]
+    );
+
+    for name in ["mlkem_768", "mlkem_1024"] {
+        let output = vault().join(format!("pq_{name}.out"));
+        commands::recv_file::execute(
+            remote.to_str().unwrap(),
+            None,
+            Some(output.to_str().unwrap()),
+            Some(name),
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"quantum-safe");
+    }
+}
+
+#[test]
+fn test_x25519_identity_encrypts_and_signs() {
+    use hermes::crypto::{ClassicalAlgorithm, KeyType};
+
+    // An X25519 identity with ML-KEM and ML-DSA keys, shared with ourselves
+    commands::keygen::execute(
+        "curve",
+        None,
+        KeyType::X25519,
+        true,
+        true,
+        false,
+        Default::default(),
+        Default::default(),
+    )
+    .unwrap();
+    let keys = vault().join(".hermes/keys");
+    for file in ["curve.pub", "curve_ed25519.pub"] {
+        commands::import_pubkey::execute("curve", keys.join(file).to_str().unwrap()).unwrap();
+    }
+    commands::import_kyber_pubkey::execute(
+        "curve",
+        keys.join("curve_kyber.pub").to_str().unwrap(),
+    )
+    .unwrap();
+    install_pq_recipient("classic", Default::default());
+
+    let input = write_input("curve.txt", b"modern keys");
+    let remote = vault().join("vault/files/curve.enc");
+    commands::send_file::execute(
+        &input,
+        None,
+        Some(remote.to_str().unwrap()),
+        None,
+        Some(vec!["curve".to_string(), "classic".to_string()]),
+        None,
+        true,
+        Some("curve"),
+        false,
+        false,
+    )
+    .unwrap();
+
+    // New slots never use PKCS#1 v1.5
+    let header = hermes::transfer::read_package_header(
+        &hermes::transfer::LocalDirTransport::new(),
+        remote.to_str().unwrap(),
+    )
+    .unwrap();
+    assert!(header.is_signed());
+    let algorithms: Vec<_> = header
+        .recipients
+        .iter()
+        .map(|r| r.classical_algorithm)
+        .collect();
+    assert_eq!(
+        algorithms,
+        [ClassicalAlgorithm::X25519, ClassicalAlgorithm::RsaOaep]
+    );
+
+    for name in ["curve", "classic"] {
+        let output = vault().join(format!("curve_{name}.out"));
+        commands::recv_file::execute(
+            remote.to_str().unwrap(),
+            None,
+            Some(output.to_str().unwrap()),
+            Some(name),
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"modern keys");
+    }
+}